@@ -38,8 +38,11 @@ export const PROMPT_SECTIONS = {
     base: ``,
     detail: `记忆管理使用细节：
 - 对话开始时查询 \`回忆\` 参数 \`project_path\` 为 git 的根目录
+- 处理具体任务时可以给 \`回忆\` 传入 \`query\`（任务关键词），只获取最相关的记忆
 - 当发现用户输入"请记住："时，要对用户的消息进行总结后调用 \`记忆\` 的 add 功能添加记忆
 - 使用 \`记忆\` 的 add 功能添加新记忆（content + category: rule/preference/pattern/context）
+- 规则过时或与新要求冲突时，先用 \`列表\` 获取记忆 ID，再用 \`更新\` 或 \`删除\` 修改原记忆，不要添加相互矛盾的记忆
+- 添加结果提示已有相似记忆或可能冲突时，根据返回的记忆 ID 使用 \`更新\` 或 \`删除\` 处理，不要重复添加
 - 仅在重要变更时更新记忆，保持简洁`,
   } as PromptSection,
 
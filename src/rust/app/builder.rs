@@ -23,6 +23,13 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             set_always_on_top,
             sync_window_state,
             reload_config,
+            get_config_diagnostics,
+
+            // 配置方案命令
+            get_config_profiles,
+            save_config_profile,
+            delete_config_profile,
+            switch_config_profile,
 
             // 音频命令
             get_audio_notification_enabled,
@@ -42,6 +49,8 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             set_window_config,
             get_reply_config,
             set_reply_config,
+            get_network_config,
+            set_network_config,
             get_window_settings,
             set_window_settings,
             get_window_settings_for_mode,
@@ -1,50 +1,259 @@
-use crate::config::load_standalone_telegram_config;
+use crate::config::{
+    check_standalone_config, effective_config, list_profiles, load_project_config, load_standalone_config,
+    request_project_path, set_request_file, switch_standalone_profile, update_standalone_config,
+};
+use crate::config::env::set_config_field;
+use crate::mcp::handlers::create_tauri_popup;
+use crate::mcp::history::RequestHistory;
+use crate::mcp::tools::acemcp::types::AcemcpRequest;
+use crate::mcp::tools::memory::rule_files::{export_rule_file, import_rule_file, read_rule_items};
+use crate::mcp::tools::memory::{MemoryCategory, MemoryEntry, MemoryManager, MemoryScope, MemorySource, RuleFile};
+use crate::mcp::tools::AcemcpTool;
+use crate::mcp::types::PopupRequest;
+use crate::mcp::utils::generate_request_id;
 use crate::telegram::handle_telegram_only_mcp_request;
 use crate::log_important;
 use crate::app::builder::run_tauri_app;
+use crate::app::doctor::{run_doctor, DoctorArgs};
+use crate::constants::mcp;
+use crate::utils::auto_init_logger;
 use anyhow::Result;
+use clap::{ArgAction, Parser, Subcommand};
+
+/// 寸止 - 智能代码审查工具
+///
+/// 不带参数运行时启动设置界面
+#[derive(Debug, Parser)]
+#[command(name = "等一下", version, disable_version_flag = true)]
+struct Cli {
+    /// 显示版本信息
+    #[arg(short = 'v', long = "version", action = ArgAction::Version)]
+    version: Option<bool>,
+
+    /// 处理 MCP 请求（由 MCP 服务器调用）
+    #[arg(long, value_name = "文件", hide = true)]
+    mcp_request: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 查看和修改配置
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// 管理配置方案
+    Profile {
+        #[command(subcommand)]
+        action: ProfileCommand,
+    },
+    /// 管理项目记忆
+    Memory {
+        #[command(subcommand)]
+        action: MemoryCommand,
+    },
+    /// 搜索项目代码上下文（与 sou 工具相同）
+    Search {
+        /// 自然语言搜索查询
+        query: String,
+        /// 项目根目录
+        #[arg(long, value_name = "路径")]
+        project: String,
+    },
+    /// 弹出交互窗口，并输出 JSON 格式的回复
+    Popup {
+        /// 弹窗消息
+        #[arg(long)]
+        message: String,
+        /// 预定义选项，可以重复指定
+        #[arg(long = "option", value_name = "选项")]
+        options: Vec<String>,
+        /// 消息按纯文本显示，不渲染 Markdown
+        #[arg(long)]
+        plain: bool,
+        /// 请求所属的项目，用于合并项目配置
+        #[arg(long, value_name = "路径")]
+        project: Option<String>,
+    },
+    /// 查看 zhi 请求历史
+    History {
+        /// 显示的已回复请求数
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// 诊断安装和配置问题
+    Doctor(DoctorArgs),
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// 读取配置项，字段路径如 telegram_config.enabled，可以是整个分区
+    Get {
+        field: String,
+        /// 包含该项目的 .cunzhi/config.toml
+        #[arg(long, value_name = "路径")]
+        project: Option<String>,
+    },
+    /// 修改全局配置项，值按字段类型解析（列表可以用逗号分隔）
+    Set { field: String, value: String },
+    /// 列出生效的配置及每一项的来源
+    List {
+        /// 包含该项目的 .cunzhi/config.toml
+        #[arg(long, value_name = "路径")]
+        project: Option<String>,
+    },
+    /// 检查配置文件
+    Check,
+}
+
+#[derive(Debug, Subcommand)]
+enum ProfileCommand {
+    /// 列出配置方案
+    List,
+    /// 切换配置方案
+    Switch { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum MemoryCommand {
+    /// 添加记忆
+    Add {
+        content: String,
+        /// 分类：rule、preference、pattern、context
+        #[arg(long, default_value = "context")]
+        category: String,
+        /// 标签，可以重复指定
+        #[arg(long = "tag", value_name = "标签")]
+        tags: Vec<String>,
+        #[command(flatten)]
+        location: MemoryLocation,
+    },
+    /// 列出记忆
+    List {
+        /// 只显示该分类
+        #[arg(long)]
+        category: Option<String>,
+        #[command(flatten)]
+        location: MemoryLocation,
+    },
+    /// 按相关度搜索记忆
+    Search {
+        query: String,
+        /// 最多显示的条数
+        #[arg(long, default_value_t = mcp::DEFAULT_MEMORY_TOP_K)]
+        limit: usize,
+        #[command(flatten)]
+        location: MemoryLocation,
+    },
+    /// 删除记忆，可以使用记忆 ID（或其前缀）或内容
+    Remove {
+        target: String,
+        #[command(flatten)]
+        location: MemoryLocation,
+    },
+    /// 从其他 AI 工具的规则文件导入列表项
+    Import {
+        /// 规则文件：claude、agents、cursor、copilot、windsurf 或 all
+        #[arg(default_value = "all")]
+        file: String,
+        /// 只显示将导入的列表项，不修改记忆
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        location: MemoryLocation,
+    },
+    /// 将记忆导出到其他 AI 工具的规则文件（只修改寸止管理的区块）
+    Export {
+        /// 规则文件：claude、agents、cursor、copilot、windsurf
+        file: String,
+        /// 只输出将写入的内容，不修改文件
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        location: MemoryLocation,
+    },
+}
+
+/// 记忆所在的项目和作用域
+#[derive(Debug, clap::Args)]
+struct MemoryLocation {
+    /// 项目路径（git 仓库中的任意目录）
+    #[arg(long, value_name = "路径", default_value = ".")]
+    project: String,
+    /// 作用域：global、project、directory
+    #[arg(long, default_value = "project")]
+    scope: String,
+    /// 子目录路径（directory 作用域时必需）
+    #[arg(long, value_name = "路径")]
+    directory: Option<String>,
+}
+
+impl MemoryLocation {
+    fn open(&self) -> Result<MemoryManager> {
+        let scope = MemoryScope::from_name(&self.scope)
+            .ok_or_else(|| anyhow::anyhow!("未知的记忆作用域 {}，可选 global、project、directory", self.scope))?;
+        MemoryManager::open(scope, &self.project, self.directory.as_deref())
+    }
+}
 
 /// 处理命令行参数
 pub fn handle_cli_args() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+    let cli = Cli::parse();
 
-    match args.len() {
-        // 无参数：正常启动GUI
-        1 => {
-            run_tauri_app();
-        }
-        // 单参数：帮助或版本
-        2 => {
-            match args[1].as_str() {
-                "--help" | "-h" => print_help(),
-                "--version" | "-v" => print_version(),
-                _ => {
-                    eprintln!("未知参数: {}", args[1]);
-                    print_help();
-                    std::process::exit(1);
-                }
-            }
-        }
-        // 多参数：MCP请求模式
-        _ => {
-            if args[1] == "--mcp-request" && args.len() >= 3 {
-                handle_mcp_request(&args[2])?;
-            } else {
-                eprintln!("无效的命令行参数");
-                print_help();
-                std::process::exit(1);
-            }
-        }
+    // 请求文件只在这里解析一次，项目配置、日志和弹窗进程的判断都从这里读取
+    if let Some(request_file) = &cli.mcp_request {
+        set_request_file(request_file);
+    }
+
+    // 初始化日志系统（MCP 请求进程输出到文件）
+    if let Err(e) = auto_init_logger() {
+        eprintln!("初始化日志系统失败: {}", e);
+    }
+
+    if let Some(request_file) = &cli.mcp_request {
+        return handle_mcp_request(request_file);
     }
 
+    let Some(command) = cli.command else {
+        // 无参数：正常启动GUI
+        run_tauri_app();
+        return Ok(());
+    };
+
+    let result = match command {
+        Command::Config { action } => match action {
+            ConfigCommand::Get { field, project } => get_config(&field, project.as_deref()),
+            ConfigCommand::Set { field, value } => set_config(&field, &value),
+            ConfigCommand::List { project } => show_config(project.as_deref()),
+            ConfigCommand::Check => check_config(),
+        },
+        Command::Profile { action } => match action {
+            ProfileCommand::List => print_profiles(),
+            ProfileCommand::Switch { name } => switch_profile(&name),
+        },
+        Command::Memory { action } => handle_memory_command(action),
+        Command::Search { query, project } => search_context(query, project),
+        Command::Popup { message, options, plain, project } => show_popup(message, options, !plain, project),
+        Command::History { limit } => print_history(limit),
+        Command::Doctor(args) => doctor(&args),
+    };
+
+    if let Err(e) = result {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
     Ok(())
 }
 
 /// 处理MCP请求
 fn handle_mcp_request(request_file: &str) -> Result<()> {
-    // 检查Telegram配置，决定是否启用纯Telegram模式
-    match load_standalone_telegram_config() {
-        Ok(telegram_config) => {
+    // 检查Telegram配置（包含请求所属项目的配置），决定是否启用纯Telegram模式
+    match load_project_config(request_project_path()) {
+        Ok(config) => {
+            let telegram_config = config.telegram_config;
             if telegram_config.enabled && telegram_config.hide_frontend_popup {
                 // 纯Telegram模式：不启动GUI，直接处理
                 if let Err(e) = tokio::runtime::Runtime::new()
@@ -68,18 +277,276 @@ fn handle_mcp_request(request_file: &str) -> Result<()> {
     Ok(())
 }
 
-/// 显示帮助信息
-fn print_help() {
-    println!("寸止 - 智能代码审查工具");
-    println!();
-    println!("用法:");
-    println!("  等一下                    启动设置界面");
-    println!("  等一下 --mcp-request <文件>  处理 MCP 请求");
-    println!("  等一下 --help             显示此帮助信息");
-    println!("  等一下 --version          显示版本信息");
+/// 检查配置文件并输出诊断结果，有问题时以非零状态退出
+fn check_config() -> Result<()> {
+    let diagnostics = check_standalone_config().map_err(|e| anyhow::anyhow!("检查配置失败: {}", e))?;
+    if diagnostics.is_empty() {
+        println!("✅ 配置检查通过");
+        return Ok(());
+    }
+
+    println!("发现 {} 个配置问题:", diagnostics.len());
+    for diagnostic in &diagnostics {
+        println!();
+        println!("⚠️  {}", diagnostic);
+    }
+    std::process::exit(1);
+}
+
+/// 输出生效的配置项，字段是分区时输出分区内的所有项
+fn get_config(field: &str, project_path: Option<&str>) -> Result<()> {
+    let values = effective_config(project_path).map_err(|e| anyhow::anyhow!("加载配置失败: {}", e))?;
+    let prefix = format!("{}.", field);
+    let matched: Vec<_> = values
+        .iter()
+        .filter(|value| value.field == field || value.field.starts_with(&prefix))
+        .collect();
+
+    match matched.as_slice() {
+        [] => anyhow::bail!("未知的配置字段 {}", field),
+        [value] if value.field == field => println!("{}", value.value),
+        _ => {
+            for value in matched {
+                println!("{} = {}", value.field, value.value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 修改全局配置文件中的一项，运行中的 MCP 服务器在下次读取配置时生效
+fn set_config(field: &str, value: &str) -> Result<()> {
+    let field = update_standalone_config(|config| set_config_field(config, field, value))
+        .map_err(|e| anyhow::anyhow!("保存配置失败: {}", e))??;
+    println!("✅ 已设置 {}", field);
+
+    // 新值可能不合理（如超出范围），提示但不回滚
+    if let Ok(diagnostics) = check_standalone_config() {
+        for diagnostic in &diagnostics {
+            println!("⚠️  {}", diagnostic);
+        }
+    }
+    Ok(())
+}
+
+/// 输出生效的配置及每一项的来源（默认值、配置文件、项目配置或环境变量）
+fn show_config(project_path: Option<&str>) -> Result<()> {
+    let values = effective_config(project_path).map_err(|e| anyhow::anyhow!("加载配置失败: {}", e))?;
+
+    if let Some(project_path) = project_path {
+        println!("项目: {}", project_path);
+        println!();
+    }
+    for value in &values {
+        println!("{} = {}  [{}]", value.field, value.value, value.source);
+    }
+    Ok(())
+}
+
+/// 列出配置方案，当前方案前显示标记
+fn print_profiles() -> Result<()> {
+    let profiles = list_profiles().map_err(|e| anyhow::anyhow!("读取配置方案失败: {}", e))?;
+    if profiles.is_empty() {
+        println!("暂无配置方案，可以在设置界面的“配置方案”中保存当前设置");
+        return Ok(());
+    }
+
+    let active_profile = load_standalone_config()
+        .ok()
+        .and_then(|config| config.active_profile);
+    for name in &profiles {
+        let marker = if active_profile.as_deref() == Some(name.as_str()) { "*" } else { " " };
+        println!("{} {}", marker, name);
+    }
+    Ok(())
+}
+
+/// 切换配置方案，运行中的 MCP 服务器在下次读取配置时生效
+fn switch_profile(name: &str) -> Result<()> {
+    switch_standalone_profile(name).map_err(|e| anyhow::anyhow!("切换配置方案失败: {}", e))?;
+    println!("✅ 已切换到配置方案: {}", name);
+    Ok(())
+}
+
+fn handle_memory_command(action: MemoryCommand) -> Result<()> {
+    match action {
+        MemoryCommand::Add { content, category, tags, location } => {
+            let category = parse_category(&category)?;
+            let added = location.open()?.add_memory(&content, category, MemorySource::Cli, tags)?;
+            if added.merged {
+                println!("♻️ 已有相似的记忆，已合并而未新增，ID: {}", added.entry.id);
+                println!("   {}", added.entry.content);
+            } else {
+                println!("✅ 记忆已添加，ID: {}", added.entry.id);
+            }
+            for conflict in &added.conflicts {
+                println!("⚠️ 可能与已有记忆冲突，ID: {}", conflict.id);
+                println!("   {}", conflict.content);
+            }
+        }
+        MemoryCommand::List { category, location } => {
+            let manager = location.open()?;
+            let memories = match category {
+                Some(category) => manager.get_memories_by_category(parse_category(&category)?)?,
+                None => manager.get_all_memories()?,
+            };
+            print_memories(&memories);
+        }
+        MemoryCommand::Search { query, limit, location } => {
+            print_memories(&location.open()?.search_memories(&query, limit)?);
+        }
+        MemoryCommand::Remove { target, location } => {
+            let manager = location.open()?;
+            let entry = manager.remove_memory(&manager.find_memory(&target)?.id)?;
+            println!("✅ 已删除记忆: {}", entry.content);
+        }
+        MemoryCommand::Import { file, dry_run, location } => {
+            let files = match file.as_str() {
+                "all" => RuleFile::ALL.to_vec(),
+                name => vec![parse_rule_file(name)?],
+            };
+            import_rule_files(&location.open()?, &files, dry_run)?;
+        }
+        MemoryCommand::Export { file, dry_run, location } => {
+            let file = parse_rule_file(&file)?;
+            let manager = location.open()?;
+            let (count, content) = export_rule_file(&manager, file, dry_run)?;
+            if dry_run {
+                print!("{}", content);
+            } else {
+                println!("✅ 已导出 {} 条记忆到 {}", count, file.path_for(&manager)?.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_rule_file(name: &str) -> Result<RuleFile> {
+    RuleFile::from_name(name)
+        .ok_or_else(|| anyhow::anyhow!("未知的规则文件 {}，可选 claude、agents、cursor、copilot、windsurf", name))
+}
+
+/// 导入规则文件中的列表项，不存在的文件跳过
+fn import_rule_files(manager: &MemoryManager, files: &[RuleFile], dry_run: bool) -> Result<()> {
+    let mut found = false;
+    for &file in files {
+        let path = file.path_for(manager)?;
+        let Some(items) = read_rule_items(&path)? else {
+            continue;
+        };
+        found = true;
+
+        if dry_run {
+            println!("{}: {} 条列表项", path.display(), items.len());
+            for item in &items {
+                println!("  [{:?}] {}", item.category, item.content);
+            }
+            continue;
+        }
+
+        let summary = import_rule_file(manager, file, &items)?;
+        println!("✅ {}: 新增 {} 条，合并 {} 条相似记忆", path.display(), summary.added, summary.merged);
+        for (entry, conflicts) in &summary.conflicts {
+            for conflict in conflicts {
+                println!("⚠️ \"{}\" 可能与已有记忆冲突，ID: {}", entry.content, conflict.id);
+                println!("   {}", conflict.content);
+            }
+        }
+    }
+
+    if !found {
+        println!("📭 没有找到规则文件");
+    }
+    Ok(())
+}
+
+fn parse_category(name: &str) -> Result<MemoryCategory> {
+    MemoryCategory::from_name(name)
+        .ok_or_else(|| anyhow::anyhow!("未知的记忆分类 {}，可选 rule、preference、pattern、context", name))
+}
+
+fn print_memories(memories: &[MemoryEntry]) {
+    if memories.is_empty() {
+        println!("📭 暂无匹配的记忆");
+        return;
+    }
+    for memory in memories {
+        let tags: String = memory.tags.iter().map(|tag| format!(" #{}", tag)).collect();
+        println!("{}  [{:?}] {}{}", &memory.id[..8.min(memory.id.len())], memory.category, memory.content, tags);
+    }
+}
+
+/// 执行与 sou 工具相同的索引和搜索，输出搜索结果
+fn search_context(query: String, project_root_path: String) -> Result<()> {
+    let request = AcemcpRequest { project_root_path, query };
+    let result = tokio::runtime::Runtime::new()?
+        .block_on(AcemcpTool::search_context(request))
+        .map_err(|e| anyhow::anyhow!("搜索失败: {}", e))?;
+
+    let value = serde_json::to_value(&result)?;
+    if let Some(items) = value["content"].as_array() {
+        for item in items {
+            if let Some(text) = item["text"].as_str() {
+                println!("{}", text);
+            }
+        }
+    }
+    if result.is_error == Some(true) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// 弹出交互窗口并输出回复（与 zhi 工具的弹窗相同）
+fn show_popup(message: String, options: Vec<String>, is_markdown: bool, project_path: Option<String>) -> Result<()> {
+    let request = PopupRequest {
+        id: generate_request_id(),
+        message,
+        predefined_options: if options.is_empty() { None } else { Some(options) },
+        is_markdown,
+        project_path,
+    };
+
+    let response = create_tauri_popup(&request).map_err(|e| anyhow::anyhow!("弹窗失败: {}", e))?;
+    println!("{}", response);
+    Ok(())
+}
+
+/// 运行诊断，有检查项失败时以非零状态退出
+fn doctor(args: &DoctorArgs) -> Result<()> {
+    if !tokio::runtime::Runtime::new()?.block_on(run_doctor(args)) {
+        std::process::exit(1);
+    }
+    Ok(())
 }
 
-/// 显示版本信息
-fn print_version() {
-    println!("寸止 v{}", env!("CARGO_PKG_VERSION"));
+/// 输出等待回复和最近已回复的 zhi 请求
+fn print_history(limit: usize) -> Result<()> {
+    let history = RequestHistory::open().map_err(|e| anyhow::anyhow!("读取请求历史失败: {}", e))?;
+
+    let pending = history.pending()?;
+    if !pending.is_empty() {
+        println!("等待回复:");
+        for record in &pending {
+            println!("  [{}] {}  {}", record.created_at.format("%Y-%m-%d %H:%M"), record.id, record.message);
+        }
+        println!();
+    }
+
+    let answers = history.recent_answers(limit)?;
+    if answers.is_empty() && pending.is_empty() {
+        println!("📭 暂无请求记录");
+        return Ok(());
+    }
+    if !answers.is_empty() {
+        println!("最近回复:");
+        for record in &answers {
+            let answered_at = record.answered_at.unwrap_or(record.created_at);
+            println!("  [{}] {}", answered_at.format("%Y-%m-%d %H:%M"), record.message);
+            if let Some(answer) = &record.answer {
+                println!("    → {}", answer);
+            }
+        }
+    }
+    Ok(())
 }
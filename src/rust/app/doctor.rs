@@ -0,0 +1,322 @@
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::process::{Command, Stdio};
+
+use crate::config::{check_standalone_config, load_project_config, AppConfig};
+use crate::constants::{app, mcp};
+use crate::mcp::handlers::{find_mcp_server_command, find_ui_command};
+use crate::mcp::tools::acemcp::commands::check_acemcp_connection;
+use crate::mcp::tools::memory::MemoryManager;
+use crate::telegram::core::test_telegram_connection_with_api_url;
+use crate::utils::mcp_log_file_path;
+
+/// 诊断命令参数，寸止和等一下共用
+#[derive(Debug, Clone, clap::Args)]
+pub struct DoctorArgs {
+    /// 以 JSON 格式输出诊断报告
+    #[arg(long)]
+    pub json: bool,
+    /// 检查该项目的配置和记忆目录，默认为当前目录
+    #[arg(long, value_name = "路径")]
+    pub project: Option<String>,
+}
+
+/// 单项检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    /// 功能未启用，未检查
+    Skip,
+}
+
+impl CheckStatus {
+    fn icon(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+            CheckStatus::Skip => "⏭️ ",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+/// 诊断报告
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub version: String,
+    pub checks: Vec<CheckResult>,
+}
+
+impl DoctorReport {
+    /// 是否没有失败的检查项
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.status != CheckStatus::Fail)
+    }
+
+    fn push(&mut self, name: &str, status: CheckStatus, message: impl Into<String>) {
+        self.checks.push(CheckResult {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+        });
+    }
+}
+
+/// 运行诊断并输出报告，返回是否全部通过
+pub async fn run_doctor(args: &DoctorArgs) -> bool {
+    let report = diagnose(args.project.as_deref()).await;
+
+    if args.json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("❌ 序列化诊断报告失败: {}", e),
+        }
+    } else {
+        print_report(&report);
+    }
+    report.passed()
+}
+
+/// 检查安装、配置和外部服务，定位“弹窗不出现”等常见问题
+pub async fn diagnose(project_path: Option<&str>) -> DoctorReport {
+    let mut report = DoctorReport {
+        version: app::VERSION.to_string(),
+        checks: Vec::new(),
+    };
+
+    check_binaries(&mut report);
+
+    let config = check_config(&mut report, project_path);
+    check_display(&mut report);
+    check_log_file(&mut report);
+
+    match &config {
+        Some(config) => {
+            check_telegram(&mut report, config).await;
+            check_acemcp(&mut report, config).await;
+        }
+        None => {
+            report.push("Telegram", CheckStatus::Skip, "配置无法加载，跳过");
+            report.push("代码搜索 (acemcp)", CheckStatus::Skip, "配置无法加载，跳过");
+        }
+    }
+
+    check_memory_dir(&mut report, project_path);
+
+    report
+}
+
+fn print_report(report: &DoctorReport) {
+    println!("{} 诊断报告 (v{})", app::NAME, report.version);
+    println!();
+    for check in &report.checks {
+        println!("{} {}: {}", check.status.icon(), check.name, check.message);
+    }
+    println!();
+
+    let failed = report.checks.iter().filter(|check| check.status == CheckStatus::Fail).count();
+    if failed == 0 {
+        println!("✅ 所有检查通过");
+    } else {
+        println!("❌ 发现 {} 个问题", failed);
+    }
+}
+
+/// 检查两个命令都能找到，且版本与当前程序一致
+fn check_binaries(report: &mut DoctorReport) {
+    for (name, found) in [("等一下", find_ui_command()), ("寸止", find_mcp_server_command())] {
+        let check_name = format!("{} 命令", name);
+        let path = match found {
+            Ok(path) => path,
+            Err(e) => {
+                report.push(&check_name, CheckStatus::Fail, e.to_string());
+                continue;
+            }
+        };
+
+        match command_version(&path) {
+            Some(version) if version == app::VERSION => {
+                report.push(&check_name, CheckStatus::Pass, format!("{} (v{})", path, version));
+            }
+            Some(version) => report.push(
+                &check_name,
+                CheckStatus::Fail,
+                format!("{} 的版本为 v{}，与当前的 v{} 不一致，请重新安装", path, version, app::VERSION),
+            ),
+            None => report.push(
+                &check_name,
+                CheckStatus::Warn,
+                format!("{} 无法获取版本，可能是旧版本", path),
+            ),
+        }
+    }
+}
+
+/// 执行 `<命令> --version`，取输出的最后一个词作为版本号
+fn command_version(path: &str) -> Option<String> {
+    let output = Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .last()
+        .map(|version| version.trim_start_matches('v').to_string())
+}
+
+/// 检查配置能否解析及校验结果，返回合并项目配置后的配置
+fn check_config(report: &mut DoctorReport, project_path: Option<&str>) -> Option<AppConfig> {
+    match check_standalone_config() {
+        Ok(diagnostics) if diagnostics.is_empty() => {
+            report.push("配置文件", CheckStatus::Pass, "解析和校验通过");
+        }
+        Ok(diagnostics) => {
+            let summary: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+            report.push(
+                "配置文件",
+                CheckStatus::Warn,
+                format!("发现 {} 个问题: {}", diagnostics.len(), summary.join("; ")),
+            );
+        }
+        Err(e) => {
+            report.push("配置文件", CheckStatus::Fail, format!("无法解析: {}", e));
+            return None;
+        }
+    }
+
+    let project_path = project_path
+        .map(str::to_string)
+        .or_else(|| std::env::current_dir().ok().map(|dir| dir.to_string_lossy().to_string()));
+    match load_project_config(project_path.as_deref()) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            report.push("项目配置", CheckStatus::Fail, format!("加载失败: {}", e));
+            None
+        }
+    }
+}
+
+/// 检查是否有可用的图形界面，弹窗需要显示服务
+fn check_display(report: &mut DoctorReport) {
+    if cfg!(target_os = "linux") {
+        let display = ["WAYLAND_DISPLAY", "DISPLAY"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()).map(|value| (var, value)));
+        match display {
+            Some((var, value)) => report.push("图形界面", CheckStatus::Pass, format!("{}={}", var, value)),
+            None => report.push(
+                "图形界面",
+                CheckStatus::Fail,
+                "未设置 DISPLAY 或 WAYLAND_DISPLAY，弹窗无法显示（可以开启 Telegram 并隐藏前端弹窗）",
+            ),
+        }
+    } else {
+        report.push("图形界面", CheckStatus::Pass, "可用");
+    }
+}
+
+fn check_log_file(report: &mut DoctorReport) {
+    let path = mcp_log_file_path();
+    match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(_) => report.push("日志文件", CheckStatus::Pass, path),
+        Err(e) => report.push("日志文件", CheckStatus::Fail, format!("{} 无法写入: {}", path, e)),
+    }
+}
+
+/// 通过发送测试消息检查 Telegram 连接
+async fn check_telegram(report: &mut DoctorReport, config: &AppConfig) {
+    let telegram_config = &config.telegram_config;
+    if !telegram_config.enabled {
+        report.push("Telegram", CheckStatus::Skip, "未启用");
+        return;
+    }
+
+    let api_url = Some(telegram_config.api_base_url.as_str()).filter(|url| !url.is_empty());
+    match test_telegram_connection_with_api_url(
+        &telegram_config.bot_token,
+        &telegram_config.chat_id,
+        api_url,
+        telegram_config.message_thread_id,
+    )
+    .await
+    {
+        Ok(_) => report.push("Telegram", CheckStatus::Pass, "测试消息发送成功"),
+        Err(e) => report.push("Telegram", CheckStatus::Fail, e.to_string()),
+    }
+}
+
+async fn check_acemcp(report: &mut DoctorReport, config: &AppConfig) {
+    const NAME: &str = "代码搜索 (acemcp)";
+    let mcp_config = &config.mcp_config;
+    let enabled = mcp_config.tools.get(mcp::TOOL_SOU).copied().unwrap_or(false);
+    if !enabled {
+        report.push(NAME, CheckStatus::Skip, "sou 工具未启用");
+        return;
+    }
+
+    let (Some(base_url), Some(token)) = (&mcp_config.acemcp_base_url, &mcp_config.acemcp_token) else {
+        report.push(NAME, CheckStatus::Fail, "sou 工具已启用，但未配置 API 端点或令牌");
+        return;
+    };
+
+    let result = check_acemcp_connection(base_url, token, &config.network_config).await;
+    let status = if result.success { CheckStatus::Pass } else { CheckStatus::Fail };
+    report.push(NAME, status, result.message);
+}
+
+/// 检查项目的记忆目录可写（不创建记忆文件）
+fn check_memory_dir(report: &mut DoctorReport, project_path: Option<&str>) {
+    const NAME: &str = "记忆目录";
+    let project_path = match project_path {
+        Some(path) => path.to_string(),
+        None => match std::env::current_dir() {
+            Ok(dir) => dir.to_string_lossy().to_string(),
+            Err(e) => {
+                report.push(NAME, CheckStatus::Skip, format!("无法获取当前目录: {}", e));
+                return;
+            }
+        },
+    };
+
+    let memory_dir = match MemoryManager::memory_dir_for(&project_path) {
+        Ok(dir) => dir,
+        Err(_) => {
+            report.push(NAME, CheckStatus::Skip, "当前目录不在 git 仓库中，可以用 --project 指定项目");
+            return;
+        }
+    };
+
+    // 记忆目录还不存在时检查 git 根目录能否创建它
+    let target_dir = if memory_dir.exists() {
+        memory_dir.clone()
+    } else {
+        memory_dir.parent().map(|dir| dir.to_path_buf()).unwrap_or_else(|| memory_dir.clone())
+    };
+    let probe = target_dir.join(format!(".cunzhi-doctor-{}", std::process::id()));
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            report.push(NAME, CheckStatus::Pass, memory_dir.display().to_string());
+        }
+        Err(e) => report.push(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} 无法写入: {}", target_dir.display(), e),
+        ),
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::Result;
+use chrono::Local;
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::constants::mcp;
+
+/// 支持自动注册的 MCP 客户端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum McpClient {
+    ClaudeDesktop,
+    Cursor,
+    Vscode,
+    Windsurf,
+    ClaudeCode,
+}
+
+impl McpClient {
+    fn display_name(self) -> &'static str {
+        match self {
+            McpClient::ClaudeDesktop => "Claude Desktop",
+            McpClient::Cursor => "Cursor",
+            McpClient::Vscode => "VS Code",
+            McpClient::Windsurf => "Windsurf",
+            McpClient::ClaudeCode => "Claude Code",
+        }
+    }
+
+    /// 客户端的 MCP 配置文件路径（用户级）
+    pub fn config_path(self) -> Result<PathBuf> {
+        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("无法获取用户主目录"))?;
+        let config_dir = dirs::config_dir().ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?;
+
+        Ok(match self {
+            McpClient::ClaudeDesktop => config_dir.join("Claude").join("claude_desktop_config.json"),
+            McpClient::Cursor => home.join(".cursor").join("mcp.json"),
+            McpClient::Vscode => config_dir.join("Code").join("User").join("mcp.json"),
+            McpClient::Windsurf => home.join(".codeium").join("windsurf").join("mcp_config.json"),
+            McpClient::ClaudeCode => home.join(".claude.json"),
+        })
+    }
+
+    /// 配置文件中存放服务器列表的字段，VS Code 使用 `servers`，其他客户端使用 `mcpServers`
+    fn servers_key(self) -> &'static str {
+        match self {
+            McpClient::Vscode => "servers",
+            _ => "mcpServers",
+        }
+    }
+
+    /// 寸止的服务器配置
+    fn server_entry(self, command: &str) -> Value {
+        match self {
+            McpClient::Vscode | McpClient::ClaudeCode => json!({
+                "type": "stdio",
+                "command": command,
+                "args": [],
+            }),
+            _ => json!({ "command": command }),
+        }
+    }
+}
+
+/// 在客户端配置中注册（或移除）寸止
+///
+/// 修改前会备份原文件，`dry_run` 时只输出将要写入的内容
+pub fn install_client(client: McpClient, uninstall: bool, dry_run: bool) -> Result<()> {
+    let path = client.config_path()?;
+    let mut config = read_client_config(&path)?;
+
+    let changed = if uninstall {
+        remove_server_entry(&mut config, client.servers_key(), mcp::SERVER_NAME)
+    } else {
+        let command = server_command_path()?;
+        merge_server_entry(
+            &mut config,
+            client.servers_key(),
+            mcp::SERVER_NAME,
+            client.server_entry(&command),
+        )?
+    };
+
+    if !changed {
+        if uninstall {
+            println!("{} 中未注册{}，无需移除", client.display_name(), mcp::SERVER_NAME);
+        } else {
+            println!("✅ {} 中已注册{}，配置无需修改", client.display_name(), mcp::SERVER_NAME);
+        }
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("将写入 {}（未修改文件）:", path.display());
+        println!("{}", serde_json::to_string_pretty(&config)?);
+        return Ok(());
+    }
+
+    if let Some(backup_path) = backup_client_config(&path)? {
+        println!("已备份原配置: {}", backup_path.display());
+    }
+    write_client_config(&path, &config)?;
+
+    if uninstall {
+        println!("✅ 已从 {} 移除{}: {}", client.display_name(), mcp::SERVER_NAME, path.display());
+    } else {
+        println!("✅ 已在 {} 注册{}: {}", client.display_name(), mcp::SERVER_NAME, path.display());
+        println!("重启 {} 后生效", client.display_name());
+    }
+    Ok(())
+}
+
+/// 当前寸止程序的绝对路径
+fn server_command_path() -> Result<String> {
+    let exe = std::env::current_exe()?;
+    let exe = exe.canonicalize().unwrap_or(exe);
+    Ok(exe.to_string_lossy().to_string())
+}
+
+/// 读取客户端配置，文件不存在或为空时视为空对象
+fn read_client_config(path: &Path) -> Result<Value> {
+    if !path.exists() {
+        return Ok(Value::Object(Map::new()));
+    }
+
+    let content = fs::read_to_string(path)?;
+    if content.trim().is_empty() {
+        return Ok(Value::Object(Map::new()));
+    }
+    let config: Value = serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("{} 不是有效的 JSON，请先修复: {}", path.display(), e))?;
+    if !config.is_object() {
+        anyhow::bail!("{} 的内容不是 JSON 对象", path.display());
+    }
+    Ok(config)
+}
+
+/// 合并服务器配置，返回配置是否发生变化
+fn merge_server_entry(config: &mut Value, servers_key: &str, name: &str, entry: Value) -> Result<bool> {
+    let servers = config
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("客户端配置不是 JSON 对象"))?
+        .entry(servers_key)
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("客户端配置的 {} 字段不是 JSON 对象", servers_key))?;
+
+    // 保留用户在已有配置中添加的其他字段（如 env）
+    let mut merged = servers.get(name).cloned().unwrap_or_else(|| Value::Object(Map::new()));
+    match (merged.as_object_mut(), entry) {
+        (Some(merged_object), Value::Object(entry)) => merged_object.extend(entry),
+        (_, entry) => merged = entry,
+    }
+
+    if servers.get(name) == Some(&merged) {
+        return Ok(false);
+    }
+    servers.insert(name.to_string(), merged);
+    Ok(true)
+}
+
+/// 移除服务器配置，返回配置是否发生变化
+fn remove_server_entry(config: &mut Value, servers_key: &str, name: &str) -> bool {
+    config
+        .get_mut(servers_key)
+        .and_then(Value::as_object_mut)
+        .map(|servers| servers.remove(name).is_some())
+        .unwrap_or(false)
+}
+
+/// 备份原配置文件，返回备份路径（文件不存在时不备份）
+fn backup_client_config(path: &Path) -> Result<Option<PathBuf>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let backup_path = path.with_file_name(format!(
+        "{}.cunzhi-backup-{}",
+        file_name,
+        Local::now().format("%Y%m%d%H%M%S")
+    ));
+    fs::copy(path, &backup_path)?;
+    Ok(Some(backup_path))
+}
+
+/// 写入客户端配置（先写临时文件再重命名）
+///
+/// 配置中可能有令牌，保留原文件的权限；新建文件在 unix 上使用 0600
+fn write_client_config(path: &Path, config: &Value) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp_path = path.with_extension("json.tmp");
+    let mut options = fs::OpenOptions::new();
+    options.create(true).truncate(true).write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temp_path)?;
+    file.write_all((serde_json::to_string_pretty(config)? + "\n").as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(&temp_path, metadata.permissions())?;
+    }
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_keeps_other_servers_and_custom_fields() {
+        let mut config = json!({
+            "theme": "dark",
+            "mcpServers": {
+                "other": { "command": "other" },
+                "寸止": { "command": "/old/寸止", "env": { "RUST_LOG": "debug" } }
+            }
+        });
+
+        let entry = McpClient::Cursor.server_entry("/usr/local/bin/寸止");
+        assert!(merge_server_entry(&mut config, "mcpServers", "寸止", entry.clone()).unwrap());
+        assert_eq!(config["theme"], "dark");
+        assert_eq!(config["mcpServers"]["other"]["command"], "other");
+        assert_eq!(config["mcpServers"]["寸止"]["command"], "/usr/local/bin/寸止");
+        assert_eq!(config["mcpServers"]["寸止"]["env"]["RUST_LOG"], "debug");
+
+        // 重复注册不修改配置
+        assert!(!merge_server_entry(&mut config, "mcpServers", "寸止", entry).unwrap());
+
+        assert!(remove_server_entry(&mut config, "mcpServers", "寸止"));
+        assert!(!remove_server_entry(&mut config, "mcpServers", "寸止"));
+        assert!(config["mcpServers"]["other"].is_object());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_keeps_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::utils::test_dir("install_client");
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        // 已有文件保留原权限
+        let existing = dir.join("claude.json");
+        fs::write(&existing, "{}").unwrap();
+        fs::set_permissions(&existing, fs::Permissions::from_mode(0o640)).unwrap();
+        write_client_config(&existing, &json!({ "mcpServers": {} })).unwrap();
+        assert_eq!(mode(&existing), 0o640);
+
+        // 新文件仅当前用户可读写
+        let created = dir.join("mcp.json");
+        write_client_config(&created, &json!({})).unwrap();
+        assert_eq!(mode(&created), 0o600);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_rejects_invalid_servers_field() {
+        let mut config = json!({ "servers": [] });
+        let entry = McpClient::Vscode.server_entry("寸止");
+        assert!(merge_server_entry(&mut config, "servers", "寸止", entry).is_err());
+    }
+}
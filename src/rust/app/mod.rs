@@ -2,6 +2,8 @@ pub mod setup;
 pub mod commands;
 pub mod builder;
 pub mod cli;
+pub mod doctor;
+pub mod install_client;
 
 pub use setup::*;
 pub use commands::*;
@@ -1,5 +1,5 @@
-use crate::config::{AppState, load_config_and_apply_window_settings};
-use crate::ui::{initialize_audio_asset_manager, setup_window_event_listeners};
+use crate::config::{AppState, load_config_and_apply_window_settings, request_file};
+use crate::ui::{initialize_audio_asset_manager, setup_tray, setup_window_event_listeners};
 use crate::ui::exit_handler::setup_exit_handlers;
 use crate::log_important;
 use tauri::{AppHandle, Manager};
@@ -18,6 +18,13 @@ pub async fn setup_application(app_handle: &AppHandle) -> Result<(), String> {
         log_important!(warn, "初始化音频资源管理器失败: {}", e);
     }
 
+    // 设置托盘图标（处理 MCP 请求的弹窗进程不需要）
+    if request_file().is_none() {
+        if let Err(e) = setup_tray(app_handle) {
+            log_important!(warn, "创建托盘图标失败: {}", e);
+        }
+    }
+
     // 设置窗口事件监听器
     setup_window_event_listeners(app_handle);
 
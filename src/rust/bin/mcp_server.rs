@@ -1,8 +1,58 @@
 // MCP 服务器入口点
+use clap::{ArgAction, Parser, Subcommand};
+use cunzhi::app::doctor::{run_doctor, DoctorArgs};
+use cunzhi::app::install_client::{install_client, McpClient};
 use cunzhi::{mcp::run_server, utils::auto_init_logger, log_important};
 
+/// 寸止 MCP 服务器，不带参数运行时通过标准输入输出提供 MCP 服务
+#[derive(Debug, Parser)]
+#[command(name = "寸止", version, disable_version_flag = true)]
+struct Cli {
+    /// 显示版本信息
+    #[arg(short = 'v', long = "version", action = ArgAction::Version)]
+    version: Option<bool>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 诊断安装和配置问题
+    Doctor(DoctorArgs),
+    /// 在 MCP 客户端的配置文件中注册寸止
+    InstallClient {
+        #[arg(value_enum)]
+        client: McpClient,
+        /// 从客户端配置中移除寸止
+        #[arg(long)]
+        uninstall: bool,
+        /// 只输出修改后的配置，不写入文件
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Doctor(args)) => {
+            if !run_doctor(&args).await {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::InstallClient { client, uninstall, dry_run }) => {
+            if let Err(e) = install_client(client, uninstall, dry_run) {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+
     // 自动初始化日志系统
     auto_init_logger()?;
 
@@ -0,0 +1,301 @@
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use super::settings::*;
+use crate::constants::{font, telegram, validation, window};
+
+/// 诊断发现问题后对配置做的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticAction {
+    /// 数值已限制到有效范围
+    Clamped,
+    /// 已重置为默认值
+    Reset,
+    /// 保持原值，需要用户手动修复
+    Unchanged,
+}
+
+/// 配置诊断：字段路径、问题描述和修复建议
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigDiagnostic {
+    /// 字段路径，如 `ui_config.window_config.fixed_width`
+    pub field: String,
+    pub problem: String,
+    pub suggestion: String,
+    pub action: DiagnosticAction,
+}
+
+impl fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            DiagnosticAction::Clamped => "已限制到有效范围",
+            DiagnosticAction::Reset => "已重置为默认值",
+            DiagnosticAction::Unchanged => "需要手动修复",
+        };
+        write!(
+            f,
+            "{}: {}（{}）\n  建议: {}",
+            self.field, self.problem, action, self.suggestion
+        )
+    }
+}
+
+/// 收集诊断的辅助结构
+#[derive(Default)]
+struct Diagnostics(Vec<ConfigDiagnostic>);
+
+impl Diagnostics {
+    fn push(&mut self, field: &str, problem: String, suggestion: impl Into<String>, action: DiagnosticAction) {
+        self.0.push(ConfigDiagnostic {
+            field: field.to_string(),
+            problem,
+            suggestion: suggestion.into(),
+            action,
+        });
+    }
+
+    /// 字符串不在允许范围内时重置为默认值
+    fn reset_string(&mut self, field: &str, value: &mut String, valid: bool, default: String) {
+        if !valid {
+            self.push(
+                field,
+                format!("无效的值 \"{}\"", value),
+                format!("使用默认值 \"{}\" 或在设置界面中重新选择", default),
+                DiagnosticAction::Reset,
+            );
+            *value = default;
+        }
+    }
+}
+
+/// 校验配置并修正无效值，返回发现的问题
+///
+/// 可以安全修正的值（越界尺寸、未知主题等）会被限制或重置，
+/// 令牌、Chat ID 这类只能由用户提供的值保持不变，仅给出建议
+pub fn validate_config(config: &mut AppConfig) -> Vec<ConfigDiagnostic> {
+    let mut diagnostics = Diagnostics::default();
+
+    validate_ui_config(&mut config.ui_config, &mut diagnostics);
+    validate_audio_config(&mut config.audio_config, &mut diagnostics);
+    validate_reply_config(&mut config.reply_config, &mut diagnostics);
+    validate_telegram_config(&mut config.telegram_config, &mut diagnostics);
+    validate_mcp_config(&config.mcp_config, &mut diagnostics);
+    validate_network_config(&mut config.network_config, &mut diagnostics);
+
+    diagnostics.0
+}
+
+fn validate_ui_config(ui_config: &mut UiConfig, diagnostics: &mut Diagnostics) {
+    let theme_valid = validation::is_valid_theme(&ui_config.theme);
+    diagnostics.reset_string("ui_config.theme", &mut ui_config.theme, theme_valid, default_theme());
+
+    let font_config = &mut ui_config.font_config;
+    let family_valid = font_config.font_family == "custom"
+        || font::FONT_FAMILIES.iter().any(|(id, _, _)| *id == font_config.font_family);
+    diagnostics.reset_string(
+        "ui_config.font_config.font_family",
+        &mut font_config.font_family,
+        family_valid,
+        default_font_family(),
+    );
+    let size_valid = font::FONT_SIZES.iter().any(|(id, _, _)| *id == font_config.font_size);
+    diagnostics.reset_string(
+        "ui_config.font_config.font_size",
+        &mut font_config.font_size,
+        size_valid,
+        default_font_size(),
+    );
+
+    let window_config = &mut ui_config.window_config;
+    for (name, width, height) in [
+        ("fixed", &mut window_config.fixed_width, &mut window_config.fixed_height),
+        ("free", &mut window_config.free_width, &mut window_config.free_height),
+    ] {
+        if !validation::is_valid_window_size(*width, *height) {
+            let (clamped_width, clamped_height) = window::clamp_window_size(*width, *height);
+            diagnostics.push(
+                &format!("ui_config.window_config.{}_width/{}_height", name, name),
+                format!("窗口尺寸 {}x{} 超出范围", width, height),
+                format!(
+                    "宽度应在 {}-{}，高度应在 {}-{} 之间",
+                    window::MIN_WIDTH,
+                    window::MAX_WIDTH,
+                    window::MIN_HEIGHT,
+                    window::MAX_HEIGHT
+                ),
+                DiagnosticAction::Clamped,
+            );
+            *width = clamped_width;
+            *height = clamped_height;
+        }
+    }
+
+    if window_config.min_width > window_config.max_width
+        || window_config.min_height > window_config.max_height
+    {
+        diagnostics.push(
+            "ui_config.window_config.min_width/max_width",
+            "最小尺寸大于最大尺寸".to_string(),
+            "使用默认的窗口约束",
+            DiagnosticAction::Reset,
+        );
+        window_config.min_width = default_min_width();
+        window_config.min_height = default_min_height();
+        window_config.max_width = default_max_width();
+        window_config.max_height = default_max_height();
+    }
+}
+
+fn validate_audio_config(audio_config: &mut AudioConfig, diagnostics: &mut Diagnostics) {
+    // 自定义音效既可以是 URL 也可以是本地路径，只检查带协议的值
+    let url = audio_config.custom_url.trim();
+    let url_valid = !url.contains("://") || validation::is_valid_url(url);
+    diagnostics.reset_string(
+        "audio_config.custom_url",
+        &mut audio_config.custom_url,
+        url_valid,
+        default_audio_url(),
+    );
+}
+
+fn validate_reply_config(reply_config: &mut ReplyConfig, diagnostics: &mut Diagnostics) {
+    if reply_config.enable_continue_reply && validation::is_empty_string(&reply_config.continue_prompt) {
+        diagnostics.push(
+            "reply_config.continue_prompt",
+            "启用了继续回复但提示词为空".to_string(),
+            "填写继续回复时发送的提示词",
+            DiagnosticAction::Reset,
+        );
+        reply_config.continue_prompt = default_continue_prompt();
+    }
+}
+
+fn validate_telegram_config(telegram_config: &mut TelegramConfig, diagnostics: &mut Diagnostics) {
+    let api_url_valid = !telegram_config.api_base_url.trim().is_empty()
+        && validation::is_valid_url(&telegram_config.api_base_url);
+    diagnostics.reset_string(
+        "telegram_config.api_base_url",
+        &mut telegram_config.api_base_url,
+        api_url_valid,
+        telegram::API_BASE_URL.to_string(),
+    );
+
+    // 未启用时允许令牌和 Chat ID 为空
+    if !telegram_config.enabled {
+        return;
+    }
+
+    if !validation::is_valid_bot_token(&telegram_config.bot_token) {
+        diagnostics.push(
+            "telegram_config.bot_token",
+            "已启用 Telegram 但 Bot Token 无效".to_string(),
+            "从 @BotFather 获取形如 123456:ABC-DEF 的 Token",
+            DiagnosticAction::Unchanged,
+        );
+    }
+
+    if !validation::is_valid_chat_id(&telegram_config.chat_id) {
+        diagnostics.push(
+            "telegram_config.chat_id",
+            format!("无效的 Chat ID \"{}\"", telegram_config.chat_id),
+            "填写数字 ID、群组负数 ID 或 @频道用户名，也可以在设置界面自动获取",
+            DiagnosticAction::Unchanged,
+        );
+    }
+}
+
+fn validate_mcp_config(mcp_config: &McpConfig, diagnostics: &mut Diagnostics) {
+    if let Some(base_url) = mcp_config.acemcp_base_url.as_deref() {
+        if !base_url.trim().is_empty() && !validation::is_valid_url(base_url) {
+            diagnostics.push(
+                "mcp_config.acemcp_base_url",
+                format!("无效的 API 地址 \"{}\"", base_url),
+                "使用以 http:// 或 https:// 开头的地址",
+                DiagnosticAction::Unchanged,
+            );
+        }
+    }
+}
+
+fn validate_network_config(network_config: &mut NetworkConfig, diagnostics: &mut Diagnostics) {
+    // 无效的代理会导致所有网络请求失败，直接重置
+    let proxy_url = network_config.proxy_url.trim();
+    let proxy_valid = proxy_url.is_empty()
+        || ["http://", "https://", "socks5://", "socks5h://"]
+            .iter()
+            .any(|scheme| proxy_url.starts_with(scheme));
+    if !proxy_valid {
+        diagnostics.push(
+            "network_config.proxy_url",
+            format!("不支持的代理地址 \"{}\"", proxy_url),
+            "使用 http://、https://、socks5:// 或 socks5h:// 开头的代理地址",
+            DiagnosticAction::Reset,
+        );
+        network_config.proxy_url = default_proxy_url();
+    }
+
+    let ca_bundle_path = network_config.ca_bundle_path.trim();
+    if !ca_bundle_path.is_empty() && !Path::new(ca_bundle_path).is_file() {
+        diagnostics.push(
+            "network_config.ca_bundle_path",
+            format!("CA 证书文件不存在: {}", ca_bundle_path),
+            "检查证书路径，或留空使用系统证书",
+            DiagnosticAction::Reset,
+        );
+        network_config.ca_bundle_path = default_ca_bundle_path();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_is_valid() {
+        let mut config = AppConfig::default();
+        assert!(validate_config(&mut config).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_values_are_fixed() {
+        let mut config = AppConfig::default();
+        config.ui_config.theme = "purple".to_string();
+        config.ui_config.window_config.fixed_width = 10000.0;
+        config.network_config.proxy_url = "ftp://proxy".to_string();
+
+        let diagnostics = validate_config(&mut config);
+        let actions: Vec<(&str, DiagnosticAction)> = diagnostics
+            .iter()
+            .map(|d| (d.field.as_str(), d.action))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("ui_config.theme", DiagnosticAction::Reset),
+                ("ui_config.window_config.fixed_width/fixed_height", DiagnosticAction::Clamped),
+                ("network_config.proxy_url", DiagnosticAction::Reset),
+            ]
+        );
+
+        assert_eq!(config.ui_config.theme, default_theme());
+        assert_eq!(config.ui_config.window_config.fixed_width, window::MAX_WIDTH);
+        assert!(config.network_config.proxy_url.is_empty());
+
+        // 修正后再次校验不再有问题
+        assert!(validate_config(&mut config).is_empty());
+    }
+
+    #[test]
+    fn test_telegram_credentials_are_reported_not_changed() {
+        let mut config = AppConfig::default();
+        config.telegram_config.enabled = true;
+        config.telegram_config.chat_id = "not-a-chat".to_string();
+
+        let diagnostics = validate_config(&mut config);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.action == DiagnosticAction::Unchanged));
+        assert_eq!(config.telegram_config.chat_id, "not-a-chat");
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::env::apply_env_overrides;
+use super::project::apply_project_config;
+use super::secrets::mask_secret;
+use super::settings::AppConfig;
+use super::storage::{load_config_file, load_raw_config_file};
+
+/// 配置值的来源，按优先级从低到高排列
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    File,
+    Project,
+    /// 环境变量覆盖，记录变量名
+    Env(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "默认值"),
+            ConfigSource::File => write!(f, "配置文件"),
+            ConfigSource::Project => write!(f, "项目配置"),
+            ConfigSource::Env(var) => write!(f, "环境变量 {}", var),
+        }
+    }
+}
+
+/// 一个生效的配置项
+#[derive(Debug, Clone)]
+pub struct EffectiveValue {
+    /// 字段路径，如 `telegram_config.enabled`
+    pub field: String,
+    /// 生效的值（令牌已掩码）
+    pub value: Value,
+    pub source: ConfigSource,
+}
+
+/// 计算生效的配置及每一项的来源
+///
+/// 依次应用配置文件、项目配置（`project_path` 不为空时）和环境变量，与工具调用时的加载顺序一致
+pub fn effective_config(project_path: Option<&str>) -> Result<Vec<EffectiveValue>> {
+    let raw_file = load_raw_config_file()?;
+
+    let mut config = load_config_file()?;
+    let file_values = flatten_config(&config)?;
+
+    apply_project_config(&mut config, project_path);
+    let project_values = flatten_config(&config)?;
+
+    let env_overrides = apply_env_overrides(&mut config);
+    let effective_values = flatten_config(&config)?;
+
+    Ok(effective_values
+        .into_iter()
+        .map(|(field, value)| {
+            let env_var = env_overrides.iter().find(|o| {
+                field == o.field || field.starts_with(&format!("{}.", o.field))
+            });
+            let source = if let Some(env_override) = env_var {
+                ConfigSource::Env(env_override.var.clone())
+            } else if project_values.get(&field) != file_values.get(&field) {
+                ConfigSource::Project
+            } else if raw_file.as_ref().is_some_and(|raw| contains_field(raw, &field)) {
+                ConfigSource::File
+            } else {
+                ConfigSource::Default
+            };
+            EffectiveValue { field, value, source }
+        })
+        .collect())
+}
+
+/// 把配置展开为 `字段路径 -> 值`，数组作为一个整体，令牌会被掩码
+fn flatten_config(config: &AppConfig) -> Result<BTreeMap<String, Value>> {
+    let mut config = config.clone();
+    config.telegram_config.bot_token = mask_secret(&config.telegram_config.bot_token);
+    config.mcp_config.acemcp_token = config.mcp_config.acemcp_token.as_deref().map(mask_secret);
+
+    let mut values = BTreeMap::new();
+    flatten_value(String::new(), serde_json::to_value(&config)?, &mut values);
+    Ok(values)
+}
+
+fn flatten_value(prefix: String, value: Value, values: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, child) in object {
+                let field = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+                flatten_value(field, child, values);
+            }
+        }
+        value => {
+            values.insert(prefix, value);
+        }
+    }
+}
+
+/// 原始配置文件中是否包含该字段
+fn contains_field(raw: &Value, field: &str) -> bool {
+    field
+        .split('.')
+        .try_fold(raw, |value, key| value.get(key))
+        .is_some()
+}
@@ -0,0 +1,230 @@
+use anyhow::Result;
+use serde_json::Value;
+
+use super::settings::AppConfig;
+use crate::constants::app;
+use crate::log_important;
+
+/// 一个生效的环境变量覆盖
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvOverride {
+    /// 环境变量名，如 `CUNZHI_MCP_CONFIG__ACEMCP_BASE_URL`
+    pub var: String,
+    /// 字段路径，如 `mcp_config.acemcp_base_url`
+    pub field: String,
+}
+
+/// 使用 `CUNZHI_` 开头的环境变量覆盖配置，返回生效的覆盖
+///
+/// 变量名去掉前缀后用 `__` 分隔字段路径（不区分大小写），
+/// 值按字段当前的类型解析，无法解析或字段不存在时记录警告并忽略
+pub fn apply_env_overrides(config: &mut AppConfig) -> Vec<EnvOverride> {
+    apply_overrides_from(config, std::env::vars())
+}
+
+fn apply_overrides_from(
+    config: &mut AppConfig,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Vec<EnvOverride> {
+    let mut vars: Vec<(String, String)> = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(app::ENV_PREFIX))
+        .collect();
+    if vars.is_empty() {
+        return Vec::new();
+    }
+    // 按变量名排序，保证覆盖顺序稳定
+    vars.sort();
+
+    let mut merged = match serde_json::to_value(&*config) {
+        Ok(value) => value,
+        Err(e) => {
+            log_important!(warn, "序列化配置失败，忽略环境变量覆盖: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut overrides = Vec::new();
+    for (var, raw) in vars {
+        match apply_env_var(&mut merged, &var, &raw) {
+            Ok(field) => overrides.push(EnvOverride { var, field }),
+            Err(e) => log_important!(warn, "忽略环境变量 {}: {}", var, e),
+        }
+    }
+
+    match serde_json::from_value(merged) {
+        Ok(overridden) => *config = overridden,
+        // 每个覆盖都已单独校验，这里只是兜底
+        Err(e) => {
+            log_important!(warn, "应用环境变量覆盖失败: {}", e);
+            overrides.clear();
+        }
+    }
+    overrides
+}
+
+/// 按字段路径（如 `telegram_config.enabled`）设置配置项，值按字段类型解析，返回规范化的字段路径
+pub fn set_config_field(config: &mut AppConfig, field: &str, raw: &str) -> Result<String> {
+    let segments: Vec<&str> = field.split('.').collect();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        anyhow::bail!("字段路径格式应为 <字段>.<子字段>");
+    }
+
+    let mut merged = serde_json::to_value(&*config)?;
+    let field = set_field(&mut merged, &segments, raw)?;
+    *config = serde_json::from_value(merged)?;
+    Ok(field)
+}
+
+/// 把一个环境变量应用到序列化后的配置上，返回字段路径
+fn apply_env_var(merged: &mut Value, var: &str, raw: &str) -> Result<String> {
+    let segments: Vec<&str> = var[app::ENV_PREFIX.len()..]
+        .split(app::ENV_PATH_SEPARATOR)
+        .collect();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        anyhow::bail!("变量名格式应为 {}<字段>{}<子字段>", app::ENV_PREFIX, app::ENV_PATH_SEPARATOR);
+    }
+
+    set_field(merged, &segments, raw)
+}
+
+/// 按字段路径设置序列化后的配置，返回字段路径
+fn set_field(merged: &mut Value, segments: &[&str], raw: &str) -> Result<String> {
+    // 字段名不区分大小写，只允许覆盖已存在的字段，避免拼写错误被静默忽略
+    let mut keys = Vec::with_capacity(segments.len());
+    let mut current = &*merged;
+    for segment in segments {
+        let key = current
+            .as_object()
+            .and_then(|object| object.keys().find(|key| key.eq_ignore_ascii_case(segment)))
+            .ok_or_else(|| anyhow::anyhow!("未知的配置字段 {}", segment.to_lowercase()))?
+            .clone();
+        current = &current[&key];
+        keys.push(key);
+    }
+    let field = keys.join(".");
+
+    // 依次尝试按类型解析出的候选值，选择能通过配置反序列化的第一个
+    for candidate in parse_candidates(current, raw) {
+        let mut trial = merged.clone();
+        *keys.iter().fold(&mut trial, |value, key| &mut value[key.as_str()]) = candidate;
+        if serde_json::from_value::<AppConfig>(trial.clone()).is_ok() {
+            *merged = trial;
+            return Ok(field);
+        }
+    }
+
+    anyhow::bail!("值 \"{}\" 不符合字段 {} 的类型", raw, field)
+}
+
+/// 根据字段当前的值推断类型，返回候选值
+fn parse_candidates(current: &Value, raw: &str) -> Vec<Value> {
+    let trimmed = raw.trim();
+    match current {
+        Value::Bool(_) => parse_bool(trimmed).map(Value::Bool).into_iter().collect(),
+        // 整数和浮点数由反序列化决定是否匹配字段类型
+        Value::Number(_) => [
+            trimmed.parse::<u64>().ok().map(Value::from),
+            trimmed.parse::<i64>().ok().map(Value::from),
+            trimmed.parse::<f64>().ok().map(Value::from),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+        Value::String(_) => vec![Value::String(raw.to_string())],
+        // 数组支持 JSON 或逗号分隔的字符串列表
+        Value::Array(_) => {
+            if trimmed.starts_with('[') {
+                serde_json::from_str(trimmed).ok().into_iter().collect()
+            } else {
+                vec![Value::Array(
+                    trimmed
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(|item| Value::String(item.to_string()))
+                        .collect(),
+                )]
+            }
+        }
+        Value::Object(_) => serde_json::from_str(trimmed).ok().into_iter().collect(),
+        // 未设置的可选字段无法确定类型，依次尝试 JSON 值和字符串
+        Value::Null => {
+            let mut candidates: Vec<Value> = serde_json::from_str(trimmed).ok().into_iter().collect();
+            candidates.push(Value::String(raw.to_string()));
+            if let Some(value) = parse_bool(trimmed) {
+                candidates.push(Value::Bool(value));
+            }
+            candidates
+        }
+    }
+}
+
+fn parse_bool(raw: &str) -> Option<bool> {
+    match raw.to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_type_aware_overrides() {
+        let mut config = AppConfig::default();
+        let overrides = apply_overrides_from(
+            &mut config,
+            vars(&[
+                ("CUNZHI_TELEGRAM_CONFIG__ENABLED", "yes"),
+                ("CUNZHI_MCP_CONFIG__ACEMCP_BASE_URL", "https://ace.example.com"),
+                ("CUNZHI_MCP_CONFIG__ACEMCP_BATCH_SIZE", "20"),
+                ("CUNZHI_MCP_CONFIG__ACEMCP_EXCLUDE_PATTERNS", "vendor, dist"),
+                ("CUNZHI_MCP_CONFIG__TOOLS__SOU", "true"),
+                ("CUNZHI_UI_CONFIG__WINDOW_CONFIG__FIXED_WIDTH", "700"),
+                ("PATH", "/usr/bin"),
+            ]),
+        );
+
+        assert_eq!(overrides.len(), 6);
+        assert!(config.telegram_config.enabled);
+        assert_eq!(config.mcp_config.acemcp_base_url.as_deref(), Some("https://ace.example.com"));
+        assert_eq!(config.mcp_config.acemcp_batch_size, Some(20));
+        assert_eq!(
+            config.mcp_config.acemcp_exclude_patterns,
+            Some(vec!["vendor".to_string(), "dist".to_string()])
+        );
+        assert_eq!(config.mcp_config.tools.get("sou"), Some(&true));
+        assert_eq!(config.ui_config.window_config.fixed_width, 700.0);
+        assert!(overrides.contains(&EnvOverride {
+            var: "CUNZHI_MCP_CONFIG__TOOLS__SOU".to_string(),
+            field: "mcp_config.tools.sou".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_invalid_overrides_are_ignored() {
+        let mut config = AppConfig::default();
+        let overrides = apply_overrides_from(
+            &mut config,
+            vars(&[
+                ("CUNZHI_TELEGRAM_CONFIG__ENABLED", "maybe"),
+                ("CUNZHI_TELEGRAM_CONFIG__UNKNOWN_FIELD", "1"),
+                ("CUNZHI_MCP_CONFIG__ACEMCP_BATCH_SIZE", "-1"),
+            ]),
+        );
+
+        assert!(overrides.is_empty());
+        assert!(!config.telegram_config.enabled);
+        assert_eq!(config.mcp_config.acemcp_batch_size, AppConfig::default().mcp_config.acemcp_batch_size);
+    }
+}
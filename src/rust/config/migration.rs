@@ -0,0 +1,170 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::constants::app;
+
+/// 单个配置迁移：将配置从 `version - 1` 升级到 `version`
+struct Migration {
+    version: u32,
+    description: &'static str,
+    migrate: fn(&mut Value),
+}
+
+/// 按版本排序的迁移列表，新增迁移时追加到末尾并同步递增 [`app::CONFIG_VERSION`]
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "增强快捷键默认值从 Shift+Enter 改为 Ctrl+Shift+Enter",
+    migrate: migrate_enhance_shortcut,
+}];
+
+/// 读取配置文件中的版本号，没有版本号的旧配置视为版本 0
+pub fn config_version(value: &Value) -> u32 {
+    value
+        .get("config_version")
+        .and_then(Value::as_u64)
+        .map_or(0, |version| version as u32)
+}
+
+/// 依次执行尚未应用的迁移，返回迁移前的版本号；无需迁移时返回 `None`
+///
+/// 迁移作用于原始 JSON，因此即使字段结构发生不兼容变化也能在反序列化前完成升级
+pub fn migrate_config(value: &mut Value) -> Option<u32> {
+    let from_version = config_version(value);
+    if from_version >= app::CONFIG_VERSION {
+        if from_version > app::CONFIG_VERSION {
+            log::warn!(
+                "配置文件版本 {} 高于当前支持的版本 {}，可能由更新版本的应用写入",
+                from_version,
+                app::CONFIG_VERSION
+            );
+        }
+        return None;
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > from_version) {
+        log::info!("迁移配置到版本 {}: {}", migration.version, migration.description);
+        (migration.migrate)(value);
+    }
+
+    if let Some(object) = value.as_object_mut() {
+        object.insert("config_version".to_string(), json!(app::CONFIG_VERSION));
+    }
+
+    Some(from_version)
+}
+
+/// 在改写配置文件前备份旧文件，如 `config.json.v0.bak`
+pub fn backup_config_file(config_path: &Path, from_version: u32) -> Result<PathBuf> {
+    let file_name = config_path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("无效的配置文件路径: {:?}", config_path))?
+        .to_string_lossy();
+    let backup_path = config_path.with_file_name(format!("{}.v{}.bak", file_name, from_version));
+
+    fs::copy(config_path, &backup_path)
+        .map_err(|e| anyhow::anyhow!("备份配置文件失败: {}", e))?;
+
+    Ok(backup_path)
+}
+
+/// v1: 旧版本默认的 Shift+Enter 增强快捷键与换行冲突，升级为 Ctrl+Shift+Enter
+///
+/// 只修改仍为旧默认值的绑定，用户自定义的组合保持不变
+fn migrate_enhance_shortcut(value: &mut Value) {
+    let Some(key_combination) = value
+        .pointer_mut("/shortcut_config/shortcuts/enhance/key_combination")
+        .and_then(Value::as_object_mut)
+    else {
+        return;
+    };
+
+    let is_old_default = key_combination.get("key") == Some(&json!("Enter"))
+        && key_combination.get("shift") == Some(&json!(true))
+        && ["ctrl", "alt", "meta"]
+            .iter()
+            .all(|modifier| key_combination.get(*modifier) != Some(&json!(true)));
+
+    if is_old_default {
+        key_combination.insert("ctrl".to_string(), json!(true));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enhance_config(ctrl: bool, shift: bool) -> Value {
+        json!({
+            "shortcut_config": {
+                "shortcuts": {
+                    "enhance": {
+                        "id": "enhance",
+                        "key_combination": {
+                            "key": "Enter", "ctrl": ctrl, "alt": false, "shift": shift, "meta": false
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn enhance_ctrl(value: &Value) -> bool {
+        value["shortcut_config"]["shortcuts"]["enhance"]["key_combination"]["ctrl"] == json!(true)
+    }
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        let expected: Vec<u32> = (1..=app::CONFIG_VERSION).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn test_migrate_unversioned_config() {
+        let mut value = enhance_config(false, true);
+        assert_eq!(config_version(&value), 0);
+
+        assert_eq!(migrate_config(&mut value), Some(0));
+        assert!(enhance_ctrl(&value));
+        assert_eq!(config_version(&value), app::CONFIG_VERSION);
+
+        // 已是最新版本时不再迁移
+        assert_eq!(migrate_config(&mut value), None);
+    }
+
+    #[test]
+    fn test_enhance_migration_keeps_custom_binding() {
+        // 用户自定义为单独的 Enter，不应被改写
+        let mut value = enhance_config(false, false);
+        migrate_config(&mut value);
+        assert!(!enhance_ctrl(&value));
+
+        // 缺少快捷键配置的旧文件也能迁移
+        let mut value = json!({ "ui_config": { "theme": "dark" } });
+        assert_eq!(migrate_config(&mut value), Some(0));
+        assert_eq!(value["ui_config"]["theme"], json!("dark"));
+    }
+
+    #[test]
+    fn test_newer_config_is_untouched() {
+        let mut value = enhance_config(false, true);
+        value["config_version"] = json!(app::CONFIG_VERSION + 1);
+        assert_eq!(migrate_config(&mut value), None);
+        assert!(!enhance_ctrl(&value));
+    }
+
+    #[test]
+    fn test_backup_config_file() {
+        let dir = crate::utils::test_dir("migration");
+        let config_path = dir.join("config.json");
+        fs::write(&config_path, "{}").unwrap();
+
+        let backup_path = backup_config_file(&config_path, 0).unwrap();
+        assert_eq!(backup_path, dir.join("config.json.v0.bak"));
+        assert_eq!(fs::read_to_string(&backup_path).unwrap(), "{}");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,22 @@
+pub mod diagnostics;
+pub mod effective;
+pub mod env;
+pub mod migration;
+pub mod profiles;
+pub mod project;
+pub mod secrets;
 pub mod settings;
 pub mod storage;
 
+pub use diagnostics::{ConfigDiagnostic, DiagnosticAction};
+pub use effective::{effective_config, ConfigSource, EffectiveValue};
+pub use env::{apply_env_overrides, EnvOverride};
+pub use profiles::{
+    apply_profile, delete_profile, list_profiles, load_profile, save_current_profile, save_profile,
+    switch_standalone_profile,
+    ConfigProfile,
+};
+pub use project::{apply_project_config, load_project_config, request_file, request_project_path, set_request_file};
+pub use secrets::{mask_secret, unmask_secret, SecretStore};
 pub use settings::*;
 pub use storage::*;
@@ -0,0 +1,181 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use super::settings::{AppConfig, AudioConfig, ReplyConfig, TelegramConfig};
+use super::storage::{get_config_dir, load_config_file, update_standalone_config, with_config_lock};
+use crate::constants::app;
+
+/// 配置方案：`AppConfig` 中可以整体切换的一组设置
+///
+/// 未设置的部分在切换时保持不变
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_config: Option<ReplyConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_config: Option<AudioConfig>,
+    /// Telegram 设置，Bot Token 不保存在方案中，切换时保留当前的 Token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telegram_config: Option<TelegramConfig>,
+    /// MCP 工具启用状态
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mcp_tools: Option<HashMap<String, bool>>,
+}
+
+impl ConfigProfile {
+    /// 从当前配置中截取方案包含的设置
+    pub fn capture(config: &AppConfig) -> Self {
+        let mut telegram_config = config.telegram_config.clone();
+        telegram_config.bot_token.clear();
+
+        Self {
+            reply_config: Some(config.reply_config.clone()),
+            audio_config: Some(config.audio_config.clone()),
+            telegram_config: Some(telegram_config),
+            mcp_tools: Some(config.mcp_config.tools.clone()),
+        }
+    }
+
+    /// 把方案中的设置应用到配置
+    pub fn apply(&self, config: &mut AppConfig) {
+        if let Some(reply_config) = &self.reply_config {
+            config.reply_config = reply_config.clone();
+        }
+        if let Some(audio_config) = &self.audio_config {
+            config.audio_config = audio_config.clone();
+        }
+        if let Some(telegram_config) = &self.telegram_config {
+            let bot_token = std::mem::take(&mut config.telegram_config.bot_token);
+            config.telegram_config = telegram_config.clone();
+            config.telegram_config.bot_token = bot_token;
+        }
+        if let Some(mcp_tools) = &self.mcp_tools {
+            config.mcp_config.tools = mcp_tools.clone();
+        }
+    }
+}
+
+/// 列出所有配置方案名称（按名称排序）
+pub fn list_profiles() -> Result<Vec<String>> {
+    Ok(with_config_lock(read_profiles)?.into_keys().collect())
+}
+
+/// 读取指定的配置方案
+pub fn load_profile(name: &str) -> Result<ConfigProfile> {
+    with_config_lock(read_profiles)?
+        .remove(name)
+        .ok_or_else(|| anyhow::anyhow!("配置方案不存在: {}", name))
+}
+
+/// 保存配置方案，同名方案会被覆盖
+pub fn save_profile(name: &str, profile: ConfigProfile) -> Result<()> {
+    validate_profile_name(name)?;
+    with_config_lock(|| {
+        let mut profiles = read_profiles()?;
+        profiles.insert(name.to_string(), profile);
+        write_profiles(&profiles)
+    })
+}
+
+/// 把配置文件中的当前设置保存为配置方案，同名方案会被覆盖
+///
+/// 从配置文件读取而不是使用内存中的配置，项目配置和环境变量覆盖不会保存到方案中
+pub fn save_current_profile(name: &str) -> Result<()> {
+    let profile = ConfigProfile::capture(&load_config_file()?);
+    save_profile(name, profile)
+}
+
+/// 删除配置方案
+pub fn delete_profile(name: &str) -> Result<()> {
+    with_config_lock(|| {
+        let mut profiles = read_profiles()?;
+        if profiles.remove(name).is_none() {
+            anyhow::bail!("配置方案不存在: {}", name);
+        }
+        write_profiles(&profiles)
+    })
+}
+
+/// 把配置方案应用到配置并记录为当前方案
+pub fn apply_profile(config: &mut AppConfig, name: &str, profile: &ConfigProfile) {
+    profile.apply(config);
+    config.active_profile = Some(name.to_string());
+}
+
+/// 在独立进程中切换配置方案（用于命令行），运行中的 MCP 服务器在下次读取配置时生效
+pub fn switch_standalone_profile(name: &str) -> Result<()> {
+    let profile = load_profile(name)?;
+    update_standalone_config(|config| apply_profile(config, name, &profile))
+}
+
+fn validate_profile_name(name: &str) -> Result<()> {
+    let name_len = name.chars().count();
+    if name.trim().is_empty() || name.trim() != name {
+        anyhow::bail!("配置方案名称不能为空，且首尾不能有空白");
+    }
+    if name_len > app::MAX_PROFILE_NAME_LENGTH || name.chars().any(char::is_control) {
+        anyhow::bail!(
+            "配置方案名称最多 {} 个字符，且不能包含控制字符",
+            app::MAX_PROFILE_NAME_LENGTH
+        );
+    }
+    Ok(())
+}
+
+/// 读取方案文件，文件不存在时视为空
+fn read_profiles() -> Result<BTreeMap<String, ConfigProfile>> {
+    let path = get_config_dir()?.join(app::PROFILES_FILE_NAME);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// 写入方案文件（先写临时文件再重命名）
+fn write_profiles(profiles: &BTreeMap<String, ConfigProfile>) -> Result<()> {
+    let dir = get_config_dir()?;
+    let path = dir.join(app::PROFILES_FILE_NAME);
+    let temp_path = dir.join(format!("{}.tmp", app::PROFILES_FILE_NAME));
+    fs::write(&temp_path, serde_json::to_string_pretty(profiles)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_keeps_bot_token_and_other_settings() {
+        let mut away = AppConfig::default();
+        away.telegram_config.enabled = true;
+        away.telegram_config.hide_frontend_popup = true;
+        away.telegram_config.bot_token = "123:old".to_string();
+        away.audio_config.notification_enabled = false;
+        let profile = ConfigProfile::capture(&away);
+        assert!(profile.telegram_config.as_ref().unwrap().bot_token.is_empty());
+
+        let mut config = AppConfig::default();
+        config.telegram_config.bot_token = "123:current".to_string();
+        config.ui_config.theme = "light".to_string();
+        apply_profile(&mut config, "away", &profile);
+
+        assert!(config.telegram_config.enabled);
+        assert!(config.telegram_config.hide_frontend_popup);
+        assert_eq!(config.telegram_config.bot_token, "123:current");
+        assert!(!config.audio_config.notification_enabled);
+        assert_eq!(config.ui_config.theme, "light");
+        assert_eq!(config.active_profile.as_deref(), Some("away"));
+    }
+
+    #[test]
+    fn test_validate_profile_name() {
+        assert!(validate_profile_name("focus").is_ok());
+        assert!(validate_profile_name("专注").is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name(" away").is_err());
+        assert!(validate_profile_name(&"x".repeat(app::MAX_PROFILE_NAME_LENGTH + 1)).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::env::apply_env_overrides;
+use super::settings::AppConfig;
+use super::storage::load_config_file;
+use crate::constants::app;
+use crate::log_important;
+
+/// 项目配置中允许覆盖的分区及其对应的 `AppConfig` 字段
+const OVERRIDE_SECTIONS: &[(&str, &str)] = &[
+    ("mcp", "mcp_config"),
+    ("reply", "reply_config"),
+    ("telegram", "telegram_config"),
+    ("custom_prompts", "custom_prompt_config"),
+];
+
+/// 加载全局配置并合并项目配置（`project_path` 为空或项目没有配置文件时等同于全局配置）
+///
+/// 环境变量覆盖在项目配置之后应用，优先级最高
+pub fn load_project_config(project_path: Option<&str>) -> Result<AppConfig> {
+    let mut config = load_config_file()?;
+    apply_project_config(&mut config, project_path);
+    apply_env_overrides(&mut config);
+    Ok(config)
+}
+
+/// 将项目的 `.cunzhi/config.toml` 合并到配置中
+///
+/// 项目配置无效时只记录警告并保持全局配置，不影响工具调用
+pub fn apply_project_config(config: &mut AppConfig, project_path: Option<&str>) {
+    let Some(config_file) = project_path.and_then(|path| find_project_config_file(Path::new(path)))
+    else {
+        return;
+    };
+
+    let result = std::fs::read_to_string(&config_file)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| merge_project_overrides(config, &content));
+    match result {
+        Ok(()) => log::debug!("已合并项目配置: {:?}", config_file),
+        Err(e) => log_important!(warn, "项目配置 {:?} 无效，使用全局配置: {}", config_file, e),
+    }
+}
+
+/// 当前进程处理的 MCP 请求文件，命令行解析 `--mcp-request` 参数后设置
+static REQUEST_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// 记录当前进程处理的 MCP 请求文件，只在解析命令行参数后调用一次
+pub fn set_request_file(request_file: &str) {
+    if REQUEST_FILE.set(PathBuf::from(request_file)).is_err() {
+        log::warn!("MCP 请求文件已设置，忽略: {}", request_file);
+    }
+}
+
+/// 当前进程处理的 MCP 请求文件，不是 MCP 请求弹窗进程时返回 `None`
+pub fn request_file() -> Option<&'static Path> {
+    REQUEST_FILE.get().map(PathBuf::as_path)
+}
+
+/// 当前进程处理的 MCP 请求所属的项目路径
+///
+/// 从 [`request_file`] 指向的请求文件中读取，进程内只读取一次
+pub fn request_project_path() -> Option<&'static str> {
+    static PROJECT_PATH: OnceLock<Option<String>> = OnceLock::new();
+
+    PROJECT_PATH
+        .get_or_init(|| {
+            let content = std::fs::read_to_string(request_file()?).ok()?;
+            let request: Value = serde_json::from_str(&content).ok()?;
+            request
+                .get("project_path")
+                .and_then(Value::as_str)
+                .filter(|path| !path.trim().is_empty())
+                .map(str::to_string)
+        })
+        .as_deref()
+}
+
+/// 从项目路径向上查找 `.cunzhi/config.toml`
+///
+/// 跳过用户主目录，`~/.cunzhi` 存放的是日志等全局数据
+fn find_project_config_file(project_path: &Path) -> Option<PathBuf> {
+    let home_dir = dirs::home_dir();
+
+    project_path
+        .ancestors()
+        .filter(|dir| home_dir.as_deref() != Some(*dir))
+        .map(|dir| dir.join(app::PROJECT_CONFIG_DIR).join(app::PROJECT_CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// 把 TOML 格式的项目配置合并到配置中
+///
+/// 表按字段递归合并，数组整体替换；自定义prompt按 `id` 合并，新的 `id` 追加到列表末尾
+fn merge_project_overrides(config: &mut AppConfig, content: &str) -> Result<()> {
+    let overrides: Value = toml::from_str(content)?;
+    let Value::Object(overrides) = overrides else {
+        anyhow::bail!("项目配置必须是 TOML 表");
+    };
+
+    let mut merged = serde_json::to_value(&*config)?;
+    for (section, value) in overrides {
+        let Some((_, field)) = OVERRIDE_SECTIONS.iter().find(|(name, _)| *name == section) else {
+            anyhow::bail!(
+                "不支持的分区 [{}]，只能覆盖 {}",
+                section,
+                OVERRIDE_SECTIONS
+                    .iter()
+                    .map(|(name, _)| format!("[{}]", name))
+                    .collect::<Vec<_>>()
+                    .join("、")
+            );
+        };
+
+        let target = &mut merged[*field];
+        if section == "custom_prompts" {
+            merge_custom_prompts(target, value)?;
+        } else {
+            merge_values(target, value);
+        }
+    }
+
+    *config = serde_json::from_value(merged)?;
+    Ok(())
+}
+
+/// 递归合并 JSON 值，`overlay` 中的表与 `base` 合并，其他值直接替换
+fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge_values(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// 合并自定义prompt配置，`prompts` 数组按 `id` 合并
+fn merge_custom_prompts(base: &mut Value, mut overlay: Value) -> Result<()> {
+    let overlay_prompts = match overlay.as_object_mut().and_then(|o| o.remove("prompts")) {
+        Some(Value::Array(prompts)) => prompts,
+        Some(_) => anyhow::bail!("custom_prompts.prompts 必须是数组"),
+        None => Vec::new(),
+    };
+    merge_values(base, overlay);
+
+    let Some(prompts) = base.get_mut("prompts").and_then(Value::as_array_mut) else {
+        anyhow::bail!("全局配置中的自定义prompt列表无效");
+    };
+    for prompt in overlay_prompts {
+        let id = prompt
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("项目配置中的自定义prompt缺少 id"))?;
+        match prompts.iter_mut().find(|p| p.get("id").and_then(Value::as_str) == Some(id)) {
+            Some(existing) => merge_values(existing, prompt),
+            None => prompts.push(prompt),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_project_overrides() {
+        let mut config = AppConfig::default();
+        config.telegram_config.bot_token = "123:global".to_string();
+        let existing_id = config.custom_prompt_config.prompts[0].id.clone();
+
+        let content = format!(
+            r#"
+            [mcp]
+            acemcp_exclude_patterns = ["vendor"]
+
+            [mcp.tools]
+            sou = true
+
+            [reply]
+            continue_prompt = "按项目规范继续"
+
+            [telegram]
+            chat_id = "-100123"
+
+            [[custom_prompts.prompts]]
+            id = "{}"
+            content = "项目内容"
+
+            [[custom_prompts.prompts]]
+            id = "project-only"
+            name = "项目提示词"
+            content = "只在项目中使用"
+            sort_order = 99
+            created_at = ""
+            updated_at = ""
+            "#,
+            existing_id
+        );
+        merge_project_overrides(&mut config, &content).unwrap();
+
+        assert_eq!(config.mcp_config.acemcp_exclude_patterns, Some(vec!["vendor".to_string()]));
+        assert_eq!(config.mcp_config.tools.get("sou"), Some(&true));
+        // 未覆盖的工具开关保持全局值
+        assert_eq!(config.mcp_config.tools.get("zhi"), Some(&true));
+        assert_eq!(config.reply_config.continue_prompt, "按项目规范继续");
+        assert_eq!(config.telegram_config.chat_id, "-100123");
+        assert_eq!(config.telegram_config.bot_token, "123:global");
+
+        let prompts = &config.custom_prompt_config.prompts;
+        assert_eq!(prompts[0].id, existing_id);
+        assert_eq!(prompts[0].content, "项目内容");
+        assert_eq!(prompts.last().unwrap().id, "project-only");
+    }
+
+    #[test]
+    fn test_unknown_section_is_rejected() {
+        let mut config = AppConfig::default();
+        let result = merge_project_overrides(&mut config, "[ui]\ntheme = \"light\"");
+        assert!(result.is_err());
+        assert_eq!(config.ui_config.theme, AppConfig::default().ui_config.theme);
+    }
+}
@@ -0,0 +1,466 @@
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use fs2::FileExt;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use super::settings::AppConfig;
+use super::storage::get_config_dir;
+
+/// 系统钥匙串中的服务名
+const KEYRING_SERVICE: &str = "cunzhi";
+
+/// 配置文件中引用密钥的前缀，如 `secret:telegram.bot_token`
+const SECRET_REF_PREFIX: &str = "secret:";
+
+/// 加密密钥文件名（钥匙串不可用时使用）
+const SECRETS_FILE_NAME: &str = "secrets.enc";
+
+/// 密钥文件锁，GUI 和 MCP 服务器进程通过它串行读写
+const LOCK_FILE_NAME: &str = "secrets.lock";
+
+/// PBKDF2 迭代次数
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Telegram Bot Token 的密钥名
+pub const TELEGRAM_BOT_TOKEN: &str = "telegram.bot_token";
+
+/// acemcp 认证令牌的密钥名
+pub const ACEMCP_TOKEN: &str = "acemcp.token";
+
+/// 加密密钥文件内容
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedSecrets {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 密钥存储
+///
+/// 优先使用系统钥匙串（Linux 为 Secret Service），不可用时退回配置目录下
+/// 权限为 0600 的加密文件。文件密钥由本机标识经 PBKDF2 派生，防止配置目录
+/// 被复制或同步后泄露令牌，但不能防御同一用户下的本地进程
+pub struct SecretStore {
+    dir: PathBuf,
+    use_keyring: bool,
+}
+
+impl SecretStore {
+    /// 打开配置目录下的密钥存储
+    pub fn open() -> Result<Self> {
+        Ok(Self {
+            dir: get_config_dir()?,
+            use_keyring: keyring_available(),
+        })
+    }
+
+    /// 读取密钥，不存在时返回 `None`
+    pub fn get(&self, name: &str) -> Result<Option<String>> {
+        if self.use_keyring {
+            match keyring::Entry::new(KEYRING_SERVICE, name)?.get_password() {
+                Ok(value) => return Ok(Some(value)),
+                // 钥匙串中没有时再查看文件（可能是钥匙串不可用时保存的）
+                Err(keyring::Error::NoEntry) => {}
+                Err(e) => return Err(anyhow::anyhow!("读取系统钥匙串失败: {}", e)),
+            }
+        }
+
+        self.with_file_lock(|secrets| Ok(secrets.remove(name)))
+    }
+
+    /// 保存密钥，空值等同于删除
+    pub fn set(&self, name: &str, value: &str) -> Result<()> {
+        if value.is_empty() {
+            return self.delete(name);
+        }
+
+        if self.use_keyring {
+            keyring::Entry::new(KEYRING_SERVICE, name)?
+                .set_password(value)
+                .map_err(|e| anyhow::anyhow!("写入系统钥匙串失败: {}", e))?;
+            // 清理文件中的旧值
+            return self.update_file(|secrets| {
+                secrets.remove(name);
+            });
+        }
+
+        self.update_file(|secrets| {
+            secrets.insert(name.to_string(), value.to_string());
+        })
+    }
+
+    /// 删除密钥
+    pub fn delete(&self, name: &str) -> Result<()> {
+        if self.use_keyring {
+            match keyring::Entry::new(KEYRING_SERVICE, name)?.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(e) => return Err(anyhow::anyhow!("删除系统钥匙串密钥失败: {}", e)),
+            }
+        }
+
+        self.update_file(|secrets| {
+            secrets.remove(name);
+        })
+    }
+
+    /// 在文件锁保护下修改并保存加密文件（内容不变时不写入）
+    fn update_file(&self, f: impl FnOnce(&mut HashMap<String, String>)) -> Result<()> {
+        self.with_file_lock(|secrets| {
+            let before = secrets.clone();
+            f(secrets);
+            if *secrets == before {
+                return Ok(());
+            }
+            write_encrypted_file(&self.dir.join(SECRETS_FILE_NAME), secrets)
+        })
+    }
+
+    /// 在文件锁保护下读取加密文件并执行操作
+    fn with_file_lock<T>(
+        &self,
+        f: impl FnOnce(&mut HashMap<String, String>) -> Result<T>,
+    ) -> Result<T> {
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join(LOCK_FILE_NAME))?;
+        lock_file.lock_exclusive()?;
+
+        let result = read_encrypted_file(&self.dir.join(SECRETS_FILE_NAME))
+            .and_then(|mut secrets| f(&mut secrets));
+
+        lock_file.unlock()?;
+        result
+    }
+}
+
+/// 系统钥匙串是否可用（进程内只探测一次）
+fn keyring_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+    *AVAILABLE.get_or_init(|| {
+        let available = keyring::Entry::new(KEYRING_SERVICE, "probe")
+            .and_then(|entry| entry.get_password())
+            .map_or_else(|e| matches!(e, keyring::Error::NoEntry), |_| true);
+        if !available {
+            log::info!("系统钥匙串不可用，密钥将保存到加密文件");
+        }
+        available
+    })
+}
+
+/// 读取加密文件，文件不存在时视为空
+fn read_encrypted_file(path: &Path) -> Result<HashMap<String, String>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let file: EncryptedSecrets = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let salt = general_purpose::STANDARD.decode(file.salt)?;
+    let nonce = general_purpose::STANDARD.decode(file.nonce)?;
+    let mut in_out = general_purpose::STANDARD.decode(file.ciphertext)?;
+
+    let nonce = Nonce::try_assume_unique_for_key(&nonce)
+        .map_err(|_| anyhow::anyhow!("密钥文件格式无效"))?;
+    let plaintext = derive_key(&salt)?
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .map_err(|_| anyhow::anyhow!("密钥文件解密失败，可能来自其他设备"))?;
+
+    Ok(serde_json::from_slice(plaintext)?)
+}
+
+/// 加密写入密钥文件（权限 0600，先写临时文件再重命名）
+fn write_encrypted_file(path: &Path, secrets: &HashMap<String, String>) -> Result<()> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| anyhow::anyhow!("生成随机数失败"))?;
+
+    let mut in_out = serde_json::to_vec(secrets)?;
+    derive_key(&salt)?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+        .map_err(|_| anyhow::anyhow!("加密密钥文件失败"))?;
+
+    let file = EncryptedSecrets {
+        salt: general_purpose::STANDARD.encode(salt),
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(in_out),
+    };
+
+    let temp_path = path.with_extension("tmp");
+    write_private_file(&temp_path, serde_json::to_string_pretty(&file)?.as_bytes())?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// 写入仅当前用户可读写的文件
+fn write_private_file(path: &Path, content: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = OpenOptions::new();
+    options.create(true).truncate(true).write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// 由本机标识和随机盐派生文件加密密钥
+fn derive_key(salt: &[u8]) -> Result<LessSafeKey> {
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).expect("迭代次数不能为0"),
+        salt,
+        machine_secret().as_bytes(),
+        &mut key,
+    );
+
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| anyhow::anyhow!("创建加密密钥失败"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// 本机标识：machine-id、用户名和主目录
+fn machine_secret() -> String {
+    let machine_id = ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    let home = dirs::home_dir().unwrap_or_default();
+
+    format!("{}|{}|{}", machine_id.trim(), user, home.display())
+}
+
+/// 生成配置文件中的密钥引用
+fn secret_ref(name: &str) -> String {
+    format!("{}{}", SECRET_REF_PREFIX, name)
+}
+
+/// 配置中需要单独存储的密钥字段
+fn secret_fields(config: &mut AppConfig) -> [(&'static str, &mut String); 2] {
+    [
+        (TELEGRAM_BOT_TOKEN, &mut config.telegram_config.bot_token),
+        (ACEMCP_TOKEN, config.mcp_config.acemcp_token.get_or_insert_with(String::new)),
+    ]
+}
+
+/// 当前进程中密钥的读写状态
+#[derive(Debug, Default)]
+struct SecretCache {
+    /// 已写入存储的密钥值，避免每次保存配置都访问钥匙串
+    stored: HashMap<String, String>,
+    /// 读取失败的密钥名，保存配置时保留其引用，避免一次读取失败导致令牌丢失
+    unresolved: HashSet<String>,
+}
+
+impl SecretCache {
+    fn set_unresolved(&mut self, name: &str, unresolved: bool) {
+        if unresolved {
+            self.unresolved.insert(name.to_string());
+        } else {
+            self.unresolved.remove(name);
+        }
+    }
+}
+
+/// 进程共享的密钥状态
+fn secret_cache() -> &'static Mutex<SecretCache> {
+    static CACHE: OnceLock<Mutex<SecretCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(SecretCache::default()))
+}
+
+/// 将配置中的密钥引用替换为实际值，返回是否发现了需要迁移的明文密钥
+///
+/// 密钥读取失败时记录警告并置空，不影响其他配置的加载；保存配置时会保留原引用
+pub fn resolve_secrets(config: &mut AppConfig) -> bool {
+    let store = SecretStore::open();
+    let mut cache = secret_cache().lock().unwrap_or_else(|e| e.into_inner());
+    resolve_secrets_with(config, &mut cache, |reference| match &store {
+        Ok(store) => store.get(reference),
+        Err(e) => Err(anyhow::anyhow!("打开密钥存储失败: {}", e)),
+    })
+}
+
+fn resolve_secrets_with(
+    config: &mut AppConfig,
+    cache: &mut SecretCache,
+    mut get: impl FnMut(&str) -> Result<Option<String>>,
+) -> bool {
+    let mut has_plaintext = false;
+
+    for (name, field) in secret_fields(config) {
+        let Some(reference) = field.strip_prefix(SECRET_REF_PREFIX).map(str::to_string) else {
+            has_plaintext |= !field.is_empty();
+            continue;
+        };
+
+        match get(&reference) {
+            Ok(value) => {
+                *field = value.unwrap_or_default();
+                cache.set_unresolved(name, false);
+                cache.stored.insert(name.to_string(), field.clone());
+            }
+            Err(e) => {
+                log::warn!("读取密钥 {} 失败: {}", name, e);
+                field.clear();
+                cache.set_unresolved(name, true);
+            }
+        }
+    }
+
+    // 空的 acemcp 令牌保持为 None
+    if config.mcp_config.acemcp_token.as_deref() == Some("") {
+        config.mcp_config.acemcp_token = None;
+    }
+
+    has_plaintext
+}
+
+/// 将配置中的密钥保存到存储，并替换为引用（用于写入配置文件前）
+pub fn externalize_secrets(config: &mut AppConfig) -> Result<()> {
+    let store = SecretStore::open()?;
+    let mut cache = secret_cache().lock().unwrap_or_else(|e| e.into_inner());
+    externalize_secrets_with(config, &mut cache, |name, value| store.set(name, value))
+}
+
+fn externalize_secrets_with(
+    config: &mut AppConfig,
+    cache: &mut SecretCache,
+    mut set: impl FnMut(&str, &str) -> Result<()>,
+) -> Result<()> {
+    for (name, field) in secret_fields(config) {
+        // 读取失败的密钥未被修改时保留原引用，存储中的令牌保持不变
+        if field.is_empty() && cache.unresolved.contains(name) {
+            *field = secret_ref(name);
+            continue;
+        }
+
+        if cache.stored.get(name) != Some(&*field) {
+            // 空值会删除存储中的旧令牌
+            set(name, field)?;
+            cache.set_unresolved(name, false);
+            cache.stored.insert(name.to_string(), field.clone());
+        }
+
+        if !field.is_empty() {
+            *field = secret_ref(name);
+        }
+    }
+
+    if config.mcp_config.acemcp_token.as_deref() == Some("") {
+        config.mcp_config.acemcp_token = None;
+    }
+
+    Ok(())
+}
+
+/// 生成用于界面展示的掩码，如 `1234****wxyz`
+pub fn mask_secret(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    match chars.len() {
+        0 => String::new(),
+        len if len <= 8 => "*".repeat(8),
+        len => {
+            let head: String = chars[..4].iter().collect();
+            let tail: String = chars[len - 4..].iter().collect();
+            format!("{}****{}", head, tail)
+        }
+    }
+}
+
+/// 界面提交的值等于当前值的掩码时，说明用户没有修改，返回当前值
+pub fn unmask_secret(submitted: &str, current: &str) -> String {
+    if !current.is_empty() && submitted == mask_secret(current) {
+        current.to_string()
+    } else {
+        submitted.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypted_file_round_trip() {
+        let dir = crate::utils::test_dir("secrets");
+        let path = dir.join("secrets.enc");
+        let mut secrets = HashMap::new();
+        secrets.insert(TELEGRAM_BOT_TOKEN.to_string(), "123456:ABCDEF".to_string());
+
+        write_encrypted_file(&path, &secrets).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("ABCDEF"));
+        assert_eq!(read_encrypted_file(&path).unwrap(), secrets);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_read_keeps_secret_reference() {
+        let mut cache = SecretCache::default();
+        let mut config = AppConfig::default();
+        config.telegram_config.bot_token = secret_ref(TELEGRAM_BOT_TOKEN);
+
+        resolve_secrets_with(&mut config, &mut cache, |_| Err(anyhow::anyhow!("钥匙串不可用")));
+        assert_eq!(config.telegram_config.bot_token, "");
+
+        // 保存配置时不写入存储（不会删除令牌），并写回原引用
+        let mut written = Vec::new();
+        externalize_secrets_with(&mut config, &mut cache, |name, value| {
+            written.push((name.to_string(), value.to_string()));
+            Ok(())
+        })
+        .unwrap();
+        assert!(written.iter().all(|(name, _)| name != TELEGRAM_BOT_TOKEN));
+        assert_eq!(config.telegram_config.bot_token, secret_ref(TELEGRAM_BOT_TOKEN));
+
+        // 用户设置新令牌后正常保存
+        config.telegram_config.bot_token = "123456:NEW".to_string();
+        externalize_secrets_with(&mut config, &mut cache, |name, value| {
+            written.push((name.to_string(), value.to_string()));
+            Ok(())
+        })
+        .unwrap();
+        assert!(written.contains(&(TELEGRAM_BOT_TOKEN.to_string(), "123456:NEW".to_string())));
+        assert!(!cache.unresolved.contains(TELEGRAM_BOT_TOKEN));
+    }
+
+    #[test]
+    fn test_mask_secret() {
+        assert_eq!(mask_secret(""), "");
+        assert_eq!(mask_secret("short"), "********");
+        assert_eq!(mask_secret("123456:ABCDEFwxyz"), "1234****wxyz");
+
+        let token = "123456:ABCDEFwxyz";
+        assert_eq!(unmask_secret(&mask_secret(token), token), token);
+        assert_eq!(unmask_secret("654321:NEW", token), "654321:NEW");
+    }
+}
@@ -1,10 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Mutex;
-use crate::constants::{window, theme, audio, mcp, telegram, font};
+use crate::constants::{app, window, theme, audio, mcp, telegram, font, network};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppConfig {
+    #[serde(default = "default_config_version")]
+    pub config_version: u32, // 配置结构版本，用于迁移
     #[serde(default = "default_ui_config")]
     pub ui_config: UiConfig, // UI相关配置（主题、窗口、置顶等）
     #[serde(default = "default_audio_config")]
@@ -19,6 +21,10 @@ pub struct AppConfig {
     pub custom_prompt_config: CustomPromptConfig, // 自定义prompt配置
     #[serde(default = "default_shortcut_config")]
     pub shortcut_config: ShortcutConfig, // 自定义快捷键配置
+    #[serde(default = "default_network_config")]
+    pub network_config: NetworkConfig, // 网络代理配置（所有出站HTTP请求共用）
+    #[serde(default = "default_active_profile")]
+    pub active_profile: Option<String>, // 最近切换到的配置方案名称
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -188,6 +194,20 @@ pub struct TelegramConfig {
     pub hide_frontend_popup: bool, // 是否隐藏前端弹窗，仅使用Telegram交互
     #[serde(default = "default_telegram_api_base_url")]
     pub api_base_url: String, // Telegram API基础URL
+    #[serde(default = "default_telegram_message_thread_id")]
+    pub message_thread_id: Option<i32>, // 群组论坛话题ID（可选）
+    #[serde(default = "default_telegram_allowed_user_ids")]
+    pub allowed_user_ids: Vec<i64>, // 允许回复的用户ID，为空时不限制
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NetworkConfig {
+    #[serde(default = "default_proxy_url")]
+    pub proxy_url: String, // 代理地址，支持 http://、https://、socks5://，为空时使用系统代理环境变量
+    #[serde(default = "default_no_proxy")]
+    pub no_proxy: Vec<String>, // 不走代理的主机或网段，如 localhost、.internal.com、10.0.0.0/8
+    #[serde(default = "default_ca_bundle_path")]
+    pub ca_bundle_path: String, // 额外信任的CA证书（PEM，可包含多个证书），为空时不加载
 }
 
 #[derive(Debug)]
@@ -202,6 +222,7 @@ pub struct AppState {
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            config_version: default_config_version(),
             ui_config: default_ui_config(),
             audio_config: default_audio_config(),
             reply_config: default_reply_config(),
@@ -209,6 +230,8 @@ impl Default for AppConfig {
             telegram_config: default_telegram_config(),
             custom_prompt_config: default_custom_prompt_config(),
             shortcut_config: default_shortcut_config(),
+            network_config: default_network_config(),
+            active_profile: default_active_profile(),
         }
     }
 }
@@ -225,6 +248,10 @@ impl Default for AppState {
 }
 
 // 默认值函数
+pub fn default_config_version() -> u32 {
+    app::CONFIG_VERSION
+}
+
 pub fn default_ui_config() -> UiConfig {
     UiConfig {
         theme: default_theme(),
@@ -260,9 +287,23 @@ pub fn default_telegram_config() -> TelegramConfig {
         chat_id: default_telegram_chat_id(),
         hide_frontend_popup: default_telegram_hide_frontend_popup(),
         api_base_url: default_telegram_api_base_url(),
+        message_thread_id: default_telegram_message_thread_id(),
+        allowed_user_ids: default_telegram_allowed_user_ids(),
     }
 }
 
+pub fn default_network_config() -> NetworkConfig {
+    NetworkConfig {
+        proxy_url: default_proxy_url(),
+        no_proxy: default_no_proxy(),
+        ca_bundle_path: default_ca_bundle_path(),
+    }
+}
+
+pub fn default_active_profile() -> Option<String> {
+    None
+}
+
 pub fn default_custom_prompt_config() -> CustomPromptConfig {
     CustomPromptConfig {
         prompts: default_custom_prompts(),
@@ -398,6 +439,26 @@ pub fn default_telegram_api_base_url() -> String {
     telegram::API_BASE_URL.to_string()
 }
 
+pub fn default_telegram_message_thread_id() -> Option<i32> {
+    telegram::DEFAULT_MESSAGE_THREAD_ID
+}
+
+pub fn default_telegram_allowed_user_ids() -> Vec<i64> {
+    telegram::DEFAULT_ALLOWED_USER_IDS.to_vec()
+}
+
+pub fn default_proxy_url() -> String {
+    String::new()
+}
+
+pub fn default_no_proxy() -> Vec<String> {
+    network::DEFAULT_NO_PROXY.iter().map(|host| host.to_string()).collect()
+}
+
+pub fn default_ca_bundle_path() -> String {
+    String::new()
+}
+
 impl WindowConfig {
     // 获取当前模式的宽度
     pub fn current_width(&self) -> f64 {
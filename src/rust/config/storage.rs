@@ -1,40 +1,98 @@
 use anyhow::Result;
-use std::fs;
-use std::path::PathBuf;
+use fs2::FileExt;
+use serde_json::Value;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, LogicalSize, Manager, State};
 
+use super::diagnostics::{validate_config, ConfigDiagnostic};
+use super::env::apply_env_overrides;
+use super::migration::{backup_config_file, migrate_config};
+use super::project::{apply_project_config, request_project_path};
+use super::secrets::{externalize_secrets, resolve_secrets};
 use super::settings::{AppConfig, AppState, default_shortcuts};
+use crate::utils::set_current_network_config;
+
+/// 配置文件锁，GUI 和 MCP 进程通过它串行读写配置文件
+const LOCK_FILE_NAME: &str = "config.lock";
 
 pub fn get_config_path(_app: &AppHandle) -> Result<PathBuf> {
     // 使用与独立配置相同的路径，确保一致性
     get_standalone_config_path()
 }
 
-pub async fn save_config(state: &State<'_, AppState>, app: &AppHandle) -> Result<()> {
-    let config_path = get_config_path(app)?;
+/// 以“读取-修改-写入”的方式更新配置
+///
+/// 修改先作用于内存中的配置，再只把实际改动的字段合并到文件锁内重新读取的磁盘配置上：
+/// 界面提交的整段设置中来自项目配置和环境变量的值不会写入配置文件，
+/// 也不会覆盖其他进程（例如 MCP 进程保存的条件性prompt状态）在此期间写入的字段。
+/// 写入成功后同步更新内存中的配置
+pub async fn update_config<T>(
+    state: &State<'_, AppState>,
+    app: &AppHandle,
+    f: impl FnOnce(&mut AppConfig) -> T,
+) -> Result<T> {
+    let (current, updated, result) = {
+        let current = state
+            .config
+            .lock()
+            .map_err(|e| anyhow::anyhow!("获取配置锁失败: {}", e))?
+            .clone();
+        let mut updated = current.clone();
+        let result = f(&mut updated);
+        (serde_json::to_value(&current)?, serde_json::to_value(&updated)?, result)
+    };
 
-    // 确保目录存在
-    if let Some(parent) = config_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
+    write_config_changes(state, app, |config| {
+        let mut value = serde_json::to_value(&*config)?;
+        apply_changes(&mut value, &current, &updated);
+        *config = serde_json::from_value(value)?;
+        Ok(())
+    })?;
 
-    let config = state
-        .config
-        .lock()
-        .map_err(|e| anyhow::anyhow!("获取配置失败: {}", e))?;
-    let config_json = serde_json::to_string_pretty(&*config)?;
+    Ok(result)
+}
+
+/// 把修改直接作用于文件锁内重新读取的磁盘配置（用于切换配置方案等不依赖界面当前值的修改），
+/// 写入成功后同步更新内存中的配置
+pub async fn update_file_config<T>(
+    state: &State<'_, AppState>,
+    app: &AppHandle,
+    f: impl FnOnce(&mut AppConfig) -> T,
+) -> Result<T> {
+    write_config_changes(state, app, |config| Ok(f(config)))
+}
 
-    // 写入文件
-    fs::write(&config_path, config_json)?;
+/// 在文件锁内读取磁盘配置、应用修改并写回，然后用新配置（应用覆盖后）更新内存中的配置
+fn write_config_changes<T>(
+    state: &State<'_, AppState>,
+    app: &AppHandle,
+    f: impl FnOnce(&mut AppConfig) -> Result<T>,
+) -> Result<T> {
+    let config_path = get_config_path(app)?;
 
-    // 强制刷新文件系统缓存
-    if let Ok(file) = std::fs::OpenOptions::new().write(true).open(&config_path) {
-        let _ = file.sync_all();
-    }
+    let (mut config, result) = with_config_lock(|| {
+        let mut config = if config_path.exists() {
+            read_config_file(&config_path)?
+        } else {
+            AppConfig::default()
+        };
+        let result = f(&mut config)?;
+        write_config_file(&config_path, &config)?;
+        Ok((config, result))
+    })?;
+
+    apply_runtime_overrides(&mut config);
+    set_current_network_config(&config.network_config);
+    *state
+        .config
+        .lock()
+        .map_err(|e| anyhow::anyhow!("获取配置锁失败: {}", e))? = config;
 
     log::debug!("配置已保存到: {:?}", config_path);
 
-    Ok(())
+    Ok(result)
 }
 
 /// Tauri应用专用的配置加载函数
@@ -42,11 +100,9 @@ pub async fn load_config(state: &State<'_, AppState>, app: &AppHandle) -> Result
     let config_path = get_config_path(app)?;
 
     if config_path.exists() {
-        let config_json = fs::read_to_string(&config_path)?;
-        let mut config: AppConfig = serde_json::from_str(&config_json)?;
-
-        // 合并默认快捷键配置，确保新的默认快捷键被添加
-        merge_default_shortcuts(&mut config);
+        let mut config = with_config_lock(|| read_config_file(&config_path))?;
+        apply_runtime_overrides(&mut config);
+        set_current_network_config(&config.network_config);
 
         let mut config_guard = state
             .config
@@ -119,32 +175,95 @@ pub async fn load_config_and_apply_window_settings(
     Ok(())
 }
 
-/// 独立加载配置文件（用于MCP服务器等独立进程）
+/// 独立加载配置文件（用于MCP服务器等独立进程），加载后应用环境变量覆盖
 pub fn load_standalone_config() -> Result<AppConfig> {
+    let mut config = load_config_file()?;
+    apply_env_overrides(&mut config);
+    Ok(config)
+}
+
+/// 只加载配置文件，不应用项目配置和环境变量覆盖
+pub(super) fn load_config_file() -> Result<AppConfig> {
     let config_path = get_standalone_config_path()?;
 
     if config_path.exists() {
-        let config_json = fs::read_to_string(config_path)?;
-        let mut config: AppConfig = serde_json::from_str(&config_json)?;
-
-        // 合并默认快捷键配置
-        merge_default_shortcuts(&mut config);
-
-        Ok(config)
+        with_config_lock(|| read_config_file(&config_path))
     } else {
         // 如果配置文件不存在，返回默认配置
         Ok(AppConfig::default())
     }
 }
 
+/// 读取配置文件的原始内容（已执行版本迁移），配置文件不存在时返回 `None`
+pub(super) fn load_raw_config_file() -> Result<Option<serde_json::Value>> {
+    let config_path = get_standalone_config_path()?;
+    if !config_path.exists() {
+        return Ok(None);
+    }
+
+    let config_json = with_config_lock(|| Ok(fs::read_to_string(&config_path)?))?;
+    let mut value: serde_json::Value = serde_json::from_str(&config_json)?;
+    migrate_config(&mut value);
+    Ok(Some(value))
+}
+
+/// 独立保存配置文件（用于不依赖Tauri状态的独立进程）
+pub fn save_standalone_config(config: &AppConfig) -> Result<()> {
+    let config_path = get_standalone_config_path()?;
+    with_config_lock(|| write_config_file(&config_path, config))?;
+
+    log::debug!("配置已保存到: {:?}", config_path);
+
+    Ok(())
+}
+
+/// 独立进程中以“读取-修改-写入”的方式更新配置，见 [`update_config`]
+pub fn update_standalone_config<T>(f: impl FnOnce(&mut AppConfig) -> T) -> Result<T> {
+    let config_path = get_standalone_config_path()?;
+
+    with_config_lock(|| {
+        let mut config = if config_path.exists() {
+            read_config_file(&config_path)?
+        } else {
+            AppConfig::default()
+        };
+        let result = f(&mut config);
+        write_config_file(&config_path, &config)?;
+        Ok(result)
+    })
+}
+
+/// 只加载网络配置（应用环境变量覆盖），不解析令牌引用，也不会改写配置文件
+pub fn load_standalone_network_config() -> Result<super::settings::NetworkConfig> {
+    let mut config = match load_raw_config_file()? {
+        Some(value) => serde_json::from_value(value)?,
+        None => AppConfig::default(),
+    };
+    apply_env_overrides(&mut config);
+    validate_config(&mut config);
+    Ok(config.network_config)
+}
+
 /// 独立加载Telegram配置（用于MCP模式下的配置检查）
 pub fn load_standalone_telegram_config() -> Result<super::settings::TelegramConfig> {
     let config = load_standalone_config()?;
     Ok(config.telegram_config)
 }
 
-/// 获取独立配置文件路径（不依赖Tauri）
-fn get_standalone_config_path() -> Result<PathBuf> {
+/// 检查配置文件并返回诊断结果（不修改配置文件），配置文件不存在时没有问题
+pub fn check_standalone_config() -> Result<Vec<ConfigDiagnostic>> {
+    let config_path = get_standalone_config_path()?;
+    if !config_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let (mut config, _) = with_config_lock(|| parse_config_file(&config_path))?;
+    resolve_secrets(&mut config);
+    Ok(validate_config(&mut config))
+}
+
+/// 获取应用配置目录（不依赖Tauri），目录不存在时自动创建
+pub fn get_config_dir() -> Result<PathBuf> {
     // 使用标准的配置目录
     let config_dir = dirs::config_dir()
         .ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?
@@ -153,31 +272,237 @@ fn get_standalone_config_path() -> Result<PathBuf> {
     // 确保目录存在
     fs::create_dir_all(&config_dir)?;
 
-    Ok(config_dir.join("config.json"))
+    Ok(config_dir)
+}
+
+/// 获取独立配置文件路径（不依赖Tauri）
+fn get_standalone_config_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join("config.json"))
+}
+
+/// 应用只作用于当前进程的覆盖：先合并请求所属项目的配置，再应用环境变量
+fn apply_runtime_overrides(config: &mut AppConfig) {
+    apply_project_config(config, request_project_path());
+    apply_env_overrides(config);
+}
+
+/// 把 `before` 到 `after` 之间实际改动的部分应用到 `target`
+///
+/// 对象按键递归比较，元素都带 `id` 的数组（如项目配置合并进来的自定义prompt）按 `id` 逐项比较，
+/// 其他值整体替换，没有改动的部分保留 `target` 中的值
+fn apply_changes(target: &mut Value, before: &Value, after: &Value) {
+    if before == after {
+        return;
+    }
+
+    if let (Value::Array(before), Value::Array(after)) = (before, after) {
+        if let Some(target) = target.as_array_mut().filter(|target| is_keyed(target)) {
+            if is_keyed(before) && is_keyed(after) {
+                apply_item_changes(target, before, after);
+                return;
+            }
+        }
+    }
+
+    match (before, after, target.as_object_mut()) {
+        (Value::Object(before), Value::Object(after), Some(target)) => {
+            target.retain(|key, _| !before.contains_key(key) || after.contains_key(key));
+            for (key, value) in after {
+                match (before.get(key), target.get_mut(key)) {
+                    (Some(old), Some(existing)) => apply_changes(existing, old, value),
+                    _ => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        _ => *target = after.clone(),
+    }
+}
+
+/// 按 `id` 把数组元素的改动应用到 `target`：删除的元素被移除，修改过的元素逐字段合并，
+/// 只存在于内存中（来自覆盖）且没有修改的元素不会写入
+fn apply_item_changes(target: &mut Vec<Value>, before: &[Value], after: &[Value]) {
+    let item_id = |item: &Value| item.get("id").and_then(Value::as_str).map(str::to_string);
+    let find = |items: &[Value], id: &Option<String>| items.iter().position(|item| item_id(item) == *id);
+
+    target.retain(|item| {
+        let id = item_id(item);
+        find(before, &id).is_none() || find(after, &id).is_some()
+    });
+
+    for item in after {
+        let id = item_id(item);
+        let old = find(before, &id).map(|index| &before[index]);
+        match (old, find(target, &id)) {
+            (Some(old), Some(index)) => apply_changes(&mut target[index], old, item),
+            (Some(old), None) if old == item => {}
+            (_, Some(index)) => target[index] = item.clone(),
+            (_, None) => target.push(item.clone()),
+        }
+    }
+}
+
+/// 数组中的元素是否都是带字符串 `id` 的对象
+fn is_keyed(items: &[Value]) -> bool {
+    items
+        .iter()
+        .all(|item| item.get("id").and_then(Value::as_str).is_some())
+}
+
+/// 在配置文件锁保护下执行操作
+///
+/// 锁不可重入，`f` 内部只能调用不加锁的 `read_config_file` / `write_config_file`
+pub(super) fn with_config_lock<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(get_config_dir()?.join(LOCK_FILE_NAME))?;
+    lock_file.lock_exclusive()?;
+
+    let result = f();
+
+    lock_file.unlock()?;
+    result
+}
+
+/// 读取配置文件：执行版本迁移、合并默认快捷键并解析令牌引用
+///
+/// 发生版本迁移或发现明文令牌时改写配置文件，版本迁移前先备份旧文件
+fn read_config_file(config_path: &Path) -> Result<AppConfig> {
+    let (mut config, migrated_from) = parse_config_file(config_path)?;
+
+    let has_plaintext_secrets = resolve_secrets(&mut config);
+    if has_plaintext_secrets {
+        log::info!("将配置文件中的明文令牌迁移到密钥存储");
+    }
+
+    // 无效值在内存中修正，下次保存时写回
+    for diagnostic in validate_config(&mut config) {
+        log::warn!("配置问题 {}", diagnostic);
+    }
+
+    let mut should_rewrite = has_plaintext_secrets;
+    if let Some(from_version) = migrated_from {
+        match backup_config_file(config_path, from_version) {
+            Ok(backup_path) => {
+                log::info!("配置已从版本 {} 迁移，旧文件备份到: {:?}", from_version, backup_path);
+                should_rewrite = true;
+            }
+            // 备份失败时不改写文件，下次加载会重新迁移
+            Err(e) => {
+                log::warn!("{}", e);
+                should_rewrite = false;
+            }
+        }
+    }
+
+    if should_rewrite {
+        if let Err(e) = write_config_file(config_path, &config) {
+            log::warn!("改写配置文件失败: {}", e);
+        }
+    }
+
+    Ok(config)
+}
+
+/// 解析配置文件：在内存中执行版本迁移并合并默认快捷键，返回迁移前的版本号
+fn parse_config_file(config_path: &Path) -> Result<(AppConfig, Option<u32>)> {
+    let config_json = fs::read_to_string(config_path)?;
+    let mut value: serde_json::Value = serde_json::from_str(&config_json)?;
+    let migrated_from = migrate_config(&mut value);
+    let mut config: AppConfig = serde_json::from_value(value)?;
+
+    // 合并默认快捷键配置，确保新的默认快捷键被添加
+    merge_default_shortcuts(&mut config);
+
+    Ok((config, migrated_from))
+}
+
+/// 写入配置文件，令牌保存到密钥存储，文件中只保留引用
+///
+/// 先写入并刷新临时文件再重命名，写入中断时不会留下半个配置文件
+fn write_config_file(config_path: &Path, config: &AppConfig) -> Result<()> {
+    let mut config = config.clone();
+    externalize_secrets(&mut config)?;
+
+    let temp_path = config_path.with_extension("json.tmp");
+    let mut temp_file = fs::File::create(&temp_path)?;
+    temp_file.write_all(serde_json::to_string_pretty(&config)?.as_bytes())?;
+    temp_file.sync_all()?;
+    fs::rename(&temp_path, config_path)?;
+    Ok(())
 }
 
 /// 合并默认快捷键配置，确保新的默认快捷键被添加到现有配置中
 fn merge_default_shortcuts(config: &mut AppConfig) {
     let default_shortcuts = default_shortcuts();
 
-    // 遍历所有默认快捷键
+    // 只添加用户配置中不存在的快捷键，旧默认值的升级见 `config::migration`
     for (key, default_binding) in default_shortcuts {
-        if !config.shortcut_config.shortcuts.contains_key(&key) {
-            // 如果用户配置中不存在，则添加
-            config.shortcut_config.shortcuts.insert(key, default_binding);
-        } else if key == "enhance" {
-            // 特殊处理：更新增强快捷键的默认值从 Shift+Enter 到 Ctrl+Shift+Enter
-            let existing_binding = config.shortcut_config.shortcuts.get(&key).unwrap();
-
-            // 检查是否是旧的默认值 (Shift+Enter)
-            if existing_binding.key_combination.key == "Enter"
-                && !existing_binding.key_combination.ctrl
-                && existing_binding.key_combination.shift
-                && !existing_binding.key_combination.alt
-                && !existing_binding.key_combination.meta {
-                // 更新为新的默认值 (Ctrl+Shift+Enter)
-                config.shortcut_config.shortcuts.insert(key, default_binding);
-            }
-        }
+        config.shortcut_config.shortcuts.entry(key).or_insert(default_binding);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_changed_fields_are_persisted() {
+        let mut file_config = AppConfig::default();
+        file_config.telegram_config.chat_id = "file-chat".to_string();
+
+        // 内存中的配置带有环境变量覆盖，界面提交整段 Telegram 设置
+        let mut current = file_config.clone();
+        current.telegram_config.chat_id = "env-chat".to_string();
+        current.telegram_config.enabled = true;
+        let mut updated = current.clone();
+        updated.telegram_config.hide_frontend_popup = true;
+
+        // 其他进程在此期间修改了配置文件
+        file_config.reply_config.enable_continue_reply = false;
+
+        let mut value = serde_json::to_value(&file_config).unwrap();
+        apply_changes(
+            &mut value,
+            &serde_json::to_value(&current).unwrap(),
+            &serde_json::to_value(&updated).unwrap(),
+        );
+        let saved: AppConfig = serde_json::from_value(value).unwrap();
+
+        assert!(saved.telegram_config.hide_frontend_popup);
+        assert_eq!(saved.telegram_config.chat_id, "file-chat");
+        assert!(!saved.telegram_config.enabled);
+        assert!(!saved.reply_config.enable_continue_reply);
+    }
+
+    #[test]
+    fn test_project_prompts_are_not_persisted() {
+        let file_config = AppConfig::default();
+        let mut current = file_config.clone();
+        // 项目配置修改了一个已有prompt，并添加了一个只属于项目的prompt
+        current.custom_prompt_config.prompts[0].content = "项目内容".to_string();
+        let mut project_prompt = current.custom_prompt_config.prompts[0].clone();
+        project_prompt.id = "project-only".to_string();
+        current.custom_prompt_config.prompts.push(project_prompt);
+
+        // 界面只修改了第二个prompt的名称
+        let mut updated = current.clone();
+        updated.custom_prompt_config.prompts[1].name = "新名称".to_string();
+
+        let mut value = serde_json::to_value(&file_config).unwrap();
+        apply_changes(
+            &mut value,
+            &serde_json::to_value(&current).unwrap(),
+            &serde_json::to_value(&updated).unwrap(),
+        );
+        let saved: AppConfig = serde_json::from_value(value).unwrap();
+
+        let prompts = &saved.custom_prompt_config.prompts;
+        assert_eq!(prompts.len(), file_config.custom_prompt_config.prompts.len());
+        assert_eq!(prompts[0].content, file_config.custom_prompt_config.prompts[0].content);
+        assert_eq!(prompts[1].name, "新名称");
     }
 }
@@ -27,6 +27,30 @@ pub const LICENSE: &str = "MIT";
 /// 配置文件名
 pub const CONFIG_FILE_NAME: &str = "config.json";
 
+/// 配置文件结构版本，`AppConfig` 发生不兼容变更时递增并在 `config::migration` 中添加迁移
+pub const CONFIG_VERSION: u32 = 1;
+
+/// 项目配置目录名（位于项目根目录或其上级目录）
+pub const PROJECT_CONFIG_DIR: &str = ".cunzhi";
+
+/// 项目配置文件名，内容合并到全局配置之上
+pub const PROJECT_CONFIG_FILE_NAME: &str = "config.toml";
+
+/// 全局记忆目录名（位于配置目录），保存所有项目共用的记忆
+pub const GLOBAL_MEMORY_DIR_NAME: &str = "memory";
+
+/// 配置方案文件名（位于配置目录）
+pub const PROFILES_FILE_NAME: &str = "profiles.json";
+
+/// 配置方案名称的最大长度（字符数）
+pub const MAX_PROFILE_NAME_LENGTH: usize = 32;
+
+/// 覆盖配置的环境变量前缀
+pub const ENV_PREFIX: &str = "CUNZHI_";
+
+/// 环境变量中分隔字段路径的字符串，如 `CUNZHI_MCP_CONFIG__ACEMCP_BASE_URL`
+pub const ENV_PATH_SEPARATOR: &str = "__";
+
 /// 日志文件名前缀
 pub const LOG_FILE_PREFIX: &str = "cunzhi";
 
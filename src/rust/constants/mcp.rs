@@ -9,6 +9,15 @@ pub const TOOL_JI: &str = "ji";
 /// 代码搜索工具标识符
 pub const TOOL_SOU: &str = "sou";
 
+/// 写入 MCP 客户端配置时使用的服务器名称
+pub const SERVER_NAME: &str = "寸止";
+
+/// 按查询回忆时默认返回的记忆条数
+pub const DEFAULT_MEMORY_TOP_K: usize = 10;
+
+/// 回忆结果默认的最大字符数，避免占用过多上下文
+pub const DEFAULT_RECALL_MAX_CHARS: usize = 4000;
+
 /// 默认启用的工具列表
 pub const DEFAULT_ENABLED_TOOLS: &[&str] = &[TOOL_ZHI, TOOL_JI, TOOL_SOU];
 
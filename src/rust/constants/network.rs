@@ -30,6 +30,12 @@ pub const WRITE_TIMEOUT_MS: u64 = 10000;
 /// 最大并发连接数
 pub const MAX_CONCURRENT_CONNECTIONS: usize = 10;
 
+/// 下载大文件（如更新包）时的请求超时时间 (ms)
+pub const DOWNLOAD_TIMEOUT_MS: u64 = 600000;
+
+/// 默认不走代理的主机
+pub const DEFAULT_NO_PROXY: &[&str] = &["localhost", "127.0.0.1", "::1"];
+
 /// 默认用户代理
 pub const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
@@ -12,12 +12,27 @@ pub const DEFAULT_CHAT_ID: &str = "";
 /// 默认隐藏前端弹窗状态
 pub const DEFAULT_HIDE_FRONTEND_POPUP: bool = false;
 
+/// 默认论坛话题 ID（不指定话题）
+pub const DEFAULT_MESSAGE_THREAD_ID: Option<i32> = None;
+
+/// 默认允许回复的用户 ID 列表（为空时不限制）
+pub const DEFAULT_ALLOWED_USER_IDS: &[i64] = &[];
+
 /// Telegram API 基础 URL
 pub const API_BASE_URL: &str = "https://api.telegram.org/bot";
 
 /// 消息最大长度
 pub const MAX_MESSAGE_LENGTH: usize = 4096;
 
+/// 超过该字符数的代码块改为以 .md 文件发送
+pub const LARGE_CODE_BLOCK_CHARS: usize = 3000;
+
+/// /history 命令默认显示的回复条数
+pub const DEFAULT_HISTORY_COUNT: usize = 5;
+
+/// /history 命令最多显示的回复条数
+pub const MAX_HISTORY_COUNT: usize = 20;
+
 /// 请求超时时间 (ms)
 pub const REQUEST_TIMEOUT_MS: u64 = 30000;
 
@@ -27,6 +42,18 @@ pub const MAX_RETRY_COUNT: u32 = 3;
 /// 轮询间隔 (ms)
 pub const POLLING_INTERVAL_MS: u64 = 1000;
 
+/// 按钮回调中请求标识的长度（callback_data 最长 64 字节）
+pub const CALLBACK_TOKEN_LENGTH: usize = 8;
+
+/// 每页显示的选项按钮数量
+pub const OPTIONS_PER_PAGE: usize = 8;
+
+/// 选项按钮文字的最大字符数，超出部分截断
+pub const MAX_BUTTON_LABEL_CHARS: usize = 20;
+
+/// Bot API 可下载的最大文件大小 (字节)
+pub const MAX_DOWNLOAD_FILE_SIZE: u32 = 20 * 1024 * 1024;
+
 // Telegram 配置结构体
 #[derive(Debug, Clone)]
 pub struct TelegramConfig {
@@ -130,7 +157,12 @@ pub fn is_valid_bot_token(token: &str) -> bool {
     !token.is_empty() && token.contains(':')
 }
 
-/// 验证 Chat ID 格式是否有效
+/// 验证 Chat ID 格式是否有效（数字 ID、群组负数 ID 或 @频道用户名）
 pub fn is_valid_chat_id(chat_id: &str) -> bool {
+    if let Some(username) = chat_id.strip_prefix('@') {
+        return !username.is_empty()
+            && username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    }
+
     !chat_id.is_empty() && (chat_id.starts_with('-') || chat_id.chars().all(|c| c.is_ascii_digit()))
 }
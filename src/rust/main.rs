@@ -1,5 +1,4 @@
 use cunzhi::app::{handle_cli_args, run_tauri_app};
-use cunzhi::utils::auto_init_logger;
 use anyhow::Result;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -8,12 +7,7 @@ pub fn run() {
 }
 
 fn main() -> Result<()> {
-    // 初始化日志系统
-    if let Err(e) = auto_init_logger() {
-        eprintln!("初始化日志系统失败: {}", e);
-    }
-
-    // 处理命令行参数
+    // 处理命令行参数（解析参数后初始化日志系统）
     handle_cli_args()
 }
 
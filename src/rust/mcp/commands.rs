@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use tauri::{AppHandle, State};
 
-use crate::config::{AppState, save_config};
+use crate::config::{AppState, update_config};
 use crate::constants::mcp;
 // use crate::mcp::tools::acemcp; // 已迁移到独立模块
 
@@ -80,9 +80,7 @@ pub async fn set_mcp_tool_enabled(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
-        
+    update_config(&state, &app, |config| {
         // 检查工具是否可以禁用
         if tool_id == mcp::TOOL_ZHI && !enabled {
             return Err("寸止工具是必需的，无法禁用".to_string());
@@ -90,11 +88,11 @@ pub async fn set_mcp_tool_enabled(
         
         // 更新工具状态
         config.mcp_config.tools.insert(tool_id.clone(), enabled);
-    }
-    
-    // 保存配置
-    save_config(&state, &app).await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))??;
 
     // 使用日志记录状态变更（在 MCP 模式下会自动输出到文件）
     log::info!("MCP工具 {} 状态已更新为: {}", tool_id, enabled);
@@ -115,18 +113,15 @@ pub async fn reset_mcp_tools_config(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         let default_config = mcp::get_default_mcp_config();
         config.mcp_config.tools.clear();
         for tool in &default_config.tools {
             config.mcp_config.tools.insert(tool.tool_id.clone(), tool.enabled);
         }
-    }
-    
-    // 保存配置
-    save_config(&state, &app).await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     // 使用日志记录配置重置（在 MCP 模式下会自动输出到文件）
     log::info!("MCP工具配置已重置为默认值");
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::fs;
 use std::path::Path;
 
@@ -44,35 +44,47 @@ pub fn create_tauri_popup(request: &PopupRequest) -> Result<String> {
 /// 查找等一下 UI 命令的路径
 ///
 /// 按优先级查找：同目录 -> 全局版本 -> 开发环境
-fn find_ui_command() -> Result<String> {
-    // 1. 优先尝试与当前 MCP 服务器同目录的等一下命令
+pub fn find_ui_command() -> Result<String> {
+    find_command("等一下").ok_or_else(|| {
+        anyhow::anyhow!(
+            "找不到等一下 UI 命令。请确保：\n\
+             1. 已编译项目：cargo build --release\n\
+             2. 或已全局安装：./install.sh\n\
+             3. 或等一下命令在同目录下"
+        )
+    })
+}
+
+/// 查找寸止 MCP 服务器命令的路径，查找顺序与等一下相同
+pub fn find_mcp_server_command() -> Result<String> {
+    find_command("寸止").ok_or_else(|| anyhow::anyhow!("找不到寸止命令，请确保它与等一下在同一目录或已添加到 PATH"))
+}
+
+/// 查找命令：优先使用与当前程序同目录的命令，其次是全局命令
+fn find_command(name: &str) -> Option<String> {
+    // 1. 优先尝试与当前程序同目录的命令
     if let Ok(current_exe) = std::env::current_exe() {
         if let Some(exe_dir) = current_exe.parent() {
-            let local_ui_path = exe_dir.join("等一下");
-            if local_ui_path.exists() && is_executable(&local_ui_path) {
-                return Ok(local_ui_path.to_string_lossy().to_string());
+            let local_path = exe_dir.join(name);
+            if local_path.exists() && is_executable(&local_path) {
+                return Some(local_path.to_string_lossy().to_string());
             }
         }
     }
 
     // 2. 尝试全局命令（最常见的部署方式）
-    if test_command_available("等一下") {
-        return Ok("等一下".to_string());
+    if test_command_available(name) {
+        return Some(name.to_string());
     }
 
-    // 3. 如果都找不到，返回详细错误信息
-    anyhow::bail!(
-        "找不到等一下 UI 命令。请确保：\n\
-         1. 已编译项目：cargo build --release\n\
-         2. 或已全局安装：./install.sh\n\
-         3. 或等一下命令在同目录下"
-    )
+    None
 }
 
 /// 测试命令是否可用
 fn test_command_available(command: &str) -> bool {
     Command::new(command)
         .arg("--version")
+        .stdin(Stdio::null())
         .output()
         .map(|output| output.status.success())
         .unwrap_or(false)
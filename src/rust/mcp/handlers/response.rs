@@ -133,6 +133,11 @@ fn parse_structured_response(response: McpResponse) -> Result<Vec<Content>, McpE
         }
     }
 
+    // 记录回复者（Telegram群组中可能由不同成员回复）
+    if let Some(responder) = &response.metadata.responder {
+        text_parts.push(format!("回复者: {}", responder));
+    }
+
     // 3. 处理图片附件
     let mut image_info_parts = Vec::new();
     for (index, image) in response.images.iter().enumerate() {
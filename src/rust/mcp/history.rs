@@ -0,0 +1,306 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::path::PathBuf;
+
+use crate::config::get_config_dir;
+use crate::mcp::types::PopupRequest;
+
+/// 历史文件名（位于配置目录）
+const HISTORY_FILE_NAME: &str = "request_history.json";
+
+/// 历史锁文件名，MCP服务器和 Telegram 监听进程通过它串行读写
+const LOCK_FILE_NAME: &str = "request_history.lock";
+
+/// 最多保留的请求记录数
+const MAX_RECORDS: usize = 100;
+
+/// 等待中的请求超过该时长仍未回复时视为已失效（MCP服务器被关闭或客户端取消后不会再记录回复）
+const PENDING_EXPIRY_HOURS: i64 = 24;
+
+/// 请求摘要的最大字符数
+const MAX_SUMMARY_CHARS: usize = 200;
+
+/// 单个 zhi 请求的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestRecord {
+    pub id: String,
+    /// 请求消息摘要
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+    /// 回复摘要，`None` 表示仍在等待回复
+    #[serde(default)]
+    pub answer: Option<String>,
+    #[serde(default)]
+    pub answered_at: Option<DateTime<Utc>>,
+}
+
+impl RequestRecord {
+    /// 是否仍在等待回复
+    pub fn is_pending(&self) -> bool {
+        self.answered_at.is_none()
+    }
+
+    /// 是否是已失效的等待中请求
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.is_pending() && now - self.created_at > Duration::hours(PENDING_EXPIRY_HOURS)
+    }
+}
+
+/// 历史文件内容
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryData {
+    #[serde(default)]
+    requests: Vec<RequestRecord>,
+    /// 最近使用记忆功能的项目路径
+    #[serde(default)]
+    last_project_path: Option<String>,
+}
+
+/// 请求历史
+///
+/// 记录等待中和已回复的 zhi 请求，以及最近活跃的项目，供 Telegram 命令查询
+pub struct RequestHistory {
+    dir: PathBuf,
+}
+
+impl RequestHistory {
+    /// 打开配置目录下的请求历史
+    pub fn open() -> Result<Self> {
+        Ok(Self {
+            dir: get_config_dir()?,
+        })
+    }
+
+    /// 记录新的等待中请求，同时清除已失效的等待中请求
+    pub fn record_pending(&self, request: &PopupRequest) -> Result<()> {
+        self.update(|data| {
+            let now = Utc::now();
+            data.requests.retain(|record| !record.is_expired(now));
+            data.requests.push(RequestRecord {
+                id: request.id.clone(),
+                message: summarize(&request.message),
+                created_at: Utc::now(),
+                answer: None,
+                answered_at: None,
+            });
+
+            // 只保留最近的记录
+            let overflow = data.requests.len().saturating_sub(MAX_RECORDS);
+            data.requests.drain(..overflow);
+        })
+    }
+
+    /// 记录请求的回复（包括取消和失败）
+    pub fn record_answer(&self, request_id: &str, answer: &str) -> Result<()> {
+        self.update(|data| {
+            if let Some(record) = data.requests.iter_mut().find(|r| r.id == request_id) {
+                record.answer = Some(summarize(answer));
+                record.answered_at = Some(Utc::now());
+            }
+        })
+    }
+
+    /// 记录最近活跃的项目
+    pub fn record_project(&self, project_path: &str) -> Result<()> {
+        self.update(|data| data.last_project_path = Some(project_path.to_string()))
+    }
+
+    /// 等待回复的请求（按创建时间排序，不包括已失效的请求）
+    pub fn pending(&self) -> Result<Vec<RequestRecord>> {
+        let data = self.with_lock(|data| Ok(std::mem::take(data)))?;
+        let now = Utc::now();
+        Ok(data
+            .requests
+            .into_iter()
+            .filter(|record| record.is_pending() && !record.is_expired(now))
+            .collect())
+    }
+
+    /// 最近 `limit` 条已回复的请求（最新的在前）
+    pub fn recent_answers(&self, limit: usize) -> Result<Vec<RequestRecord>> {
+        let data = self.with_lock(|data| Ok(std::mem::take(data)))?;
+        Ok(data
+            .requests
+            .into_iter()
+            .rev()
+            .filter(|r| !r.is_pending())
+            .take(limit)
+            .collect())
+    }
+
+    /// 最近活跃的项目路径
+    pub fn last_project_path(&self) -> Result<Option<String>> {
+        self.with_lock(|data| Ok(data.last_project_path.take()))
+    }
+
+    /// 在文件锁保护下修改并保存历史
+    fn update(&self, f: impl FnOnce(&mut HistoryData)) -> Result<()> {
+        self.with_lock(|data| {
+            f(data);
+            self.write_data(data)
+        })
+    }
+
+    /// 在文件锁保护下读取历史并执行操作
+    fn with_lock<T>(&self, f: impl FnOnce(&mut HistoryData) -> Result<T>) -> Result<T> {
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join(LOCK_FILE_NAME))?;
+        lock_file.lock_exclusive()?;
+
+        let mut data = self.read_data();
+        let result = f(&mut data);
+
+        lock_file.unlock()?;
+        result
+    }
+
+    /// 读取历史文件，文件不存在或损坏时视为空
+    fn read_data(&self) -> HistoryData {
+        fs::read_to_string(self.dir.join(HISTORY_FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// 写入历史文件（先写临时文件再重命名）
+    fn write_data(&self, data: &HistoryData) -> Result<()> {
+        let path = self.dir.join(HISTORY_FILE_NAME);
+        let temp_path = self.dir.join(format!("{}.tmp", HISTORY_FILE_NAME));
+        fs::write(&temp_path, serde_json::to_string_pretty(data)?)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+}
+
+/// 生成 zhi 回复的摘要：结构化响应取选项和文本，其他内容原样截断
+pub fn summarize_response(response: &str) -> String {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(response) else {
+        return response.to_string();
+    };
+
+    let mut parts = Vec::new();
+    if let Some(options) = value["selected_options"].as_array() {
+        let options: Vec<&str> = options.iter().filter_map(|o| o.as_str()).collect();
+        if !options.is_empty() {
+            parts.push(format!("选项: {}", options.join(", ")));
+        }
+    }
+    if let Some(input) = value["user_input"].as_str().filter(|s| !s.trim().is_empty()) {
+        parts.push(input.trim().to_string());
+    }
+    if let Some(images) = value["images"].as_array().filter(|a| !a.is_empty()) {
+        parts.push(format!("图片 {} 张", images.len()));
+    }
+    if value["metadata"]["source"]
+        .as_str()
+        .is_some_and(|source| source.ends_with("continue"))
+    {
+        parts.push("继续".to_string());
+    }
+
+    if parts.is_empty() {
+        "（空回复）".to_string()
+    } else {
+        parts.join(" | ")
+    }
+}
+
+/// 压缩空白并截断到 [`MAX_SUMMARY_CHARS`]
+fn summarize(text: &str) -> String {
+    let compact = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if compact.chars().count() > MAX_SUMMARY_CHARS {
+        let truncated: String = compact.chars().take(MAX_SUMMARY_CHARS).collect();
+        format!("{}…", truncated)
+    } else {
+        compact
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    fn request(id: &str, message: &str) -> PopupRequest {
+        PopupRequest {
+            id: id.to_string(),
+            message: message.to_string(),
+            predefined_options: None,
+            is_markdown: true,
+            project_path: None,
+        }
+    }
+
+    #[test]
+    fn test_pending_requests_become_answers() {
+        let dir = test_dir("history");
+        let history = RequestHistory { dir: dir.clone() };
+
+        for id in ["a", "b", "c"] {
+            history.record_pending(&request(id, &format!("请求  {}\n", id))).unwrap();
+        }
+        history.record_answer("a", "好的").unwrap();
+        history.record_answer("c", "取消").unwrap();
+        // 未知请求的回复被忽略
+        history.record_answer("unknown", "忽略").unwrap();
+
+        let pending = history.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].id.as_str(), pending[0].message.as_str()), ("b", "请求 b"));
+
+        // 最新回复在前，并受 limit 限制
+        let answers = history.recent_answers(5).unwrap();
+        let ids: Vec<&str> = answers.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "a"]);
+        assert_eq!(answers[0].answer.as_deref(), Some("取消"));
+        assert_eq!(history.recent_answers(1).unwrap().len(), 1);
+
+        assert_eq!(history.last_project_path().unwrap(), None);
+        history.record_project("/path/to/project").unwrap();
+        assert_eq!(history.last_project_path().unwrap().as_deref(), Some("/path/to/project"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_history_keeps_recent_records() {
+        let dir = test_dir("history_limit");
+        let history = RequestHistory { dir: dir.clone() };
+
+        for index in 0..MAX_RECORDS + 5 {
+            history.record_pending(&request(&index.to_string(), "请求")).unwrap();
+        }
+        let pending = history.pending().unwrap();
+        assert_eq!(pending.len(), MAX_RECORDS);
+        assert_eq!(pending[0].id, "5");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stale_pending_requests_expire() {
+        let dir = test_dir("history_expiry");
+        let history = RequestHistory { dir: dir.clone() };
+
+        // 模拟服务器被关闭后遗留的请求
+        history.record_pending(&request("stale", "请求")).unwrap();
+        history
+            .update(|data| data.requests[0].created_at -= Duration::hours(PENDING_EXPIRY_HOURS + 1))
+            .unwrap();
+        assert!(history.pending().unwrap().is_empty());
+
+        history.record_pending(&request("fresh", "请求")).unwrap();
+        let ids: Vec<String> = history
+            .with_lock(|data| Ok(data.requests.iter().map(|r| r.id.clone()).collect()))
+            .unwrap();
+        assert_eq!(ids, vec!["fresh"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
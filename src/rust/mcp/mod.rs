@@ -3,6 +3,7 @@ pub mod server;
 pub mod tools;
 pub mod types;
 pub mod handlers;
+pub mod history;
 pub mod utils;
 
 pub use commands::*;
@@ -9,7 +9,7 @@ use std::collections::HashMap;
 
 use super::tools::{InteractionTool, MemoryTool, AcemcpTool};
 use super::types::{ZhiRequest, JiyiRequest};
-use crate::config::load_standalone_config;
+use crate::config::{load_project_config, load_standalone_config};
 use crate::{log_important, log_debug};
 
 #[derive(Clone)]
@@ -38,9 +38,11 @@ impl ZhiServer {
     }
 
     /// 检查工具是否启用 - 动态读取最新配置
-    fn is_tool_enabled(&self, tool_name: &str) -> bool {
+    ///
+    /// `project_path` 所在项目的 `.cunzhi/config.toml` 可以覆盖全局的工具开关
+    fn is_tool_enabled(&self, tool_name: &str, project_path: Option<&str>) -> bool {
         // 每次都重新读取配置，确保获取最新状态
-        match load_standalone_config() {
+        match load_project_config(project_path) {
             Ok(config) => {
                 let enabled = config.mcp_config.tools.get(tool_name).copied().unwrap_or(true);
                 log_debug!("工具 {} 当前状态: {}", tool_name, enabled);
@@ -86,6 +88,10 @@ impl ServerHandler for ZhiServer {
 
         let mut tools = Vec::new();
 
+        // 列出工具时没有项目路径，使用服务器的工作目录（通常是客户端打开的项目）查找项目配置
+        let current_dir = std::env::current_dir().ok();
+        let project_path = current_dir.as_deref().and_then(|dir| dir.to_str());
+
         // 寸止工具始终可用（必需工具）
         let zhi_schema = serde_json::json!({
             "type": "object",
@@ -102,6 +108,10 @@ impl ServerHandler for ZhiServer {
                 "is_markdown": {
                     "type": "boolean",
                     "description": "消息是否为Markdown格式，默认为true"
+                },
+                "project_path": {
+                    "type": "string",
+                    "description": "当前项目的路径（可选），用于加载项目的 .cunzhi/config.toml"
                 }
             },
             "required": ["message"]
@@ -117,13 +127,13 @@ impl ServerHandler for ZhiServer {
         }
 
         // 记忆管理工具 - 仅在启用时添加
-        if self.is_tool_enabled("ji") {
+        if self.is_tool_enabled("ji", project_path) {
             let ji_schema = serde_json::json!({
                 "type": "object",
                 "properties": {
                     "action": {
                         "type": "string",
-                        "description": "操作类型：记忆(添加记忆), 回忆(获取项目信息)"
+                        "description": "操作类型：记忆/add(添加记忆), 回忆/recall(获取项目信息), 列表/list(列出记忆及ID), 更新/update(修改记忆), 删除/delete(删除记忆)"
                     },
                     "project_path": {
                         "type": "string",
@@ -131,11 +141,43 @@ impl ServerHandler for ZhiServer {
                     },
                     "content": {
                         "type": "string",
-                        "description": "记忆内容（记忆操作时必需）"
+                        "description": "记忆内容（记忆操作时必需，更新操作时为新内容）"
                     },
                     "category": {
                         "type": "string",
-                        "description": "记忆分类：rule(规范规则), preference(用户偏好), pattern(最佳实践), context(项目上下文)"
+                        "description": "记忆分类：rule(规范规则), preference(用户偏好), pattern(最佳实践), context(项目上下文)。添加时默认为 context，列表时用于筛选，更新时用于修改分类"
+                    },
+                    "id": {
+                        "type": "string",
+                        "description": "要更新或删除的记忆：记忆 ID（可通过列表操作获取）或完整的原内容"
+                    },
+                    "tags": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "记忆标签（添加和更新时可选）"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "回忆时的检索关键词，提供时只返回最相关的记忆（支持中文和模糊匹配）"
+                    },
+                    "top_k": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "按关键词回忆时返回的最大条数，默认 10"
+                    },
+                    "max_chars": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "回忆结果的最大字符数，默认 4000"
+                    },
+                    "scope": {
+                        "type": "string",
+                        "enum": ["global", "project", "directory"],
+                        "description": "记忆作用域：global(所有项目共用), project(当前项目，默认), directory(子目录，如 monorepo 子包)。添加时决定写入位置，列表/更新/删除时限定范围，回忆总是合并所有作用域"
+                    },
+                    "directory_path": {
+                        "type": "string",
+                        "description": "子目录路径（使用 directory 作用域时必需，回忆时提供则包含该目录的记忆）"
                     }
                 },
                 "required": ["action", "project_path"]
@@ -152,7 +194,7 @@ impl ServerHandler for ZhiServer {
         }
 
         // 代码搜索工具 - 仅在启用时添加
-        if self.is_tool_enabled("sou") {
+        if self.is_tool_enabled("sou", project_path) {
             tools.push(AcemcpTool::get_tool_definition());
         }
 
@@ -185,14 +227,6 @@ impl ServerHandler for ZhiServer {
                 InteractionTool::zhi(zhi_request).await
             }
             "ji" => {
-                // 检查记忆管理工具是否启用
-                if !self.is_tool_enabled("ji") {
-                    return Err(McpError::internal_error(
-                        "记忆管理工具已被禁用".to_string(),
-                        None
-                    ));
-                }
-
                 // 解析请求参数
                 let arguments_value = request.arguments
                     .map(serde_json::Value::Object)
@@ -201,18 +235,18 @@ impl ServerHandler for ZhiServer {
                 let ji_request: JiyiRequest = serde_json::from_value(arguments_value)
                     .map_err(|e| McpError::invalid_params(format!("参数解析失败: {}", e), None))?;
 
-                // 调用记忆工具
-                MemoryTool::jiyi(ji_request).await
-            }
-            "sou" => {
-                // 检查代码搜索工具是否启用
-                if !self.is_tool_enabled("sou") {
+                // 检查记忆管理工具是否启用
+                if !self.is_tool_enabled("ji", Some(&ji_request.project_path)) {
                     return Err(McpError::internal_error(
-                        "代码搜索工具已被禁用".to_string(),
+                        "记忆管理工具已被禁用".to_string(),
                         None
                     ));
                 }
 
+                // 调用记忆工具
+                MemoryTool::jiyi(ji_request).await
+            }
+            "sou" => {
                 // 解析请求参数
                 let arguments_value = request.arguments
                     .map(serde_json::Value::Object)
@@ -222,6 +256,14 @@ impl ServerHandler for ZhiServer {
                 let acemcp_request: crate::mcp::tools::acemcp::types::AcemcpRequest = serde_json::from_value(arguments_value)
                     .map_err(|e| McpError::invalid_params(format!("参数解析失败: {}", e), None))?;
 
+                // 检查代码搜索工具是否启用
+                if !self.is_tool_enabled("sou", Some(&acemcp_request.project_root_path)) {
+                    return Err(McpError::internal_error(
+                        "代码搜索工具已被禁用".to_string(),
+                        None
+                    ));
+                }
+
                 // 调用代码搜索工具
                 AcemcpTool::search_context(acemcp_request).await
             }
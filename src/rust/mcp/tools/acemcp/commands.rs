@@ -1,6 +1,7 @@
 use tauri::{AppHandle, State};
 
-use crate::config::{AppState, save_config};
+use crate::config::{AppState, NetworkConfig, mask_secret, update_config, unmask_secret};
+use crate::utils::build_http_client;
 use super::{AcemcpTool};
 use super::types::AcemcpRequest;
 use reqwest;
@@ -32,23 +33,18 @@ pub async fn save_acemcp_config(
         base_url = format!("http://{}", base_url);
         log::warn!("BASE_URL 缺少协议，已自动补全为: {}", base_url);
     }
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
-
+    update_config(&state, &app, |config| {
         config.mcp_config.acemcp_base_url = Some(base_url.clone());
-        config.mcp_config.acemcp_token = Some(args.token.clone());
+        // 界面提交的仍是掩码时保留原令牌
+        let current_token = config.mcp_config.acemcp_token.clone().unwrap_or_default();
+        config.mcp_config.acemcp_token = Some(unmask_secret(&args.token, &current_token));
         config.mcp_config.acemcp_batch_size = Some(args.batch_size);
         config.mcp_config.acemcp_max_lines_per_blob = Some(args.max_lines_per_blob);
         config.mcp_config.acemcp_text_extensions = Some(args.text_extensions.clone());
         config.mcp_config.acemcp_exclude_patterns = Some(args.exclude_patterns.clone());
-    }
-
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -73,53 +69,68 @@ pub async fn test_acemcp_connection(
     state: State<'_, AppState>,
 ) -> Result<TestConnectionResult, String> {
     // 获取配置并立即释放锁
-    let (effective_base_url, effective_token) = {
+    let (effective_base_url, effective_token, network_config) = {
         let config = state.config
             .lock()
             .map_err(|e| format!("获取配置失败: {}", e))?;
         
         let base_url = config.mcp_config.acemcp_base_url.as_ref().unwrap_or(&args.base_url).clone();
         let token = config.mcp_config.acemcp_token.as_ref().unwrap_or(&args.token).clone();
-        (base_url, token)
+        (base_url, token, config.network_config.clone())
     };
     
+    Ok(check_acemcp_connection(&effective_base_url, &effective_token, &network_config).await)
+}
+
+/// 测试 acemcp 端点是否可用：先请求健康检查端点，失败时发送一个最小的检索请求
+pub async fn check_acemcp_connection(
+    base_url: &str,
+    token: &str,
+    network_config: &NetworkConfig,
+) -> TestConnectionResult {
     // 验证 URL 格式
-    if !effective_base_url.starts_with("http://") && !effective_base_url.starts_with("https://") {
+    if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
         let msg = "无效的API端点URL格式，必须以 http:// 或 https:// 开头".to_string();
-        return Ok(TestConnectionResult {
+        return TestConnectionResult {
             success: false,
             message: msg,
-        });
+        };
     }
     
     // 验证 token
-    if effective_token.trim().is_empty() {
+    if token.trim().is_empty() {
         let msg = "认证令牌不能为空".to_string();
-        return Ok(TestConnectionResult {
+        return TestConnectionResult {
             success: false,
             message: msg,
-        });
+        };
     }
     
     // 规范化 base_url
-    let normalized_url = if effective_base_url.ends_with('/') {
-        effective_base_url[..effective_base_url.len() - 1].to_string()
+    let normalized_url = if base_url.ends_with('/') {
+        base_url[..base_url.len() - 1].to_string()
     } else {
-        effective_base_url.clone()
+        base_url.to_string()
     };
     
     // 实际测试连接 - 发送一个简单的健康检查请求
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+    let client = match build_http_client(network_config) {
+        Ok(client) => client,
+        Err(e) => {
+            return TestConnectionResult {
+                success: false,
+                message: format!("创建 HTTP 客户端失败: {}", e),
+            };
+        }
+    };
     
     // 尝试访问一个常见的端点（如果存在健康检查端点）
     let test_url = format!("{}/health", normalized_url);
     
     match client
         .get(&test_url)
-        .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", effective_token))
+        .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token))
+        .timeout(std::time::Duration::from_secs(10))
         .send()
         .await
     {
@@ -128,10 +139,10 @@ pub async fn test_acemcp_connection(
             
             if status.is_success() {
                 let msg = format!("连接测试成功！API 端点响应正常 (HTTP {})", status.as_u16());
-                return Ok(TestConnectionResult {
+                return TestConnectionResult {
                     success: true,
                     message: msg,
-                });
+                };
             }
         }
         Err(_) => {
@@ -154,9 +165,10 @@ pub async fn test_acemcp_connection(
     
     match client
         .post(&search_url)
-        .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", effective_token))
+        .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token))
         .header(reqwest::header::CONTENT_TYPE, "application/json")
         .json(&test_payload)
+        .timeout(std::time::Duration::from_secs(10))
         .send()
         .await
     {
@@ -165,25 +177,25 @@ pub async fn test_acemcp_connection(
             
             if status.is_success() {
                 let msg = format!("连接测试成功！API 端点响应正常 (HTTP {})", status.as_u16());
-                Ok(TestConnectionResult {
+                TestConnectionResult {
                     success: true,
                     message: msg,
-                })
+                }
             } else {
                 let body = response.text().await.unwrap_or_default();
                 let msg = format!("API 端点返回错误状态: {} {}", status.as_u16(), status.as_str());
-                Ok(TestConnectionResult {
+                TestConnectionResult {
                     success: false,
                     message: format!("{} - 响应: {}", msg, if body.len() > 200 { format!("{}...", &body[..200]) } else { body }),
-                })
+                }
             }
         }
         Err(e) => {
             let msg = format!("连接失败: {}", e);
-            Ok(TestConnectionResult {
+            TestConnectionResult {
                 success: false,
                 message: msg,
-            })
+            }
         }
     }
 }
@@ -253,7 +265,7 @@ pub async fn get_acemcp_config(state: State<'_, AppState>) -> Result<AcemcpConfi
         .map_err(|e| format!("获取配置失败: {}", e))?;
     Ok(AcemcpConfigResponse {
         base_url: config.mcp_config.acemcp_base_url.clone(),
-        token: config.mcp_config.acemcp_token.clone(),
+        token: config.mcp_config.acemcp_token.as_deref().map(mask_secret),
         batch_size: config.mcp_config.acemcp_batch_size.unwrap_or(10),
         max_lines_per_blob: config.mcp_config.acemcp_max_lines_per_blob.unwrap_or(800),
         text_extensions: config.mcp_config.acemcp_text_extensions.clone().unwrap_or_else(|| {
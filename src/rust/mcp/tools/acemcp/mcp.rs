@@ -9,7 +9,6 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
-use reqwest::Client;
 use ring::digest::{Context as ShaContext, SHA256};
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use serde::{Deserialize, Serialize};
@@ -17,6 +16,7 @@ use encoding_rs::{GBK, WINDOWS_1252, UTF_8};
 use globset::{Glob, GlobSet, GlobSetBuilder};
 
 use super::types::{AcemcpRequest, AcemcpConfig};
+use crate::utils::create_http_client;
 use crate::log_debug;
 use crate::log_important;
 
@@ -32,7 +32,7 @@ impl AcemcpTool {
         );
 
         // 读取配置
-        let mut acemcp_config = Self::get_acemcp_config()
+        let mut acemcp_config = Self::get_acemcp_config(&request.project_root_path)
             .await
             .map_err(|e| McpError::internal_error(format!("获取acemcp配置失败: {}", e), None))?;
 
@@ -49,10 +49,10 @@ impl AcemcpTool {
         }
     }
 
-    /// 获取acemcp配置
-    async fn get_acemcp_config() -> Result<AcemcpConfig> {
+    /// 获取acemcp配置（合并项目的 `.cunzhi/config.toml`）
+    async fn get_acemcp_config(project_root_path: &str) -> Result<AcemcpConfig> {
         // 从配置文件中读取acemcp配置
-        let config = crate::config::load_standalone_config()
+        let config = crate::config::load_project_config(Some(project_root_path))
             .map_err(|e| anyhow::anyhow!("读取配置文件失败: {}", e))?;
         
         Ok(AcemcpConfig {
@@ -454,7 +454,7 @@ async fn index_and_search(config: &AcemcpConfig, project_root_path: &str, query:
         new_blobs.len()
     );
 
-    let client = Client::new();
+    let client = create_http_client()?;
 
     // 批量上传新增 blobs
     let mut uploaded_names: Vec<String> = Vec::new();
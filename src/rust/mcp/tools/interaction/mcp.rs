@@ -3,7 +3,9 @@ use rmcp::{Error as McpError, model::*};
 
 use crate::mcp::{ZhiRequest, PopupRequest};
 use crate::mcp::handlers::{create_tauri_popup, parse_mcp_response};
+use crate::mcp::history::{summarize_response, RequestHistory};
 use crate::mcp::utils::{generate_request_id, popup_error};
+use crate::log_important;
 
 /// 智能代码审查交互工具
 ///
@@ -24,9 +26,30 @@ impl InteractionTool {
                 Some(request.predefined_options)
             },
             is_markdown: request.is_markdown,
+            project_path: request.project_path,
         };
 
-        match create_tauri_popup(&popup_request) {
+        // 记录等待中的请求，供 Telegram /pending 和 /history 命令查询
+        let history = RequestHistory::open().ok();
+        if let Some(history) = &history {
+            if let Err(e) = history.record_pending(&popup_request) {
+                log_important!(warn, "记录请求历史失败: {}", e);
+            }
+        }
+
+        let result = create_tauri_popup(&popup_request);
+
+        if let Some(history) = &history {
+            let answer = match &result {
+                Ok(response) => summarize_response(response),
+                Err(e) => format!("弹窗失败: {}", e),
+            };
+            if let Err(e) = history.record_answer(&popup_request.id, &answer) {
+                log_important!(warn, "记录请求历史失败: {}", e);
+            }
+        }
+
+        match result {
             Ok(response) => {
                 // 解析响应内容，支持文本和图片
                 let content = parse_mcp_response(&response)?;
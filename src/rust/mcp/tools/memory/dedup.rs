@@ -0,0 +1,241 @@
+//! 记忆去重和冲突检测
+//!
+//! 添加记忆前与同分类的已有记忆比较：否定含义、数字和互斥选项都相同，且规范化后的文本相同或
+//! 字符双字组相似度超过阈值时视为重复；主题相近但否定含义或数字不同，或提到同一组互斥选项中的不同项时视为可能冲突
+
+use std::collections::{BTreeSet, HashSet};
+
+use super::search::tokenize;
+use super::types::MemoryEntry;
+
+/// 视为重复的相似度阈值
+const DUPLICATE_THRESHOLD: f64 = 0.85;
+
+/// 否定含义或数字不同时视为冲突所需的相似度（去掉否定词或数字后比较）
+const CONFLICT_THRESHOLD: f64 = 0.6;
+
+/// 否定词，按最长优先匹配
+const NEGATION_WORDS: &[&str] = &[
+    "不要", "不用", "不能", "禁止", "避免", "不", "别", "勿", "never", "don't", "dont", "do not", "not", "no", "avoid",
+];
+
+/// 互斥选项组：两条记忆分别提到同一组中的不同选项时可能冲突
+const EXCLUSIVE_GROUPS: &[&[&str]] = &[
+    &["tab", "tabs", "制表符"],
+    &["space", "spaces", "空格"],
+    &["npm", "pnpm", "yarn", "bun"],
+    &["单引号", "single"],
+    &["双引号", "double"],
+    &["中文", "chinese"],
+    &["英文", "english"],
+];
+
+/// 互斥的选项组之间的对应关系（按 `EXCLUSIVE_GROUPS` 的下标）
+const EXCLUSIVE_PAIRS: &[(usize, usize)] = &[(0, 1), (3, 4), (5, 6)];
+
+/// 在已有记忆中查找与新内容重复的一条
+///
+/// 否定含义、数字或互斥选项不同的两条记忆即使文本相似也不是重复，而是可能冲突
+pub fn find_duplicate<'a>(content: &str, existing: &'a [MemoryEntry]) -> Option<&'a MemoryEntry> {
+    let normalized = normalize(content);
+    let key_terms = KeyTerms::of(content);
+    existing
+        .iter()
+        .filter(|memory| KeyTerms::of(&memory.content) == key_terms)
+        .map(|memory| (memory, normalize(&memory.content)))
+        .filter(|(_, other)| normalized == *other || similarity(&normalized, other) >= DUPLICATE_THRESHOLD)
+        .max_by(|a, b| similarity(&normalized, &a.1).total_cmp(&similarity(&normalized, &b.1)))
+        .map(|(memory, _)| memory)
+}
+
+/// 在已有记忆中查找可能与新内容冲突的条目
+pub fn find_conflicts<'a>(content: &str, existing: &'a [MemoryEntry]) -> Vec<&'a MemoryEntry> {
+    existing
+        .iter()
+        .filter(|memory| is_likely_conflict(content, &memory.content))
+        .collect()
+}
+
+/// 决定记忆含义的关键词：否定、数字和互斥选项，不同时两条记忆不能合并
+#[derive(Debug, PartialEq, Eq)]
+struct KeyTerms {
+    negated: bool,
+    numbers: Vec<String>,
+    options: BTreeSet<(usize, &'static str)>,
+}
+
+impl KeyTerms {
+    fn of(text: &str) -> Self {
+        let lower = text.to_lowercase();
+        Self {
+            negated: strip_negations(&lower).1,
+            numbers: numbers(&lower),
+            options: mentioned_options(&lower).into_iter().collect(),
+        }
+    }
+}
+
+fn is_likely_conflict(a: &str, b: &str) -> bool {
+    let (a_lower, b_lower) = (a.to_lowercase(), b.to_lowercase());
+
+    // 主题相同但一条是否定说法
+    let (a_core, a_negated) = strip_negations(&a_lower);
+    let (b_core, b_negated) = strip_negations(&b_lower);
+    let (a_core, b_core) = (normalize(&a_core), normalize(&b_core));
+    if a_negated != b_negated && similarity(&a_core, &b_core) >= CONFLICT_THRESHOLD {
+        return true;
+    }
+
+    // 主题相同但数字不同（如 2 空格和 4 空格缩进）
+    let without_digits = |text: &str| -> String { text.chars().filter(|ch| !ch.is_ascii_digit()).collect() };
+    if numbers(&a_lower) != numbers(&b_lower)
+        && similarity(&without_digits(&a_core), &without_digits(&b_core)) >= CONFLICT_THRESHOLD
+    {
+        return true;
+    }
+
+    // 提到同一组互斥选项中的不同项（如 npm 和 pnpm、tab 和空格）
+    let a_groups = mentioned_options(&a_lower);
+    let b_groups = mentioned_options(&b_lower);
+    let conflicts_within_group = a_groups.iter().any(|(group, option)| {
+        b_groups.iter().any(|(other_group, other_option)| group == other_group && option != other_option)
+            && !b_groups.contains(&(*group, *option))
+    });
+    let conflicts_across_groups = EXCLUSIVE_PAIRS.iter().any(|(x, y)| {
+        let mentions = |groups: &HashSet<(usize, &str)>, index: usize| groups.iter().any(|(g, _)| *g == index);
+        (mentions(&a_groups, *x) && mentions(&b_groups, *y) && !mentions(&a_groups, *y) && !mentions(&b_groups, *x))
+            || (mentions(&a_groups, *y) && mentions(&b_groups, *x) && !mentions(&a_groups, *x) && !mentions(&b_groups, *y))
+    });
+    conflicts_within_group || conflicts_across_groups
+}
+
+/// 文本中的数字（按出现顺序）
+fn numbers(text: &str) -> Vec<String> {
+    text.split(|ch: char| !ch.is_ascii_digit())
+        .filter(|number| !number.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// 提到的互斥选项（组下标和选项）
+fn mentioned_options(text: &str) -> HashSet<(usize, &'static str)> {
+    let tokens: HashSet<String> = tokenize(text).into_iter().collect();
+    EXCLUSIVE_GROUPS
+        .iter()
+        .enumerate()
+        .flat_map(|(index, group)| {
+            group
+                .iter()
+                .filter(|option| tokens.contains(**option))
+                .map(move |option| (index, *option))
+        })
+        .collect()
+}
+
+/// 去掉否定词，返回剩余文本和否定词出现次数是否为奇数
+fn strip_negations(text: &str) -> (String, bool) {
+    let mut negated = false;
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    'outer: while let Some(ch) = rest.chars().next() {
+        for word in NEGATION_WORDS {
+            if rest.starts_with(word) && is_word_boundary(&stripped, &rest[word.len()..], word) {
+                negated = !negated;
+                rest = &rest[word.len()..];
+                continue 'outer;
+            }
+        }
+        stripped.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    (stripped, negated)
+}
+
+/// 英文否定词需要是完整的单词，中文否定词直接匹配
+fn is_word_boundary(before: &str, after: &str, word: &str) -> bool {
+    if !word.is_ascii() {
+        return true;
+    }
+    let is_letter = |ch: Option<char>| ch.is_some_and(|ch| ch.is_ascii_alphanumeric());
+    !is_letter(before.chars().last()) && !is_letter(after.chars().next())
+}
+
+/// 规范化文本：小写，只保留字母、数字和文字
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|ch| ch.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 字符双字组的 Dice 相似度
+fn similarity(a: &str, b: &str) -> f64 {
+    let bigrams = |text: &str| -> Vec<(char, char)> {
+        let chars: Vec<char> = text.chars().collect();
+        chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    };
+    let (a_bigrams, mut b_bigrams) = (bigrams(a), bigrams(b));
+    if a_bigrams.is_empty() || b_bigrams.is_empty() {
+        return if a == b { 1.0 } else { 0.0 };
+    }
+
+    let total = (a_bigrams.len() + b_bigrams.len()) as f64;
+    let mut shared = 0;
+    for bigram in &a_bigrams {
+        if let Some(position) = b_bigrams.iter().position(|other| other == bigram) {
+            b_bigrams.swap_remove(position);
+            shared += 1;
+        }
+    }
+    2.0 * shared as f64 / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::test_memory as memory;
+
+    #[test]
+    fn test_find_duplicate() {
+        let existing = vec![memory("Use 4-space indent"), memory("前端使用 pnpm 管理依赖")];
+
+        assert_eq!(find_duplicate("use 4 space indent.", &existing).unwrap().content, "Use 4-space indent");
+        assert_eq!(find_duplicate("前端使用pnpm管理依赖。", &existing).unwrap().content, "前端使用 pnpm 管理依赖");
+        assert!(find_duplicate("后端使用 cargo 管理依赖", &existing).is_none());
+    }
+
+    #[test]
+    fn test_similar_contradictions_are_not_duplicates() {
+        let pairs = [
+            (
+                "Use semicolons at the end of TypeScript statements",
+                "Do not use semicolons at the end of TypeScript statements",
+            ),
+            ("提交代码前运行全部单元测试和集成测试", "提交代码前不运行全部单元测试和集成测试"),
+            ("Use 4-space indent", "Use 2-space indent"),
+        ];
+
+        for (existing, content) in pairs {
+            let existing = vec![memory(existing)];
+            assert!(find_duplicate(content, &existing).is_none(), "{} 不应被合并", content);
+            assert_eq!(find_conflicts(content, &existing).len(), 1, "{} 应报告冲突", content);
+        }
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let existing = vec![
+            memory("Use 4-space indent"),
+            memory("前端使用 pnpm 管理依赖"),
+            memory("提交前运行测试"),
+        ];
+
+        let conflicts = |content: &str| -> Vec<String> {
+            find_conflicts(content, &existing).into_iter().map(|m| m.content.clone()).collect()
+        };
+        assert_eq!(conflicts("Use tabs for indent"), vec!["Use 4-space indent"]);
+        assert_eq!(conflicts("前端使用 npm 管理依赖"), vec!["前端使用 pnpm 管理依赖"]);
+        assert_eq!(conflicts("提交前不运行测试"), vec!["提交前运行测试"]);
+        assert!(conflicts("数据库迁移放在 migrations 目录").is_empty());
+    }
+}
@@ -1,40 +1,133 @@
 use anyhow::Result;
-use chrono::Utc;
-use std::fs;
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
 use std::path::{Path, PathBuf};
 
-use super::types::{MemoryEntry, MemoryCategory, MemoryMetadata};
+use super::dedup::{find_conflicts, find_duplicate};
+use super::search::rank_memories;
+use super::types::{AddMemoryResult, MemoryEntry, MemoryCategory, MemoryMetadata, MemoryScope, MemorySource};
+use crate::constants::app;
+
+/// 记忆目录名（位于 git 根目录）
+const MEMORY_DIR_NAME: &str = ".cunzhi-memory";
+
+/// 锁文件名，同一项目的多个进程（MCP 服务器、命令行、Telegram）通过它串行修改记忆文件
+const LOCK_FILE_NAME: &str = "memory.lock";
+
+/// 记忆目录结构版本，1.1.0 起每个条目在行末注释中保存元数据
+const METADATA_VERSION: &str = "1.1.0";
+
+/// 条目元数据注释的开头和结尾，如 `- 内容 <!-- cunzhi:{"id":"..."} -->`
+const ENTRY_METADATA_PREFIX: &str = "<!-- cunzhi:";
+const ENTRY_METADATA_SUFFIX: &str = "-->";
+
+/// 按 ID 前缀查找记忆时要求的最短前缀长度
+const MIN_ID_PREFIX_LENGTH: usize = 4;
+
+const ALL_CATEGORIES: [MemoryCategory; 4] = [
+    MemoryCategory::Rule,
+    MemoryCategory::Preference,
+    MemoryCategory::Pattern,
+    MemoryCategory::Context,
+];
+
+/// 保存在记忆行末注释中的元数据
+#[derive(Debug, Serialize, Deserialize)]
+struct EntryMetadata {
+    id: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    #[serde(default)]
+    source: MemorySource,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
 
 /// 记忆管理器
 pub struct MemoryManager {
     memory_dir: PathBuf,
     project_path: String,
+    scope: MemoryScope,
 }
 
 impl MemoryManager {
     /// 创建新的记忆管理器
+    ///
+    /// 只读取时不创建记忆目录和文件，第一次修改记忆时再创建
     pub fn new(project_path: &str) -> Result<Self> {
         // 规范化项目路径
         let normalized_path = Self::normalize_project_path(project_path)?;
-        let memory_dir = normalized_path.join(".cunzhi-memory");
 
-        // 创建记忆目录，如果失败则说明项目不适合使用记忆功能
-        fs::create_dir_all(&memory_dir)
-            .map_err(|e| anyhow::anyhow!(
-                "无法在git项目中创建记忆目录: {}\n错误: {}\n这可能是因为项目目录没有写入权限。",
-                memory_dir.display(),
-                e
-            ))?;
+        Ok(Self {
+            memory_dir: normalized_path.join(MEMORY_DIR_NAME),
+            project_path: normalized_path.to_string_lossy().to_string(),
+            scope: MemoryScope::Project,
+        })
+    }
+
+    /// 创建全局记忆管理器，记忆保存在配置目录，所有项目共用
+    pub fn global() -> Result<Self> {
+        let memory_dir = crate::config::get_config_dir()?.join(app::GLOBAL_MEMORY_DIR_NAME);
 
-        let manager = Self {
+        Ok(Self {
+            project_path: memory_dir.to_string_lossy().to_string(),
             memory_dir,
+            scope: MemoryScope::Global,
+        })
+    }
+
+    /// 创建目录记忆管理器，用于 monorepo 子包等项目中的子目录，子目录需要在项目的 git 仓库中
+    pub fn directory(project_path: &str, directory_path: &str) -> Result<Self> {
+        let project_root = Self::normalize_project_path(project_path)?;
+        let normalized_path = Self::normalize_directory_path(&project_root, directory_path)?;
+
+        Ok(Self {
+            memory_dir: normalized_path.join(MEMORY_DIR_NAME),
             project_path: normalized_path.to_string_lossy().to_string(),
-        };
+            scope: MemoryScope::Directory,
+        })
+    }
+
+    /// 使用指定的记忆目录，测试中避免读写真实的全局记忆
+    #[cfg(test)]
+    pub(super) fn with_memory_dir(memory_dir: PathBuf, scope: MemoryScope) -> Self {
+        Self {
+            project_path: memory_dir.to_string_lossy().to_string(),
+            memory_dir,
+            scope,
+        }
+    }
+
+    /// 按作用域创建记忆管理器，目录作用域需要提供 `directory_path`
+    pub fn open(scope: MemoryScope, project_path: &str, directory_path: Option<&str>) -> Result<Self> {
+        match scope {
+            MemoryScope::Global => Self::global(),
+            MemoryScope::Project => Self::new(project_path),
+            MemoryScope::Directory => {
+                let directory_path = directory_path
+                    .filter(|path| !path.trim().is_empty())
+                    .ok_or_else(|| anyhow::anyhow!("目录作用域需要提供子目录路径"))?;
+                Self::directory(project_path, directory_path)
+            }
+        }
+    }
+
+    /// 记忆管理器的作用域
+    pub fn scope(&self) -> MemoryScope {
+        self.scope
+    }
 
-        // 初始化记忆文件结构
-        manager.initialize_memory_structure()?;
+    /// 作用域对应的路径：全局记忆为记忆目录，其他为项目或子目录路径
+    pub fn path(&self) -> &str {
+        &self.project_path
+    }
 
-        Ok(manager)
+    /// 项目的记忆目录路径（不创建目录），项目不在 git 仓库中时返回错误
+    pub fn memory_dir_for(project_path: &str) -> Result<PathBuf> {
+        Ok(Self::normalize_project_path(project_path)?.join(MEMORY_DIR_NAME))
     }
 
     /// 规范化项目路径
@@ -85,6 +178,36 @@ impl MemoryManager {
         }
     }
 
+    /// 规范化子目录路径，要求位于项目的 git 仓库中且不是仓库根目录（根目录请使用项目作用域）
+    fn normalize_directory_path(project_root: &Path, directory_path: &str) -> Result<PathBuf> {
+        let normalized_path_str = crate::mcp::utils::decode_and_normalize_path(directory_path)
+            .map_err(|e| anyhow::anyhow!("路径格式错误: {}", e))?;
+        let path = Path::new(&normalized_path_str);
+        let absolute_path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir()?.join(path)
+        };
+        let canonical_path = absolute_path.canonicalize()
+            .map_err(|e| anyhow::anyhow!("子目录不存在: {}\n错误: {}", absolute_path.display(), e))?;
+
+        if !canonical_path.is_dir() {
+            anyhow::bail!("子目录路径不是目录: {}", canonical_path.display());
+        }
+        if canonical_path == project_root {
+            anyhow::bail!("{} 是 git 根目录，请使用项目作用域", canonical_path.display());
+        }
+        // 嵌套的 git 仓库（如子模块）不属于当前项目
+        match Self::find_git_root(&canonical_path) {
+            Some(git_root) if git_root == project_root => Ok(canonical_path),
+            _ => anyhow::bail!(
+                "子目录不在项目 {} 的 git 仓库中: {}",
+                project_root.display(),
+                canonical_path.display()
+            ),
+        }
+    }
+
     /// 手动规范化路径
     ///
     /// 当 canonicalize 失败时的备用方案
@@ -137,77 +260,68 @@ impl MemoryManager {
         None
     }
 
-    /// 初始化记忆文件结构
-    fn initialize_memory_structure(&self) -> Result<()> {
-        // 创建各类记忆文件，使用新的结构化格式
-        let categories = [
-            MemoryCategory::Rule,
-            MemoryCategory::Preference,
-            MemoryCategory::Pattern,
-            MemoryCategory::Context,
-        ];
-
-        for category in categories.iter() {
-            let filename = match category {
-                MemoryCategory::Rule => "rules.md",
-                MemoryCategory::Preference => "preferences.md",
-                MemoryCategory::Pattern => "patterns.md",
-                MemoryCategory::Context => "context.md",
-            };
-
-            let file_path = self.memory_dir.join(filename);
-            if !file_path.exists() {
-                let header_content = self.get_category_header(category);
-                fs::write(&file_path, header_content)?;
-            }
+    /// 添加记忆条目
+    ///
+    /// 同分类中已有相似记忆时合并到该条目（更新时间并合并标签）而不新增，
+    /// 否则新增条目并返回可能与之冲突的已有记忆
+    pub fn add_memory(
+        &self,
+        content: &str,
+        category: MemoryCategory,
+        source: MemorySource,
+        tags: Vec<String>,
+    ) -> Result<AddMemoryResult> {
+        let content = Self::normalize_content(content);
+        if content.is_empty() {
+            anyhow::bail!("记忆内容不能为空");
         }
 
-        // 创建或更新元数据
-        self.update_metadata()?;
-
-        Ok(())
-    }
+        self.with_lock(|| {
+            let existing = self.get_memories_by_category(category)?;
+            let now = Utc::now();
 
-    /// 添加记忆条目
-    pub fn add_memory(&self, content: &str, category: MemoryCategory) -> Result<String> {
-        let id = uuid::Uuid::new_v4().to_string();
-        let now = Utc::now();
+            if let Some(duplicate) = find_duplicate(&content, &existing) {
+                let mut entry = duplicate.clone();
+                for tag in tags {
+                    if !entry.tags.contains(&tag) {
+                        entry.tags.push(tag);
+                    }
+                }
+                entry.updated_at = now;
+                self.rewrite_category_file(category, |memory| {
+                    Some(if memory.id == entry.id { entry.clone() } else { memory })
+                })?;
+                self.update_metadata()?;
 
-        let entry = MemoryEntry {
-            id: id.clone(),
-            content: content.to_string(),
-            category,
-            created_at: now,
-            updated_at: now,
-        };
+                return Ok(AddMemoryResult { entry, merged: true, conflicts: Vec::new() });
+            }
 
-        // 将记忆添加到对应的文件中
-        self.append_to_category_file(&entry)?;
+            let conflicts = find_conflicts(&content, &existing).into_iter().cloned().collect();
+            let entry = MemoryEntry {
+                id: uuid::Uuid::new_v4().to_string(),
+                content,
+                category,
+                created_at: now,
+                updated_at: now,
+                source,
+                tags,
+                scope: self.scope,
+            };
 
-        // 更新元数据
-        self.update_metadata()?;
+            // 将记忆添加到对应的文件中
+            self.append_to_category_file(&entry)?;
 
-        Ok(id)
+            // 更新元数据
+            self.update_metadata()?;
+            Ok(AddMemoryResult { entry, merged: false, conflicts })
+        })
     }
 
     /// 获取所有记忆
     pub fn get_all_memories(&self) -> Result<Vec<MemoryEntry>> {
         let mut memories = Vec::new();
-
-        let categories = [
-            (MemoryCategory::Rule, "rules.md"),
-            (MemoryCategory::Preference, "preferences.md"),
-            (MemoryCategory::Pattern, "patterns.md"),
-            (MemoryCategory::Context, "context.md"),
-        ];
-
-        for (category, filename) in categories.iter() {
-            let file_path = self.memory_dir.join(filename);
-            if file_path.exists() {
-                let content = fs::read_to_string(&file_path)?;
-                let entries = self.parse_memory_file(&content, *category)?;
-                memories.extend(entries);
-            }
+        for category in ALL_CATEGORIES {
+            memories.extend(self.get_memories_by_category(category)?);
         }
 
         // 按更新时间排序
@@ -218,79 +332,344 @@ impl MemoryManager {
 
     /// 获取指定分类的记忆
     pub fn get_memories_by_category(&self, category: MemoryCategory) -> Result<Vec<MemoryEntry>> {
-        let filename = match category {
-            MemoryCategory::Rule => "rules.md",
-            MemoryCategory::Preference => "preferences.md",
-            MemoryCategory::Pattern => "patterns.md",
-            MemoryCategory::Context => "context.md",
-        };
-
-        let file_path = self.memory_dir.join(filename);
+        let file_path = self.memory_dir.join(Self::category_file_name(category));
         if !file_path.exists() {
             return Ok(Vec::new());
         }
 
         let content = fs::read_to_string(&file_path)?;
-        self.parse_memory_file(&content, category)
+        let fallback_time = Self::file_modified_time(&file_path);
+        Ok(Self::parse_memory_lines(&content, category, fallback_time)
+            .into_iter()
+            .flatten()
+            .map(|memory| MemoryEntry { scope: self.scope, ..memory })
+            .collect())
     }
 
-    /// 将记忆条目添加到对应分类文件
-    fn append_to_category_file(&self, entry: &MemoryEntry) -> Result<()> {
-        let filename = match entry.category {
+    /// 按相关度搜索记忆（BM25，支持中文和拼写相近的词），返回最相关的 `limit` 条
+    pub fn search_memories(&self, query: &str, limit: usize) -> Result<Vec<MemoryEntry>> {
+        Ok(rank_memories(&self.get_all_memories()?, query, limit)
+            .into_iter()
+            .map(|(memory, _)| memory)
+            .collect())
+    }
+
+    /// 查找记忆条目
+    ///
+    /// 依次按 ID、唯一的 ID 前缀、完全相同的内容和唯一包含该文本的内容匹配，避免误操作
+    pub fn find_memory(&self, target: &str) -> Result<MemoryEntry> {
+        let target = target.trim();
+        if target.is_empty() {
+            anyhow::bail!("请提供记忆 ID 或内容");
+        }
+
+        let memories = self.get_all_memories()?;
+        let matchers: [&dyn Fn(&MemoryEntry) -> bool; 4] = [
+            &|m| m.id == target,
+            &|m| target.len() >= MIN_ID_PREFIX_LENGTH && m.id.starts_with(target),
+            &|m| m.content == target,
+            &|m| m.content.contains(target),
+        ];
+
+        for matcher in matchers {
+            let matched: Vec<&MemoryEntry> = memories.iter().filter(|m| matcher(m)).collect();
+            match matched.as_slice() {
+                [] => continue,
+                [entry] => return Ok((*entry).clone()),
+                _ => anyhow::bail!("有 {} 条记忆匹配 \"{}\"，请使用记忆 ID", matched.len(), target),
+            }
+        }
+
+        anyhow::bail!("没有找到匹配的记忆: {}", target)
+    }
+
+    /// 按 ID 或完全相同的内容查找记忆条目，供 ji 工具使用
+    pub fn find_memory_exact(&self, target: &str) -> Result<MemoryEntry> {
+        let target = target.trim();
+        let memories = self.get_all_memories()?;
+        if let Some(entry) = memories.iter().find(|m| m.id == target) {
+            return Ok(entry.clone());
+        }
+
+        let matched: Vec<&MemoryEntry> = memories.iter().filter(|m| m.content == target).collect();
+        match matched.as_slice() {
+            [] => anyhow::bail!("没有找到 ID 或内容为 \"{}\" 的记忆", target),
+            [entry] => Ok((*entry).clone()),
+            _ => anyhow::bail!("有 {} 条内容相同的记忆，请使用记忆 ID", matched.len()),
+        }
+    }
+
+    /// 更新记忆条目，未提供的字段保持不变，修改分类时条目会移动到对应的分类文件
+    pub fn update_memory(
+        &self,
+        id: &str,
+        content: Option<&str>,
+        category: Option<MemoryCategory>,
+        tags: Option<Vec<String>>,
+    ) -> Result<MemoryEntry> {
+        let content = content.map(Self::normalize_content).filter(|content| !content.is_empty());
+
+        self.with_lock(|| {
+            let mut entry = self.get_memory(id)?;
+            let old_category = entry.category;
+            if let Some(content) = content {
+                entry.content = content;
+            }
+            if let Some(category) = category {
+                entry.category = category;
+            }
+            if let Some(tags) = tags {
+                entry.tags = tags;
+            }
+            entry.updated_at = Utc::now();
+
+            if entry.category == old_category {
+                self.rewrite_category_file(old_category, |memory| {
+                    Some(if memory.id == entry.id { entry.clone() } else { memory })
+                })?;
+            } else {
+                self.rewrite_category_file(old_category, |memory| (memory.id != entry.id).then_some(memory))?;
+                self.append_to_category_file(&entry)?;
+            }
+
+            self.update_metadata()?;
+            Ok(entry)
+        })
+    }
+
+    /// 按 ID 删除记忆条目，返回被删除的条目
+    pub fn remove_memory(&self, id: &str) -> Result<MemoryEntry> {
+        self.with_lock(|| {
+            let entry = self.get_memory(id)?;
+            self.rewrite_category_file(entry.category, |memory| (memory.id != entry.id).then_some(memory))?;
+
+            self.update_metadata()?;
+            Ok(entry)
+        })
+    }
+
+    /// 按 ID 获取记忆条目
+    fn get_memory(&self, id: &str) -> Result<MemoryEntry> {
+        self.get_all_memories()?
+            .into_iter()
+            .find(|memory| memory.id == id)
+            .ok_or_else(|| anyhow::anyhow!("记忆不存在: {}", id))
+    }
+
+    /// 在记忆锁保护下执行修改，记忆目录不存在时创建
+    fn with_lock<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        fs::create_dir_all(&self.memory_dir)
+            .map_err(|e| anyhow::anyhow!(
+                "无法创建记忆目录: {}\n错误: {}\n这可能是因为项目目录没有写入权限。",
+                self.memory_dir.display(),
+                e
+            ))?;
+
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.memory_dir.join(LOCK_FILE_NAME))?;
+        lock_file.lock_exclusive()?;
+
+        let result = f();
+
+        lock_file.unlock()?;
+        result
+    }
+
+    /// 写入记忆文件（先写临时文件再重命名，避免中断时留下半截文件）
+    fn write_memory_file(path: &Path, content: &str) -> Result<()> {
+        let temp_path = path.with_extension("md.tmp");
+        fs::write(&temp_path, content)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// 分类对应的记忆文件名
+    fn category_file_name(category: MemoryCategory) -> &'static str {
+        match category {
             MemoryCategory::Rule => "rules.md",
             MemoryCategory::Preference => "preferences.md",
             MemoryCategory::Pattern => "patterns.md",
             MemoryCategory::Context => "context.md",
-        };
+        }
+    }
 
-        let file_path = self.memory_dir.join(filename);
+    /// 将记忆条目添加到对应分类文件
+    fn append_to_category_file(&self, entry: &MemoryEntry) -> Result<()> {
+        let file_path = self.memory_dir.join(Self::category_file_name(entry.category));
         let mut content = if file_path.exists() {
             fs::read_to_string(&file_path)?
         } else {
-            format!("# {}\n\n", self.get_category_title(&entry.category))
+            self.get_category_header(&entry.category)
         };
 
-        // 简化格式：一行一个记忆
-        content.push_str(&format!("- {}\n", entry.content));
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&Self::format_memory_line(entry));
+        content.push('\n');
 
-        fs::write(&file_path, content)?;
+        Self::write_memory_file(&file_path, &content)?;
         Ok(())
     }
 
-    /// 解析记忆文件内容 - 简化版本
-    fn parse_memory_file(&self, content: &str, category: MemoryCategory) -> Result<Vec<MemoryEntry>> {
-        let mut memories = Vec::new();
-
-        // 按列表项解析，每个 "- " 开头的行是一个记忆条目
-        for line in content.lines() {
-            let line = line.trim();
-            if line.starts_with("- ") && line.len() > 2 {
-                let content = line[2..].trim(); // 去掉 "- " 前缀
-                if !content.is_empty() {
-                    let entry = MemoryEntry {
-                        id: uuid::Uuid::new_v4().to_string(),
-                        content: content.to_string(),
-                        category,
-                        created_at: Utc::now(),
-                        updated_at: Utc::now(),
-                    };
+    /// 逐行改写分类文件（调用方需持有记忆锁）：`f` 返回替换后的记忆条目，返回 `None` 时删除该条目，
+    /// 未改变的条目和其他行原样保留（手写的条目不会被补充元数据）
+    ///
+    /// 返回文件是否发生变化
+    fn rewrite_category_file(
+        &self,
+        category: MemoryCategory,
+        mut f: impl FnMut(MemoryEntry) -> Option<MemoryEntry>,
+    ) -> Result<bool> {
+        let file_path = self.memory_dir.join(Self::category_file_name(category));
+        if !file_path.exists() {
+            return Ok(false);
+        }
 
-                    memories.push(entry);
+        let content = fs::read_to_string(&file_path)?;
+        let fallback_time = Self::file_modified_time(&file_path);
+        let entries = Self::parse_memory_lines(&content, category, fallback_time);
+
+        let mut rewritten = String::with_capacity(content.len());
+        for (line, entry) in content.lines().zip(entries) {
+            match entry {
+                Some(entry) => {
+                    let original = Self::format_memory_line(&entry);
+                    if let Some(entry) = f(entry) {
+                        let formatted = Self::format_memory_line(&entry);
+                        rewritten.push_str(if formatted == original { line } else { formatted.as_str() });
+                        rewritten.push('\n');
+                    }
+                }
+                None => {
+                    rewritten.push_str(line);
+                    rewritten.push('\n');
                 }
             }
         }
 
-        Ok(memories)
+        if rewritten == content {
+            return Ok(false);
+        }
+        Self::write_memory_file(&file_path, &rewritten)?;
+        Ok(true)
+    }
+
+    /// 解析记忆文件，返回值与文件的每一行一一对应，不是记忆条目的行为 `None`
+    ///
+    /// 每个顶格的 "- " 行是一个记忆条目，元数据保存在行末的注释中；代码块和缩进的子列表不是条目。
+    /// 没有元数据的条目（手写或旧版本）根据分类和内容生成固定的 ID，时间使用文件的修改时间
+    fn parse_memory_lines(
+        content: &str,
+        category: MemoryCategory,
+        fallback_time: DateTime<Utc>,
+    ) -> Vec<Option<MemoryEntry>> {
+        let mut seen_ids = HashSet::new();
+        let mut in_code_block = false;
+
+        content
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                let trimmed = line.trim();
+                if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                    in_code_block = !in_code_block;
+                    return None;
+                }
+                if in_code_block {
+                    return None;
+                }
+                let body = line.trim_end().strip_prefix("- ")?;
+
+                let (content, metadata) = match body.rfind(ENTRY_METADATA_PREFIX) {
+                    Some(start) if body.ends_with(ENTRY_METADATA_SUFFIX) => {
+                        let json = &body[start + ENTRY_METADATA_PREFIX.len()..body.len() - ENTRY_METADATA_SUFFIX.len()];
+                        (body[..start].trim(), serde_json::from_str::<EntryMetadata>(json.trim()).ok())
+                    }
+                    _ => (body.trim(), None),
+                };
+                if content.is_empty() {
+                    return None;
+                }
+
+                let mut entry = match metadata {
+                    Some(metadata) => MemoryEntry {
+                        id: metadata.id,
+                        content: content.to_string(),
+                        category,
+                        created_at: metadata.created_at,
+                        updated_at: metadata.updated_at,
+                        source: metadata.source,
+                        tags: metadata.tags,
+                        scope: MemoryScope::default(),
+                    },
+                    None => MemoryEntry {
+                        id: Self::derived_entry_id(category, content),
+                        content: content.to_string(),
+                        category,
+                        created_at: fallback_time,
+                        updated_at: fallback_time,
+                        source: MemorySource::Manual,
+                        tags: Vec::new(),
+                        scope: MemoryScope::default(),
+                    },
+                };
+
+                // 手动复制的行会带着相同的 ID，按行号重新生成
+                if !seen_ids.insert(entry.id.clone()) {
+                    entry.id = Self::derived_entry_id(category, &format!("{}#{}", content, index));
+                    seen_ids.insert(entry.id.clone());
+                }
+                Some(entry)
+            })
+            .collect()
+    }
+
+    /// 格式化记忆条目为一行：`- 内容 <!-- cunzhi:{元数据} -->`
+    fn format_memory_line(entry: &MemoryEntry) -> String {
+        let metadata = EntryMetadata {
+            id: entry.id.clone(),
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+            source: entry.source,
+            tags: entry.tags.clone(),
+        };
+        let metadata = serde_json::to_string(&metadata).unwrap_or_default();
+        format!(
+            "- {} {}{}{}",
+            entry.content, ENTRY_METADATA_PREFIX, metadata, ENTRY_METADATA_SUFFIX
+        )
+    }
+
+    /// 没有元数据的条目的 ID，由分类和内容决定，多次读取保持一致
+    fn derived_entry_id(category: MemoryCategory, content: &str) -> String {
+        let name = format!("{:?}:{}", category, content);
+        uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, name.as_bytes()).to_string()
+    }
+
+    /// 记忆在文件中占一行，多行内容合并为一行
+    fn normalize_content(content: &str) -> String {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn file_modified_time(path: &Path) -> DateTime<Utc> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now())
     }
 
     /// 获取分类标题
     fn get_category_title(&self, category: &MemoryCategory) -> &str {
-        match category {
-            MemoryCategory::Rule => "开发规范和规则",
-            MemoryCategory::Preference => "用户偏好设置",
-            MemoryCategory::Pattern => "常用模式和最佳实践",
-            MemoryCategory::Context => "项目上下文信息",
-        }
+        category.title()
     }
 
     /// 获取分类文件头部（简化版本）
@@ -304,7 +683,7 @@ impl MemoryManager {
             project_path: self.project_path.clone(),
             last_organized: Utc::now(),
             total_entries: self.get_all_memories()?.len(),
-            version: "1.0.0".to_string(),
+            version: METADATA_VERSION.to_string(),
         };
 
         let metadata_path = self.memory_dir.join("metadata.json");
@@ -322,6 +701,16 @@ impl MemoryManager {
             return Ok("📭 暂无项目记忆".to_string());
         }
 
+        let compressed_info = Self::compress_memories(&all_memories);
+        if compressed_info.is_empty() {
+            Ok("📭 暂无有效项目记忆".to_string())
+        } else {
+            Ok(format!("📚 项目记忆总览: {}", compressed_info.join(" | ")))
+        }
+    }
+
+    /// 按分类压缩汇总记忆，每个分类一项（如 `**规范**: a; b`），分类内保持文件中的顺序
+    pub fn compress_memories(memories: &[MemoryEntry]) -> Vec<String> {
         let mut compressed_info = Vec::new();
 
         // 按分类压缩汇总
@@ -333,7 +722,8 @@ impl MemoryManager {
         ];
 
         for (category, title) in categories.iter() {
-            let memories = self.get_memories_by_category(*category)?;
+            let mut memories: Vec<&MemoryEntry> = memories.iter().filter(|m| m.category == *category).collect();
+            memories.sort_by_key(|m| m.created_at);
             if !memories.is_empty() {
                 let mut items = Vec::new();
                 for memory in memories {
@@ -353,10 +743,124 @@ impl MemoryManager {
             }
         }
 
-        if compressed_info.is_empty() {
-            Ok("📭 暂无有效项目记忆".to_string())
-        } else {
-            Ok(format!("📚 项目记忆总览: {}", compressed_info.join(" | ")))
-        }
+        compressed_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn test_parse_keeps_metadata_and_derives_stable_ids() {
+        let fallback_time = Utc::now();
+        let entry = MemoryEntry {
+            id: "5f0c6c1e-0000-4000-8000-000000000001".to_string(),
+            content: "使用 pnpm 管理依赖".to_string(),
+            category: MemoryCategory::Rule,
+            created_at: fallback_time,
+            updated_at: fallback_time,
+            source: MemorySource::Cli,
+            tags: vec!["build".to_string()],
+            scope: MemoryScope::Project,
+        };
+        let content = format!(
+            "# 开发规范和规则\n\n{}\n- 手写的规则\n普通说明文字\n",
+            MemoryManager::format_memory_line(&entry)
+        );
+
+        let lines = MemoryManager::parse_memory_lines(&content, MemoryCategory::Rule, fallback_time);
+        assert_eq!(lines.len(), content.lines().count());
+
+        let parsed = lines[2].as_ref().unwrap();
+        assert_eq!(parsed.id, entry.id);
+        assert_eq!(parsed.content, entry.content);
+        assert_eq!(parsed.source, MemorySource::Cli);
+        assert_eq!(parsed.tags, entry.tags);
+
+        let manual = lines[3].as_ref().unwrap();
+        assert_eq!(manual.content, "手写的规则");
+        assert_eq!(manual.source, MemorySource::Manual);
+        assert!(lines[4].is_none());
+
+        // 没有元数据的条目每次读取得到相同的 ID
+        let again = MemoryManager::parse_memory_lines(&content, MemoryCategory::Rule, Utc::now());
+        assert_eq!(again[3].as_ref().unwrap().id, manual.id);
+    }
+
+    #[test]
+    fn test_parse_skips_code_blocks_and_nested_lists() {
+        let content = "- 顶层规则\n  - 子列表\n```\n- 代码块\n```\n- 另一条规则\n";
+        let lines = MemoryManager::parse_memory_lines(content, MemoryCategory::Rule, Utc::now());
+        let contents: Vec<&str> = lines.iter().flatten().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["顶层规则", "另一条规则"]);
+    }
+
+    #[test]
+    fn test_parse_regenerates_duplicated_ids() {
+        let content = "- 同一条\n- 同一条\n";
+        let lines = MemoryManager::parse_memory_lines(content, MemoryCategory::Context, Utc::now());
+        let first = lines[0].as_ref().unwrap();
+        let second = lines[1].as_ref().unwrap();
+        assert_ne!(first.id, second.id);
+    }
+
+    fn temp_manager(name: &str) -> MemoryManager {
+        MemoryManager::with_memory_dir(test_dir(&format!("memory_{}", name)), MemoryScope::Project)
+    }
+
+    #[test]
+    fn test_update_moves_entry_to_other_category_file() {
+        let manager = temp_manager("update");
+        let added = manager
+            .add_memory("使用 pnpm 管理依赖", MemoryCategory::Rule, MemorySource::Cli, Vec::new())
+            .unwrap()
+            .entry;
+
+        let updated = manager
+            .update_memory(&added.id, Some("前端使用 pnpm workspace"), Some(MemoryCategory::Pattern), None)
+            .unwrap();
+        assert_eq!(updated.id, added.id);
+        assert_eq!(updated.created_at, added.created_at);
+
+        let read = |category| fs::read_to_string(manager.memory_dir.join(MemoryManager::category_file_name(category))).unwrap();
+        assert!(!read(MemoryCategory::Rule).contains("pnpm"));
+        assert!(read(MemoryCategory::Pattern).contains("- 前端使用 pnpm workspace <!-- cunzhi:"));
+
+        let memories = manager.get_all_memories().unwrap();
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].category, MemoryCategory::Pattern);
+        assert!(manager.update_memory("missing", Some("x"), None, None).is_err());
+
+        fs::remove_dir_all(&manager.memory_dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_and_find_with_duplicate_content() {
+        let manager = temp_manager("remove");
+        fs::create_dir_all(&manager.memory_dir).unwrap();
+        let rules_path = manager.memory_dir.join(MemoryManager::category_file_name(MemoryCategory::Rule));
+        fs::write(&rules_path, "# 开发规范和规则\n\n- 同一条\n- 同一条\n- 另一条\n").unwrap();
+
+        // 内容相同的两条记忆不能按内容查找，只能使用 ID
+        assert!(manager.find_memory_exact("同一条").is_err());
+        let another = manager.find_memory_exact("另一条").unwrap();
+        assert_eq!(manager.find_memory_exact(&another.id).unwrap().content, "另一条");
+
+        let duplicates: Vec<MemoryEntry> = manager
+            .get_all_memories()
+            .unwrap()
+            .into_iter()
+            .filter(|memory| memory.content == "同一条")
+            .collect();
+        assert_eq!(duplicates.len(), 2);
+        manager.remove_memory(&duplicates[1].id).unwrap();
+
+        // 只删除其中一条，其他手写的行保持原样
+        assert_eq!(fs::read_to_string(&rules_path).unwrap(), "# 开发规范和规则\n\n- 同一条\n- 另一条\n");
+        assert_eq!(manager.find_memory_exact("同一条").unwrap().id, duplicates[0].id);
+
+        fs::remove_dir_all(&manager.memory_dir).unwrap();
     }
 }
@@ -1,8 +1,11 @@
 use anyhow::Result;
 use rmcp::{Error as McpError, model::*};
 
-use super::{MemoryManager, MemoryCategory};
+use super::{AddMemoryResult, MemoryManager, MemoryCategory, MemoryEntry, MemoryScope, MemorySource, MemoryStack};
 use crate::mcp::{JiyiRequest, utils::{validate_project_path, project_path_error}};
+use crate::mcp::history::RequestHistory;
+use crate::constants::mcp;
+use crate::log_important;
 
 /// 全局记忆管理工具
 ///
@@ -23,31 +26,85 @@ impl MemoryTool {
             )).into());
         }
 
-        let manager = MemoryManager::new(&request.project_path)
+        let stack = MemoryStack::open(&request.project_path, request.directory_path.as_deref())
             .map_err(|e| McpError::internal_error(format!("创建记忆管理器失败: {}", e), None))?;
+        let scope = request.scope.as_deref().map(parse_scope).transpose()?;
+
+        // 记录最近活跃的项目，供 Telegram /remember 和 /recall 命令使用
+        if let Err(e) = RequestHistory::open().and_then(|h| h.record_project(&request.project_path)) {
+            log_important!(warn, "记录活跃项目失败: {}", e);
+        }
 
         let result = match request.action.as_str() {
-            "记忆" => {
+            "记忆" | "add" => {
                 if request.content.trim().is_empty() {
                     return Err(McpError::invalid_params("缺少记忆内容".to_string(), None));
                 }
 
-                let category = match request.category.as_str() {
-                    "rule" => MemoryCategory::Rule,
-                    "preference" => MemoryCategory::Preference,
-                    "pattern" => MemoryCategory::Pattern,
-                    "context" => MemoryCategory::Context,
-                    _ => MemoryCategory::Context,
-                };
+                // 未知分类按项目上下文处理，与之前的行为一致
+                let category = request.category.as_deref()
+                    .and_then(MemoryCategory::from_name)
+                    .unwrap_or(MemoryCategory::Context);
+                let tags = request.tags.clone().unwrap_or_default();
+                let manager = scoped_manager(&stack, scope.unwrap_or(MemoryScope::Project))?;
 
-                let id = manager.add_memory(&request.content, category)
+                let added = manager.add_memory(&request.content, category, MemorySource::Mcp, tags)
                     .map_err(|e| McpError::internal_error(format!("添加记忆失败: {}", e), None))?;
 
-                format!("✅ 记忆已添加，ID: {}\n📝 内容: {}\n📂 分类: {:?}", id, request.content, category)
+                format_add_result(&added, manager.scope())
+            }
+            "回忆" | "recall" => {
+                if request.top_k == Some(0) || request.max_chars == Some(0) {
+                    return Err(McpError::invalid_params("top_k 和 max_chars 必须大于 0".to_string(), None));
+                }
+                let max_chars = request.max_chars.unwrap_or(mcp::DEFAULT_RECALL_MAX_CHARS);
+                match request.query.as_deref().filter(|query| !query.trim().is_empty()) {
+                    Some(query) => {
+                        let top_k = request.top_k.unwrap_or(mcp::DEFAULT_MEMORY_TOP_K);
+                        let memories = stack.search_memories(query, top_k)
+                            .map_err(|e| McpError::internal_error(format!("检索记忆失败: {}", e), None))?;
+                        format_recall_results(query, &memories, max_chars)
+                    }
+                    None => {
+                        let info = stack.summary()
+                            .map_err(|e| McpError::internal_error(format!("获取项目信息失败: {}", e), None))?;
+                        truncate_chars(&info, max_chars, TRUNCATED_SUMMARY_SUFFIX)
+                    }
+                }
+            }
+            "列表" | "list" => {
+                let category = request.category.as_deref().map(parse_category).transpose()?;
+                let memories = match scope {
+                    Some(scope) => scoped_manager(&stack, scope)?.get_all_memories(),
+                    None => stack.get_all_memories(),
+                }
+                .map_err(|e| McpError::internal_error(format!("读取记忆失败: {}", e), None))?;
+                let memories: Vec<MemoryEntry> = memories
+                    .into_iter()
+                    .filter(|memory| category.is_none() || category == Some(memory.category))
+                    .collect();
+
+                format_memory_list(&memories)
             }
-            "回忆" => {
-                manager.get_project_info()
-                    .map_err(|e| McpError::internal_error(format!("获取项目信息失败: {}", e), None))?
+            "更新" | "update" => {
+                let (manager, entry) = find_target(&stack, scope, &request)?;
+                let content = Some(request.content.as_str()).filter(|content| !content.trim().is_empty());
+                let category = request.category.as_deref().map(parse_category).transpose()?;
+                if content.is_none() && category.is_none() && request.tags.is_none() {
+                    return Err(McpError::invalid_params("更新操作需要提供新的内容、分类或标签".to_string(), None));
+                }
+
+                let updated = manager.update_memory(&entry.id, content, category, request.tags.clone())
+                    .map_err(|e| McpError::internal_error(format!("更新记忆失败: {}", e), None))?;
+
+                format!("✅ 记忆已更新，ID: {}\n📝 内容: {}\n📂 分类: {:?}", updated.id, updated.content, updated.category)
+            }
+            "删除" | "delete" => {
+                let (manager, entry) = find_target(&stack, scope, &request)?;
+                let removed = manager.remove_memory(&entry.id)
+                    .map_err(|e| McpError::internal_error(format!("删除记忆失败: {}", e), None))?;
+
+                format!("🗑️ 记忆已删除，ID: {}\n📝 内容: {}", removed.id, removed.content)
             }
             _ => {
                 return Err(McpError::invalid_params(
@@ -60,3 +117,162 @@ impl MemoryTool {
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 }
+
+fn parse_category(name: &str) -> Result<MemoryCategory, McpError> {
+    MemoryCategory::from_name(name).ok_or_else(|| {
+        McpError::invalid_params(
+            format!("未知的记忆分类: {}，可选 rule、preference、pattern、context", name),
+            None,
+        )
+    })
+}
+
+fn parse_scope(name: &str) -> Result<MemoryScope, McpError> {
+    MemoryScope::from_name(name).ok_or_else(|| {
+        McpError::invalid_params(format!("未知的记忆作用域: {}，可选 global、project、directory", name), None)
+    })
+}
+
+fn scoped_manager(stack: &MemoryStack, scope: MemoryScope) -> Result<&MemoryManager, McpError> {
+    stack
+        .manager(scope)
+        .map_err(|e| McpError::invalid_params(e.to_string(), None))
+}
+
+/// 按请求中的记忆 ID 或完整内容查找要修改的记忆，未指定作用域时在所有作用域中查找
+fn find_target<'a>(
+    stack: &'a MemoryStack,
+    scope: Option<MemoryScope>,
+    request: &JiyiRequest,
+) -> Result<(&'a MemoryManager, MemoryEntry), McpError> {
+    let target = request
+        .id
+        .as_deref()
+        .filter(|id| !id.trim().is_empty())
+        .ok_or_else(|| McpError::invalid_params("缺少记忆 ID（可以先使用列表操作获取）".to_string(), None))?;
+
+    let found = match scope {
+        Some(scope) => {
+            let manager = scoped_manager(stack, scope)?;
+            manager.find_memory_exact(target).map(|entry| (manager, entry))
+        }
+        None => stack.find_memory_exact(target),
+    };
+    found.map_err(|e| McpError::invalid_params(e.to_string(), None))
+}
+
+/// 格式化添加结果：合并到相似记忆时提示已有条目，可能冲突时列出冲突条目供更新或删除
+fn format_add_result(added: &AddMemoryResult, scope: MemoryScope) -> String {
+    let entry = &added.entry;
+    if added.merged {
+        return format!(
+            "♻️ 已有相似的记忆 (similar existing entry {})，已合并而未新增\n📝 已有内容: {}\n📂 分类: {:?}\n🗂️ 作用域: {}\n如需修改已有内容，请使用更新操作",
+            entry.id, entry.content, entry.category, scope.title()
+        );
+    }
+
+    let mut output = format!(
+        "✅ 记忆已添加，ID: {}\n📝 内容: {}\n📂 分类: {:?}\n🗂️ 作用域: {}",
+        entry.id, entry.content, entry.category, scope.title()
+    );
+    if !added.conflicts.is_empty() {
+        output.push_str("\n⚠️ 可能与以下已有记忆冲突，请确认后使用更新或删除操作处理:");
+        for conflict in &added.conflicts {
+            output.push_str(&format!("\n- similar existing entry {}: {}", conflict.id, conflict.content));
+        }
+    }
+    output
+}
+
+/// 列出记忆，每行包含 ID、分类、内容和标签
+fn format_memory_list(memories: &[MemoryEntry]) -> String {
+    if memories.is_empty() {
+        return "📭 暂无项目记忆".to_string();
+    }
+
+    let lines: Vec<String> = memories
+        .iter()
+        .map(|memory| {
+            let tags = if memory.tags.is_empty() {
+                String::new()
+            } else {
+                format!(" [标签: {}]", memory.tags.join(", "))
+            };
+            format!("- {} ({}/{:?}) {}{}", memory.id, memory.scope.title(), memory.category, memory.content, tags)
+        })
+        .collect();
+    format!("📚 共 {} 条记忆:\n{}", memories.len(), lines.join("\n"))
+}
+
+/// 截断提示，回忆总览超出字符预算时使用
+const TRUNCATED_SUMMARY_SUFFIX: &str = "…（已截断，可以使用 query 参数检索相关记忆）";
+
+/// 格式化按关键词回忆的结果，总长度（包括标题和省略提示）不超过 `max_chars`，放不下的条目不再输出
+fn format_recall_results(query: &str, memories: &[MemoryEntry], max_chars: usize) -> String {
+    if memories.is_empty() {
+        return truncate_chars(&format!("📭 没有与「{}」相关的记忆", query), max_chars, "…");
+    }
+
+    let header = format!("📚 与「{}」最相关的记忆:", query);
+    let lines: Vec<String> = memories
+        .iter()
+        .map(|memory| {
+            format!(
+                "\n- [{}/{:?}] {} (ID: {})",
+                memory.scope.title(), memory.category, memory.content, memory.id
+            )
+        })
+        .collect();
+
+    // 从全部显示开始逐条减少，直到加上省略提示后也不超出预算
+    let mut output = String::new();
+    for shown in (0..=lines.len()).rev() {
+        output = header.clone() + &lines[..shown].concat();
+        if shown < lines.len() {
+            output.push_str(&format!("\n…另有 {} 条因长度限制未显示", lines.len() - shown));
+        }
+        if output.chars().count() <= max_chars {
+            return output;
+        }
+    }
+    truncate_chars(&output, max_chars, "…")
+}
+
+/// 按字符数截断文本，结果（包括 `suffix`）不超过 `max_chars` 个字符
+fn truncate_chars(text: &str, max_chars: usize, suffix: &str) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let suffix_chars = suffix.chars().count();
+    if max_chars <= suffix_chars {
+        return text.chars().take(max_chars).collect();
+    }
+    let truncated: String = text.chars().take(max_chars - suffix_chars).collect();
+    format!("{}{}", truncated, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::test_memory as memory;
+
+    #[test]
+    fn test_recall_output_stays_within_max_chars() {
+        let memories: Vec<MemoryEntry> = (0..5).map(|i| memory(&format!("第 {} 条记忆内容", i))).collect();
+        let full = format_recall_results("记忆", &memories, usize::MAX);
+        assert!(!full.contains("另有"));
+
+        for max_chars in [1, 10, 30, 60, 100, full.chars().count() - 1] {
+            let output = format_recall_results("记忆", &memories, max_chars);
+            assert!(output.chars().count() <= max_chars, "{} 超出 {}", output, max_chars);
+        }
+        assert!(format_recall_results("记忆", &memories, 100).contains("因长度限制未显示"));
+
+        let summary = "总览".repeat(100);
+        let truncated = truncate_chars(&summary, 50, TRUNCATED_SUMMARY_SUFFIX);
+        assert_eq!(truncated.chars().count(), 50);
+        assert!(truncated.ends_with(TRUNCATED_SUMMARY_SUFFIX));
+        assert_eq!(truncate_chars(&summary, 3, TRUNCATED_SUMMARY_SUFFIX), "总览总");
+    }
+}
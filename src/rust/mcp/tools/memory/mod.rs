@@ -2,11 +2,33 @@
 //!
 //! 提供全局记忆管理功能，用于存储和管理重要的开发规范、用户偏好和最佳实践
 
+pub mod dedup;
 pub mod manager;
 pub mod types;
 pub mod mcp;
+pub mod rule_files;
+pub mod scope;
+pub mod search;
 
 // 重新导出主要类型和功能
 pub use manager::MemoryManager;
-pub use types::{MemoryEntry, MemoryCategory, MemoryMetadata};
+pub use rule_files::RuleFile;
+pub use scope::MemoryStack;
+pub use types::{AddMemoryResult, MemoryEntry, MemoryCategory, MemoryMetadata, MemoryScope, MemorySource};
 pub use mcp::MemoryTool;
+
+/// 测试用的项目规范记忆，ID 与内容相同
+#[cfg(test)]
+pub(crate) fn test_memory(content: &str) -> MemoryEntry {
+    let now = chrono::Utc::now();
+    MemoryEntry {
+        id: content.to_string(),
+        content: content.to_string(),
+        category: MemoryCategory::Rule,
+        created_at: now,
+        updated_at: now,
+        source: MemorySource::Manual,
+        tags: Vec::new(),
+        scope: MemoryScope::Project,
+    }
+}
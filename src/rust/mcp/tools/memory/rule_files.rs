@@ -0,0 +1,324 @@
+//! 与其他 AI 工具的规则文件同步记忆
+//!
+//! 导入时把 `CLAUDE.md`、`AGENTS.md` 等文件中的列表项作为规则或模式记忆，并用 `from:<文件>` 标签记录来源；
+//! 导出时把记忆写入文件中由寸止管理的区块，区块外的内容保持不变
+
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::manager::MemoryManager;
+use super::types::{MemoryCategory, MemoryEntry, MemoryScope, MemorySource};
+
+/// 受管理区块的开始和结束标记
+const MANAGED_BLOCK_BEGIN: &str = "<!-- cunzhi:memory:begin -->";
+const MANAGED_BLOCK_END: &str = "<!-- cunzhi:memory:end -->";
+
+/// 来源标签的前缀，如 `from:CLAUDE.md`
+const SOURCE_TAG_PREFIX: &str = "from:";
+
+/// 标题包含这些词时，其下的列表项作为模式记忆导入，否则作为规则
+const PATTERN_HEADING_KEYWORDS: &[&str] = &["pattern", "best practice", "architecture", "example", "模式", "最佳实践", "架构", "示例"];
+
+/// 支持同步的规则文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleFile {
+    ClaudeMd,
+    AgentsMd,
+    CursorRules,
+    CopilotInstructions,
+    WindsurfRules,
+}
+
+impl RuleFile {
+    pub const ALL: [RuleFile; 5] = [
+        RuleFile::ClaudeMd,
+        RuleFile::AgentsMd,
+        RuleFile::CursorRules,
+        RuleFile::CopilotInstructions,
+        RuleFile::WindsurfRules,
+    ];
+
+    /// 从名称（claude、agents、cursor、copilot、windsurf）解析规则文件
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "claude" => Some(RuleFile::ClaudeMd),
+            "agents" => Some(RuleFile::AgentsMd),
+            "cursor" => Some(RuleFile::CursorRules),
+            "copilot" => Some(RuleFile::CopilotInstructions),
+            "windsurf" => Some(RuleFile::WindsurfRules),
+            _ => None,
+        }
+    }
+
+    /// 相对于项目（或子目录）的文件路径
+    pub fn relative_path(self) -> &'static str {
+        match self {
+            RuleFile::ClaudeMd => "CLAUDE.md",
+            RuleFile::AgentsMd => "AGENTS.md",
+            RuleFile::CursorRules => ".cursorrules",
+            RuleFile::CopilotInstructions => ".github/copilot-instructions.md",
+            RuleFile::WindsurfRules => ".windsurfrules",
+        }
+    }
+
+    /// 记录导入来源的标签
+    pub fn source_tag(self) -> String {
+        format!("{}{}", SOURCE_TAG_PREFIX, self.relative_path())
+    }
+
+    /// 记忆作用域对应的规则文件路径，全局记忆没有对应的项目文件
+    pub fn path_for(self, manager: &MemoryManager) -> Result<PathBuf> {
+        if manager.scope() == MemoryScope::Global {
+            anyhow::bail!("全局记忆没有对应的规则文件，请使用 project 或 directory 作用域");
+        }
+        Ok(Path::new(manager.path()).join(self.relative_path()))
+    }
+}
+
+/// 从规则文件解析出的列表项
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleItem {
+    pub content: String,
+    pub category: MemoryCategory,
+}
+
+/// 导入结果
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// 新增的记忆数
+    pub added: usize,
+    /// 与已有记忆相似而合并的数量
+    pub merged: usize,
+    /// 新增的记忆和可能与之冲突的已有记忆
+    pub conflicts: Vec<(MemoryEntry, Vec<MemoryEntry>)>,
+}
+
+/// 将规则文件中的列表项导入记忆，已有相似记忆时合并（重复导入不会新增）
+pub fn import_rule_file(manager: &MemoryManager, file: RuleFile, items: &[RuleItem]) -> Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    for item in items {
+        let added = manager.add_memory(&item.content, item.category, MemorySource::Import, vec![file.source_tag()])?;
+        if added.merged {
+            summary.merged += 1;
+        } else {
+            summary.added += 1;
+            if !added.conflicts.is_empty() {
+                summary.conflicts.push((added.entry, added.conflicts));
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// 读取规则文件中的列表项，文件不存在时返回 `None`
+pub fn read_rule_items(path: &Path) -> Result<Option<Vec<RuleItem>>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(parse_rule_items(&fs::read_to_string(path)?)))
+}
+
+/// 解析 Markdown 列表项，跳过代码块和寸止管理的区块，缩进的续行合并到上一项
+pub fn parse_rule_items(text: &str) -> Vec<RuleItem> {
+    let mut items: Vec<RuleItem> = Vec::new();
+    let mut category = MemoryCategory::Rule;
+    let mut in_code_block = false;
+    let mut in_managed_block = false;
+    let mut continuing = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed == MANAGED_BLOCK_BEGIN {
+            in_managed_block = true;
+            continue;
+        }
+        if trimmed == MANAGED_BLOCK_END {
+            in_managed_block = false;
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continuing = false;
+            continue;
+        }
+        if in_managed_block || in_code_block {
+            continue;
+        }
+
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            let heading = heading.trim_start_matches('#').trim().to_lowercase();
+            category = if PATTERN_HEADING_KEYWORDS.iter().any(|keyword| heading.contains(keyword)) {
+                MemoryCategory::Pattern
+            } else {
+                MemoryCategory::Rule
+            };
+            continuing = false;
+        } else if let Some(content) = strip_list_marker(trimmed) {
+            continuing = !content.is_empty();
+            if continuing {
+                items.push(RuleItem { content: content.to_string(), category });
+            }
+        } else if continuing && !trimmed.is_empty() && line.starts_with(char::is_whitespace) {
+            if let Some(item) = items.last_mut() {
+                item.content.push(' ');
+                item.content.push_str(trimmed);
+            }
+        } else {
+            continuing = false;
+        }
+    }
+
+    items
+}
+
+/// 去掉列表标记（`-`、`*`、`+`、`1.`、`1)`）和任务框，不是列表项时返回 `None`
+fn strip_list_marker(line: &str) -> Option<&str> {
+    let rest = match line.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => {
+            let digits = line.chars().take_while(char::is_ascii_digit).count();
+            if digits == 0 {
+                return None;
+            }
+            line[digits..].strip_prefix(['.', ')'])?
+        }
+    };
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let rest = rest.trim();
+    Some(
+        ["[ ]", "[x]", "[X]"]
+            .iter()
+            .find_map(|checkbox| rest.strip_prefix(checkbox))
+            .map(str::trim_start)
+            .unwrap_or(rest),
+    )
+}
+
+/// 将记忆写入规则文件的受管理区块，返回写入的条目数
+///
+/// 从该文件导入的记忆已在区块外，不再重复写入；`dry_run` 时只返回将写入的文件内容
+pub fn export_rule_file(manager: &MemoryManager, file: RuleFile, dry_run: bool) -> Result<(usize, String)> {
+    let path = file.path_for(manager)?;
+    let source_tag = file.source_tag();
+    let memories: Vec<MemoryEntry> = manager
+        .get_all_memories()?
+        .into_iter()
+        .filter(|memory| !memory.tags.contains(&source_tag))
+        .collect();
+
+    let existing = if path.exists() { fs::read_to_string(&path)? } else { String::new() };
+    let content = replace_managed_block(&existing, &render_managed_block(&memories));
+    if !dry_run && content != existing {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp_path = path.with_file_name(format!(
+            "{}.tmp",
+            path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default()
+        ));
+        fs::write(&temp_path, &content)?;
+        fs::rename(&temp_path, &path)?;
+    }
+
+    Ok((memories.len(), content))
+}
+
+/// 生成受管理区块，按分类分组
+fn render_managed_block(memories: &[MemoryEntry]) -> String {
+    let mut block = format!(
+        "{}\n## 寸止记忆\n\n<!-- 此区块由寸止根据 .cunzhi-memory 生成，修改请使用寸止的记忆功能 -->\n",
+        MANAGED_BLOCK_BEGIN
+    );
+    for category in [MemoryCategory::Rule, MemoryCategory::Preference, MemoryCategory::Pattern, MemoryCategory::Context] {
+        let entries: Vec<&MemoryEntry> = memories.iter().filter(|memory| memory.category == category).collect();
+        if entries.is_empty() {
+            continue;
+        }
+        block.push_str(&format!("\n### {}\n\n", category.title()));
+        for entry in entries {
+            block.push_str(&format!("- {}\n", entry.content));
+        }
+    }
+    block.push_str(MANAGED_BLOCK_END);
+    block.push('\n');
+    block
+}
+
+/// 替换文件中的受管理区块，没有区块时追加到文件末尾
+fn replace_managed_block(existing: &str, block: &str) -> String {
+    if let Some(begin) = existing.find(MANAGED_BLOCK_BEGIN) {
+        if let Some(end) = existing[begin..].find(MANAGED_BLOCK_END) {
+            let mut after = &existing[begin + end + MANAGED_BLOCK_END.len()..];
+            after = after.strip_prefix('\n').unwrap_or(after);
+            return format!("{}{}{}", &existing[..begin], block, after);
+        }
+    }
+
+    if existing.trim().is_empty() {
+        return block.to_string();
+    }
+    let separator = if existing.ends_with("\n\n") {
+        ""
+    } else if existing.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+    format!("{}{}{}", existing, separator, block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule_items() {
+        let text = "# 项目说明\n\
+            \n\
+            - 使用 pnpm 管理依赖\n\
+            * [x] 提交前运行 cargo fmt\n\
+            1. 错误信息使用中文，\n\
+            \x20  面向用户的提示也一样\n\
+            ```bash\n\
+            - 代码块中的内容\n\
+            ```\n\
+            ## Architecture patterns\n\
+            + 配置修改统一通过 update_config\n\
+            -不是列表项\n\
+            <!-- cunzhi:memory:begin -->\n\
+            - 导出的记忆\n\
+            <!-- cunzhi:memory:end -->\n";
+
+        let items = parse_rule_items(text);
+        let contents: Vec<&str> = items.iter().map(|item| item.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec![
+                "使用 pnpm 管理依赖",
+                "提交前运行 cargo fmt",
+                "错误信息使用中文， 面向用户的提示也一样",
+                "配置修改统一通过 update_config",
+            ]
+        );
+        assert_eq!(items[0].category, MemoryCategory::Rule);
+        assert_eq!(items[3].category, MemoryCategory::Pattern);
+    }
+
+    #[test]
+    fn test_replace_managed_block() {
+        let block = |content: &str| format!("{}\n- {}\n{}\n", MANAGED_BLOCK_BEGIN, content, MANAGED_BLOCK_END);
+
+        let appended = replace_managed_block("# CLAUDE.md\n- 手写的规则\n", &block("第一版"));
+        assert_eq!(appended, format!("# CLAUDE.md\n- 手写的规则\n\n{}", block("第一版")));
+
+        // 再次导出只替换区块，区块外的内容不变
+        let edited = format!("{}\n## 其他说明\n", appended);
+        let replaced = replace_managed_block(&edited, &block("第二版"));
+        assert_eq!(replaced, format!("# CLAUDE.md\n- 手写的规则\n\n{}\n## 其他说明\n", block("第二版")));
+        assert_eq!(replace_managed_block("", &block("第一版")), block("第一版"));
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::Result;
+use std::collections::HashSet;
+
+use super::manager::MemoryManager;
+use super::search::rank_memories;
+use super::types::{MemoryEntry, MemoryScope};
+use crate::log_important;
+
+/// 多个作用域的记忆
+///
+/// 按优先级从高到低排列：目录 > 项目 > 全局，合并时内容相同的记忆只保留优先级最高的一条
+pub struct MemoryStack {
+    managers: Vec<MemoryManager>,
+}
+
+impl MemoryStack {
+    /// 打开项目相关的所有作用域：提供子目录时包含目录作用域，全局记忆不可用时跳过
+    pub fn open(project_path: &str, directory_path: Option<&str>) -> Result<Self> {
+        let mut managers = Vec::new();
+        if let Some(directory_path) = directory_path.filter(|path| !path.trim().is_empty()) {
+            managers.push(MemoryManager::directory(project_path, directory_path)?);
+        }
+        managers.push(MemoryManager::new(project_path)?);
+        match MemoryManager::global() {
+            Ok(manager) => managers.push(manager),
+            Err(e) => log_important!(warn, "打开全局记忆失败: {}", e),
+        }
+
+        Ok(Self { managers })
+    }
+
+    /// 指定作用域的记忆管理器
+    pub fn manager(&self, scope: MemoryScope) -> Result<&MemoryManager> {
+        self.managers
+            .iter()
+            .find(|manager| manager.scope() == scope)
+            .ok_or_else(|| match scope {
+                MemoryScope::Directory => anyhow::anyhow!("目录作用域需要提供子目录路径"),
+                _ => anyhow::anyhow!("{}记忆不可用", scope.title()),
+            })
+    }
+
+    /// 合并所有作用域的记忆，按优先级从高到低排列
+    pub fn get_all_memories(&self) -> Result<Vec<MemoryEntry>> {
+        let mut seen_contents = HashSet::new();
+        let mut memories = Vec::new();
+        for manager in &self.managers {
+            for memory in manager.get_all_memories()? {
+                if seen_contents.insert(memory.content.clone()) {
+                    memories.push(memory);
+                }
+            }
+        }
+        Ok(memories)
+    }
+
+    /// 在所有作用域中按相关度搜索记忆
+    pub fn search_memories(&self, query: &str, limit: usize) -> Result<Vec<MemoryEntry>> {
+        Ok(rank_memories(&self.get_all_memories()?, query, limit)
+            .into_iter()
+            .map(|(memory, _)| memory)
+            .collect())
+    }
+
+    /// 各作用域的记忆总览，优先级高的作用域在前
+    pub fn summary(&self) -> Result<String> {
+        let memories = self.get_all_memories()?;
+        let mut sections = Vec::new();
+        for manager in &self.managers {
+            let scoped: Vec<MemoryEntry> = memories
+                .iter()
+                .filter(|memory| memory.scope == manager.scope())
+                .cloned()
+                .collect();
+            let compressed = MemoryManager::compress_memories(&scoped);
+            if !compressed.is_empty() {
+                sections.push(format!("【{}】{}", manager.scope().title(), compressed.join(" | ")));
+            }
+        }
+
+        if sections.is_empty() {
+            return Ok("📭 暂无项目记忆".to_string());
+        }
+        Ok(format!(
+            "📚 记忆总览（优先级：目录 > 项目 > 全局，冲突时以优先级高的为准）:\n{}",
+            sections.join("\n")
+        ))
+    }
+
+    /// 按 ID 或完全相同的内容在所有作用域中查找记忆，返回所在的记忆管理器
+    pub fn find_memory_exact(&self, target: &str) -> Result<(&MemoryManager, MemoryEntry)> {
+        let target = target.trim();
+        let mut content_matches = Vec::new();
+        for manager in &self.managers {
+            for memory in manager.get_all_memories()? {
+                if memory.id == target {
+                    return Ok((manager, memory));
+                }
+                if memory.content == target {
+                    content_matches.push((manager, memory));
+                }
+            }
+        }
+
+        match content_matches.len() {
+            0 => anyhow::bail!("没有找到 ID 或内容为 \"{}\" 的记忆", target),
+            1 => Ok(content_matches.remove(0)),
+            count => anyhow::bail!("有 {} 条内容相同的记忆，请使用记忆 ID 或指定作用域", count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::{MemoryCategory, MemorySource};
+    use crate::utils::test_dir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// 创建临时 git 仓库（只需要 .git 目录），包含子目录 packages/app
+    fn temp_repo(name: &str) -> PathBuf {
+        let root = test_dir(&format!("scope_{}", name));
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("packages").join("app")).unwrap();
+        root.canonicalize().unwrap()
+    }
+
+    fn add(manager: &MemoryManager, content: &str) -> String {
+        manager.add_memory(content, MemoryCategory::Rule, MemorySource::Cli, Vec::new()).unwrap().entry.id
+    }
+
+    #[test]
+    fn test_stack_merges_scopes_by_precedence() {
+        let root = temp_repo("merge");
+        let project = root.to_string_lossy().to_string();
+        let app = root.join("packages").join("app").to_string_lossy().to_string();
+        let stack = MemoryStack {
+            managers: vec![
+                MemoryManager::directory(&project, &app).unwrap(),
+                MemoryManager::new(&project).unwrap(),
+                MemoryManager::with_memory_dir(root.join("global"), MemoryScope::Global),
+            ],
+        };
+
+        add(stack.manager(MemoryScope::Directory).unwrap(), "子包使用 vitest 运行测试");
+        add(stack.manager(MemoryScope::Project).unwrap(), "使用 pnpm 管理依赖");
+        let global_id = add(stack.manager(MemoryScope::Global).unwrap(), "使用 pnpm 管理依赖");
+        let reply_id = add(stack.manager(MemoryScope::Global).unwrap(), "用中文回复");
+
+        // 目录 > 项目 > 全局，内容相同时只保留优先级最高的一条
+        let memories = stack.get_all_memories().unwrap();
+        let merged: Vec<(MemoryScope, &str)> = memories.iter().map(|m| (m.scope, m.content.as_str())).collect();
+        assert_eq!(
+            merged,
+            vec![
+                (MemoryScope::Directory, "子包使用 vitest 运行测试"),
+                (MemoryScope::Project, "使用 pnpm 管理依赖"),
+                (MemoryScope::Global, "用中文回复"),
+            ]
+        );
+
+        // 按 ID 查找时返回所在作用域的管理器，相同内容出现在多个作用域时要求使用 ID
+        let (manager, entry) = stack.find_memory_exact(&reply_id).unwrap();
+        assert_eq!((manager.scope(), entry.content.as_str()), (MemoryScope::Global, "用中文回复"));
+        let (manager, _) = stack.find_memory_exact(&global_id).unwrap();
+        assert_eq!(manager.scope(), MemoryScope::Global);
+        let (manager, _) = stack.find_memory_exact("子包使用 vitest 运行测试").unwrap();
+        assert_eq!(manager.scope(), MemoryScope::Directory);
+        assert!(stack.find_memory_exact("使用 pnpm 管理依赖").is_err());
+        assert!(stack.find_memory_exact("不存在的记忆").is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_directory_scope_stays_in_project_and_reads_without_writing() {
+        let root = temp_repo("directory");
+        let other = temp_repo("directory_other");
+        let project = root.to_string_lossy().to_string();
+        let app = root.join("packages").join("app");
+
+        // 其他仓库的子目录和项目根目录都不能作为目录作用域
+        let other_app = other.join("packages").join("app").to_string_lossy().to_string();
+        assert!(MemoryManager::directory(&project, &other_app).is_err());
+        assert!(MemoryManager::directory(&project, &project).is_err());
+
+        // 只读取时不创建记忆目录
+        let manager = MemoryManager::directory(&project, &app.to_string_lossy()).unwrap();
+        assert!(manager.get_all_memories().unwrap().is_empty());
+        assert!(MemoryManager::new(&project).unwrap().get_all_memories().unwrap().is_empty());
+        assert!(!app.join(".cunzhi-memory").exists());
+        assert!(!root.join(".cunzhi-memory").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_dir_all(&other).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! 记忆检索
+//!
+//! 使用 BM25 对记忆内容和标签打分，中文按单字和相邻双字切分，英文按单词切分，
+//! 并允许拼写相近或前缀相同的词以较低权重匹配
+
+use std::collections::HashMap;
+
+use super::types::MemoryEntry;
+
+/// BM25 词频饱和参数
+const BM25_K1: f64 = 1.2;
+
+/// BM25 文档长度归一化参数
+const BM25_B: f64 = 0.75;
+
+/// 模糊匹配（拼写相近或前缀相同）相对于精确匹配的权重
+const FUZZY_WEIGHT: f64 = 0.5;
+
+/// 参与模糊匹配的最短词长，过短的词容易误匹配
+const MIN_FUZZY_TERM_CHARS: usize = 3;
+
+/// 按与查询的相关度排序记忆，返回得分大于 0 的前 `limit` 条
+pub fn rank_memories(memories: &[MemoryEntry], query: &str, limit: usize) -> Vec<(MemoryEntry, f64)> {
+    let query_terms = tokenize(query);
+    if query_terms.is_empty() || memories.is_empty() {
+        return Vec::new();
+    }
+
+    let documents: Vec<Vec<String>> = memories
+        .iter()
+        .map(|memory| tokenize(&format!("{} {}", memory.content, memory.tags.join(" "))))
+        .collect();
+    let average_length =
+        documents.iter().map(Vec::len).sum::<usize>() as f64 / documents.len() as f64;
+
+    // 每个查询词的文档频率（包含模糊匹配）
+    let document_count = documents.len() as f64;
+    let idf: HashMap<&str, f64> = query_terms
+        .iter()
+        .map(|term| {
+            let frequency = documents
+                .iter()
+                .filter(|document| document.iter().any(|token| term_weight(term, token) > 0.0))
+                .count() as f64;
+            let idf = ((document_count - frequency + 0.5) / (frequency + 0.5) + 1.0).ln();
+            (term.as_str(), idf)
+        })
+        .collect();
+
+    let mut ranked: Vec<(MemoryEntry, f64)> = memories
+        .iter()
+        .zip(&documents)
+        .map(|(memory, document)| {
+            let length_norm = 1.0 - BM25_B + BM25_B * document.len() as f64 / average_length.max(1.0);
+            let score = query_terms
+                .iter()
+                .map(|term| {
+                    let tf: f64 = document.iter().map(|token| term_weight(term, token)).sum();
+                    if tf == 0.0 {
+                        return 0.0;
+                    }
+                    idf[term.as_str()] * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * length_norm)
+                })
+                .sum::<f64>();
+            (memory.clone(), score)
+        })
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| b.0.updated_at.cmp(&a.0.updated_at)));
+    ranked.truncate(limit);
+    ranked
+}
+
+/// 切分文本：连续的字母数字为一个词（小写），中日韩文字取单字和相邻双字
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut previous_cjk: Option<char> = None;
+
+    for ch in text.chars() {
+        if is_cjk(ch) {
+            flush_word(&mut word, &mut tokens);
+            tokens.push(ch.to_string());
+            if let Some(previous) = previous_cjk {
+                tokens.push(format!("{}{}", previous, ch));
+            }
+            previous_cjk = Some(ch);
+        } else if ch.is_alphanumeric() || ch == '_' {
+            previous_cjk = None;
+            word.extend(ch.to_lowercase());
+        } else {
+            previous_cjk = None;
+            flush_word(&mut word, &mut tokens);
+        }
+    }
+    flush_word(&mut word, &mut tokens);
+
+    tokens
+}
+
+fn flush_word(word: &mut String, tokens: &mut Vec<String>) {
+    if !word.is_empty() {
+        tokens.push(std::mem::take(word));
+    }
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch,
+        '\u{3040}'..='\u{30FF}'   // 日文假名
+        | '\u{3400}'..='\u{4DBF}' // 扩展 A
+        | '\u{4E00}'..='\u{9FFF}' // 基本汉字
+        | '\u{AC00}'..='\u{D7AF}' // 韩文
+        | '\u{F900}'..='\u{FAFF}' // 兼容汉字
+    )
+}
+
+/// 查询词与文档词的匹配权重：相同为 1，拼写相近（编辑距离 1）或前缀相同时为 `FUZZY_WEIGHT`
+fn term_weight(term: &str, token: &str) -> f64 {
+    if term == token {
+        return 1.0;
+    }
+
+    let term_chars = term.chars().count();
+    if term_chars < MIN_FUZZY_TERM_CHARS || !term.is_ascii() || !token.is_ascii() {
+        return 0.0;
+    }
+    if token.starts_with(term) || (term_chars > MIN_FUZZY_TERM_CHARS && is_one_edit_apart(term, token)) {
+        FUZZY_WEIGHT
+    } else {
+        0.0
+    }
+}
+
+/// 两个 ASCII 词的编辑距离是否为 1（替换、插入、删除或相邻字母交换一次）
+fn is_one_edit_apart(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len().abs_diff(b.len()) > 1 {
+        return false;
+    }
+
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a_rest, b_rest) = (&a[prefix..], &b[prefix..]);
+    match (a_rest.len(), b_rest.len()) {
+        (0, 0) => false,
+        (x, y) if x == y => {
+            a_rest[1..] == b_rest[1..]
+                || (x >= 2 && a_rest[0] == b_rest[1] && a_rest[1] == b_rest[0] && a_rest[2..] == b_rest[2..])
+        }
+        (x, y) if x > y => a_rest[1..] == *b_rest,
+        _ => *a_rest == b_rest[1..],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::test_memory as memory;
+
+    #[test]
+    fn test_tokenize_mixed_text() {
+        assert_eq!(
+            tokenize("使用pnpm安装"),
+            vec!["使", "用", "使用", "pnpm", "安", "装", "安装"]
+        );
+        assert_eq!(tokenize("Rust_fmt, CI!"), vec!["rust_fmt", "ci"]);
+    }
+
+    #[test]
+    fn test_rank_prefers_relevant_memories() {
+        let memories = vec![
+            memory("提交前运行 cargo fmt 格式化代码"),
+            memory("前端使用 pnpm 管理依赖"),
+            memory("数据库迁移放在 migrations 目录"),
+        ];
+
+        let ranked = rank_memories(&memories, "依赖管理", 2);
+        assert_eq!(ranked[0].0.content, "前端使用 pnpm 管理依赖");
+
+        // 拼写错误和前缀也能匹配
+        let ranked = rank_memories(&memories, "migratoins", 5);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].0.content, "数据库迁移放在 migrations 目录");
+        assert_eq!(rank_memories(&memories, "migr", 5).len(), 1);
+
+        assert!(rank_memories(&memories, "kubernetes", 5).is_empty());
+    }
+}
@@ -9,6 +9,64 @@ pub struct MemoryEntry {
     pub category: MemoryCategory,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// 记忆的来源
+    #[serde(default)]
+    pub source: MemorySource,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 记忆所在的作用域（由存储位置决定，不写入记忆文件）
+    #[serde(default)]
+    pub scope: MemoryScope,
+}
+
+/// 记忆作用域，按优先级从低到高排列
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryScope {
+    /// 所有项目共用，保存在配置目录
+    Global,
+    /// 当前 git 项目，保存在 git 根目录的 `.cunzhi-memory`
+    #[default]
+    Project,
+    /// 项目中的子目录（如 monorepo 的子包），保存在该目录的 `.cunzhi-memory`
+    Directory,
+}
+
+impl MemoryScope {
+    /// 从作用域名称（global、project、directory）解析作用域
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "global" => Some(MemoryScope::Global),
+            "project" => Some(MemoryScope::Project),
+            "directory" => Some(MemoryScope::Directory),
+            _ => None,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            MemoryScope::Global => "全局",
+            MemoryScope::Project => "项目",
+            MemoryScope::Directory => "目录",
+        }
+    }
+}
+
+/// 记忆来源
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MemorySource {
+    /// MCP ji 工具
+    Mcp,
+    /// 命令行
+    Cli,
+    /// Telegram /remember 命令
+    Telegram,
+    /// 从其他 AI 工具的规则文件导入
+    Import,
+    /// 直接编辑记忆文件
+    #[default]
+    Manual,
 }
 
 /// 记忆分类
@@ -20,6 +78,28 @@ pub enum MemoryCategory {
     Context,     // 项目上下文信息
 }
 
+impl MemoryCategory {
+    /// 从分类名称（rule、preference、pattern、context）解析分类
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rule" => Some(MemoryCategory::Rule),
+            "preference" => Some(MemoryCategory::Preference),
+            "pattern" => Some(MemoryCategory::Pattern),
+            "context" => Some(MemoryCategory::Context),
+            _ => None,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            MemoryCategory::Rule => "开发规范和规则",
+            MemoryCategory::Preference => "用户偏好设置",
+            MemoryCategory::Pattern => "常用模式和最佳实践",
+            MemoryCategory::Context => "项目上下文信息",
+        }
+    }
+}
+
 /// 记忆元数据
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MemoryMetadata {
@@ -28,3 +108,14 @@ pub struct MemoryMetadata {
     pub total_entries: usize,
     pub version: String,
 }
+
+/// 添加记忆的结果
+#[derive(Debug, Clone)]
+pub struct AddMemoryResult {
+    /// 新增的记忆，或合并了新内容的已有记忆
+    pub entry: MemoryEntry,
+    /// 同分类中已有相似记忆，新内容已合并到该条目而没有新增
+    pub merged: bool,
+    /// 同分类中可能与新内容冲突的记忆
+    pub conflicts: Vec<MemoryEntry>,
+}
@@ -11,6 +11,9 @@ pub struct ZhiRequest {
     #[schemars(description = "消息是否为Markdown格式，默认为true")]
     #[serde(default = "default_is_markdown")]
     pub is_markdown: bool,
+    #[schemars(description = "当前项目的路径（可选），用于加载项目的 .cunzhi/config.toml")]
+    #[serde(default)]
+    pub project_path: Option<String>,
 }
 
 fn default_is_markdown() -> bool {
@@ -19,18 +22,43 @@ fn default_is_markdown() -> bool {
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct JiyiRequest {
-    #[schemars(description = "操作类型：记忆(添加记忆), 回忆(获取项目信息)")]
+    #[schemars(
+        description = "操作类型：记忆/add(添加记忆), 回忆/recall(获取项目信息), 列表/list(列出记忆及ID), 更新/update(修改记忆), 删除/delete(删除记忆)"
+    )]
     pub action: String,
     #[schemars(description = "项目路径（必需）")]
     pub project_path: String,
-    #[schemars(description = "记忆内容（记忆操作时必需）")]
+    #[schemars(description = "记忆内容（记忆操作时必需，更新操作时为新内容）")]
     #[serde(default)]
     pub content: String,
     #[schemars(
-        description = "记忆分类：rule(规范规则), preference(用户偏好), pattern(最佳实践), context(项目上下文)"
+        description = "记忆分类：rule(规范规则), preference(用户偏好), pattern(最佳实践), context(项目上下文)。添加时默认为 context，列表时用于筛选，更新时用于修改分类"
+    )]
+    #[serde(default)]
+    pub category: Option<String>,
+    #[schemars(description = "要更新或删除的记忆：记忆 ID（可通过列表操作获取）或完整的原内容")]
+    #[serde(default)]
+    pub id: Option<String>,
+    #[schemars(description = "记忆标签（添加和更新时可选）")]
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[schemars(description = "回忆时的检索关键词，提供时只返回最相关的记忆（支持中文和模糊匹配）")]
+    #[serde(default)]
+    pub query: Option<String>,
+    #[schemars(description = "按关键词回忆时返回的最大条数，默认 10")]
+    #[serde(default)]
+    pub top_k: Option<usize>,
+    #[schemars(description = "回忆结果的最大字符数，默认 4000")]
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+    #[schemars(
+        description = "记忆作用域：global(所有项目共用), project(当前项目，默认), directory(子目录，如 monorepo 子包)。添加时决定写入位置，列表/更新/删除时限定范围，回忆总是合并所有作用域"
     )]
-    #[serde(default = "default_category")]
-    pub category: String,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[schemars(description = "子目录路径（使用 directory 作用域时必需，回忆时提供则包含该目录的记忆）")]
+    #[serde(default)]
+    pub directory_path: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -41,16 +69,15 @@ pub struct AcemcpRequest {
     pub query: String,
 }
 
-fn default_category() -> String {
-    "context".to_string()
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PopupRequest {
     pub id: String,
     pub message: String,
     pub predefined_options: Option<Vec<String>>,
     pub is_markdown: bool,
+    /// 请求所属的项目路径，用于合并项目配置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_path: Option<String>,
 }
 
 /// 新的结构化响应数据格式
@@ -62,7 +89,7 @@ pub struct McpResponse {
     pub metadata: ResponseMetadata,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageAttachment {
     pub data: String,
     pub media_type: String,
@@ -74,6 +101,9 @@ pub struct ResponseMetadata {
     pub timestamp: Option<String>,
     pub request_id: Option<String>,
     pub source: Option<String>,
+    /// 回复者（Telegram群组中记录实际回复的用户）
+    #[serde(default)]
+    pub responder: Option<String>,
 }
 
 /// 旧格式兼容性支持
@@ -103,7 +133,19 @@ pub fn build_mcp_response(
     request_id: Option<String>,
     source: &str,
 ) -> serde_json::Value {
-    serde_json::json!({
+    build_mcp_response_with_responder(user_input, selected_options, images, request_id, source, None)
+}
+
+/// 构建带回复者信息的响应（回复者为空时与 [`build_mcp_response`] 输出一致）
+pub fn build_mcp_response_with_responder(
+    user_input: Option<String>,
+    selected_options: Vec<String>,
+    images: Vec<ImageAttachment>,
+    request_id: Option<String>,
+    source: &str,
+    responder: Option<String>,
+) -> serde_json::Value {
+    let mut response = serde_json::json!({
         "user_input": user_input,
         "selected_options": selected_options,
         "images": images,
@@ -112,7 +154,13 @@ pub fn build_mcp_response(
             "request_id": request_id,
             "source": source
         }
-    })
+    });
+
+    if let Some(responder) = responder {
+        response["metadata"]["responder"] = serde_json::Value::String(responder);
+    }
+
+    response
 }
 
 /// 构建发送操作的响应
@@ -129,13 +177,31 @@ pub fn build_send_response(
 
 /// 构建继续操作的响应
 pub fn build_continue_response(request_id: Option<String>, source: &str) -> String {
-    // 动态获取继续提示词
-    let continue_prompt = if let Ok(config) = crate::config::load_standalone_config() {
+    build_continue_response_with_responder(request_id, source, None)
+}
+
+/// 构建带回复者信息的继续操作响应
+pub fn build_continue_response_with_responder(
+    request_id: Option<String>,
+    source: &str,
+    responder: Option<String>,
+) -> String {
+    // 动态获取继续提示词（包含请求所属项目的配置）
+    let continue_prompt = if let Ok(config) =
+        crate::config::load_project_config(crate::config::request_project_path())
+    {
         config.reply_config.continue_prompt
     } else {
         "请按照最佳实践继续".to_string()
     };
 
-    let response = build_mcp_response(Some(continue_prompt), vec![], vec![], request_id, source);
+    let response = build_mcp_response_with_responder(
+        Some(continue_prompt),
+        vec![],
+        vec![],
+        request_id,
+        source,
+        responder,
+    );
     response.to_string()
 }
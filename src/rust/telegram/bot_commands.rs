@@ -0,0 +1,185 @@
+use anyhow::Result;
+use teloxide::{prelude::*, types::BotCommand};
+
+use super::core::TelegramCore;
+use crate::constants::telegram as telegram_constants;
+use crate::mcp::history::{RequestHistory, RequestRecord};
+use crate::mcp::tools::memory::{MemoryCategory, MemoryManager, MemorySource, MemoryStack};
+
+/// 支持的Bot命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotCommandKind {
+    /// 列出等待回复的请求
+    Pending,
+    /// 显示最近的回复，可指定条数
+    History(Option<usize>),
+    /// 为最近活跃的项目添加记忆
+    Remember(String),
+    /// 获取最近活跃项目的记忆
+    Recall,
+    /// 显示帮助
+    Help,
+}
+
+/// 注册Bot命令菜单（setMyCommands）
+pub async fn register_bot_commands(bot: &Bot) -> Result<()> {
+    let commands = [
+        ("pending", "查看等待回复的请求"),
+        ("history", "查看最近的回复，如 /history 10"),
+        ("remember", "为最近的项目添加记忆，如 /remember 使用中文注释"),
+        ("recall", "查看最近项目的记忆"),
+    ]
+    .into_iter()
+    .map(|(command, description)| BotCommand::new(command, description));
+
+    bot.set_my_commands(commands)
+        .await
+        .map_err(|e| anyhow::anyhow!("注册Bot命令失败: {}", e))?;
+
+    Ok(())
+}
+
+/// 解析命令文本，如 `/history 5` 或群组中的 `/pending@my_bot`
+pub fn parse_bot_command(text: &str) -> Option<BotCommandKind> {
+    let text = text.trim();
+    let rest = text.strip_prefix('/')?;
+    let (command, argument) = match rest.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (rest, ""),
+    };
+    // 去掉群组中附带的 @bot 用户名
+    let command = command.split('@').next().unwrap_or_default();
+
+    match command {
+        "pending" => Some(BotCommandKind::Pending),
+        "history" => Some(BotCommandKind::History(argument.parse().ok())),
+        "remember" => Some(BotCommandKind::Remember(argument.to_string())),
+        "recall" => Some(BotCommandKind::Recall),
+        "help" | "start" => Some(BotCommandKind::Help),
+        _ => None,
+    }
+}
+
+/// 处理Bot命令消息，返回 `true` 表示消息是命令且已处理（调用方不应再当作回复文本）
+pub async fn handle_bot_command(core: &TelegramCore, message: &Message) -> Result<bool> {
+    let Some(command) = message.text().and_then(parse_bot_command) else {
+        return Ok(false);
+    };
+
+    // 只处理目标聊天中允许的用户发出的命令
+    if !core.is_target_message(message) || !core.is_allowed_user(message.from.as_ref()) {
+        return Ok(true);
+    }
+
+    let reply = match execute_bot_command(command) {
+        Ok(reply) => reply,
+        Err(e) => format!("❌ 命令执行失败: {}", e),
+    };
+    core.send_message(&reply).await?;
+
+    Ok(true)
+}
+
+/// 执行命令并生成回复文本
+fn execute_bot_command(command: BotCommandKind) -> Result<String> {
+    let history = RequestHistory::open()?;
+
+    match command {
+        BotCommandKind::Pending => {
+            let pending = history.pending()?;
+            if pending.is_empty() {
+                return Ok("📭 当前没有等待回复的请求".to_string());
+            }
+
+            let mut reply = format!("⏳ 等待回复的请求（{}）：", pending.len());
+            for (index, record) in pending.iter().enumerate() {
+                reply.push_str(&format!("\n\n{}. {}", index + 1, format_record_message(record)));
+            }
+            Ok(reply)
+        }
+        BotCommandKind::History(limit) => {
+            let limit = limit
+                .unwrap_or(telegram_constants::DEFAULT_HISTORY_COUNT)
+                .clamp(1, telegram_constants::MAX_HISTORY_COUNT);
+            let answers = history.recent_answers(limit)?;
+            if answers.is_empty() {
+                return Ok("📭 暂无回复记录".to_string());
+            }
+
+            let mut reply = format!("🕘 最近 {} 条回复：", answers.len());
+            for (index, record) in answers.iter().enumerate() {
+                reply.push_str(&format!(
+                    "\n\n{}. {}\n↪️ {}",
+                    index + 1,
+                    format_record_message(record),
+                    record.answer.as_deref().unwrap_or_default()
+                ));
+            }
+            Ok(reply)
+        }
+        BotCommandKind::Remember(content) => {
+            if content.is_empty() {
+                return Ok("用法: /remember <记忆内容>".to_string());
+            }
+
+            let project_path = last_project_path(&history)?;
+            let manager = MemoryManager::new(&project_path)?;
+            let added = manager.add_memory(&content, MemoryCategory::Context, MemorySource::Telegram, Vec::new())?;
+            if added.merged {
+                return Ok(format!(
+                    "♻️ 已有相似的记忆，已合并而未新增\n📁 项目: {}\n📝 已有内容: {}\nID: {}",
+                    project_path, added.entry.content, added.entry.id
+                ));
+            }
+
+            let mut reply = format!("✅ 已添加记忆\n📁 项目: {}\n📝 内容: {}", project_path, content);
+            for conflict in &added.conflicts {
+                reply.push_str(&format!("\n⚠️ 可能与已有记忆冲突: {} (ID: {})", conflict.content, conflict.id));
+            }
+            Ok(reply)
+        }
+        BotCommandKind::Recall => {
+            let project_path = last_project_path(&history)?;
+            let stack = MemoryStack::open(&project_path, None)?;
+            Ok(format!("📁 项目: {}\n\n{}", project_path, stack.summary()?))
+        }
+        BotCommandKind::Help => Ok("可用命令：\n\
+             /pending - 查看等待回复的请求\n\
+             /history [条数] - 查看最近的回复\n\
+             /remember <内容> - 为最近的项目添加记忆\n\
+             /recall - 查看最近项目的记忆"
+            .to_string()),
+    }
+}
+
+/// 最近活跃的项目（AI 调用记忆工具时记录）
+fn last_project_path(history: &RequestHistory) -> Result<String> {
+    history
+        .last_project_path()?
+        .ok_or_else(|| anyhow::anyhow!("还没有活跃的项目，请先在项目中使用一次记忆功能"))
+}
+
+/// 格式化请求记录：本地时间 + 消息摘要
+fn format_record_message(record: &RequestRecord) -> String {
+    let created_at = record.created_at.with_timezone(&chrono::Local);
+    format!("[{}] {}", created_at.format("%m-%d %H:%M"), record.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bot_command() {
+        assert_eq!(parse_bot_command("/pending"), Some(BotCommandKind::Pending));
+        assert_eq!(parse_bot_command("/pending@cunzhi_bot"), Some(BotCommandKind::Pending));
+        assert_eq!(parse_bot_command("/history 10"), Some(BotCommandKind::History(Some(10))));
+        assert_eq!(parse_bot_command("/history"), Some(BotCommandKind::History(None)));
+        assert_eq!(
+            parse_bot_command("/remember  使用中文注释 "),
+            Some(BotCommandKind::Remember("使用中文注释".to_string()))
+        );
+        assert_eq!(parse_bot_command("⏩继续"), None);
+        assert_eq!(parse_bot_command("/unknown"), None);
+    }
+}
@@ -1,7 +1,12 @@
-use crate::config::{save_config, AppState, TelegramConfig};
+use crate::config::{mask_secret, update_config, unmask_secret, AppState, TelegramConfig};
 use crate::constants::telegram as telegram_constants;
+use crate::telegram::core::{
+    append_caption, callback_token, compose_user_input, create_bot, download_message_images,
+    insert_prompt_content, CallbackAction,
+};
 use crate::telegram::{
-    handle_callback_query, handle_text_message, TelegramCore,
+    handle_bot_command, handle_callback_query, handle_text_message, register_bot_commands,
+    TelegramCore, TelegramEvent, UpdateOffsetStore,
 };
 use crate::log_important;
 use tauri::{AppHandle, Emitter, Manager, State};
@@ -14,28 +19,28 @@ pub async fn get_telegram_config(state: State<'_, AppState>) -> Result<TelegramC
         .config
         .lock()
         .map_err(|e| format!("获取配置失败: {}", e))?;
-    Ok(config.telegram_config.clone())
+
+    // 界面只展示掩码后的Token
+    let mut telegram_config = config.telegram_config.clone();
+    telegram_config.bot_token = mask_secret(&telegram_config.bot_token);
+    Ok(telegram_config)
 }
 
 /// 设置Telegram配置
 #[tauri::command]
 pub async fn set_telegram_config(
-    telegram_config: TelegramConfig,
+    mut telegram_config: TelegramConfig,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
+        // 界面提交的仍是掩码时保留原Token
+        telegram_config.bot_token =
+            unmask_secret(&telegram_config.bot_token, &config.telegram_config.bot_token);
         config.telegram_config = telegram_config;
-    }
-
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -47,13 +52,17 @@ pub async fn test_telegram_connection_cmd(
     chat_id: String,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
-    // 获取API URL配置
-    let api_url = {
+    // 获取API URL和话题配置
+    let (bot_token, api_url, message_thread_id) = {
         let config = state
             .config
             .lock()
             .map_err(|e| format!("获取配置失败: {}", e))?;
-        config.telegram_config.api_base_url.clone()
+        (
+            unmask_secret(&bot_token, &config.telegram_config.bot_token),
+            config.telegram_config.api_base_url.clone(),
+            config.telegram_config.message_thread_id,
+        )
     };
 
     // 使用默认API URL时传递None，否则传递自定义URL
@@ -63,9 +72,14 @@ pub async fn test_telegram_connection_cmd(
         Some(api_url.as_str())
     };
 
-    crate::telegram::core::test_telegram_connection_with_api_url(&bot_token, &chat_id, api_url_option)
-        .await
-        .map_err(|e| e.to_string())
+    crate::telegram::core::test_telegram_connection_with_api_url(
+        &bot_token,
+        &chat_id,
+        api_url_option,
+        message_thread_id,
+    )
+    .await
+    .map_err(|e| e.to_string())
 }
 
 /// 自动获取Chat ID（通过监听Bot消息）
@@ -74,19 +88,20 @@ pub async fn auto_get_chat_id(
     bot_token: String,
     app_handle: AppHandle,
 ) -> Result<(), String> {
-    // 获取API URL配置
-    let mut bot = Bot::new(bot_token.clone());
-    
-    if let Some(state) = app_handle.try_state::<AppState>() {
-        if let Ok(config) = state.config.lock() {
-            let api_url = &config.telegram_config.api_base_url;
-            if api_url != telegram_constants::API_BASE_URL {
-                if let Ok(url) = reqwest::Url::parse(api_url) {
-                    bot = bot.set_api_url(url);
-                }
-            }
-        }
-    }
+    // 获取API URL配置（界面提交的Token可能是掩码）
+    let (bot_token, api_url) = match app_handle
+        .try_state::<AppState>()
+        .and_then(|state| state.config.lock().ok().map(|config| config.telegram_config.clone()))
+    {
+        Some(telegram_config) => (
+            unmask_secret(&bot_token, &telegram_config.bot_token),
+            Some(telegram_config.api_base_url)
+                .filter(|api_url| api_url != telegram_constants::API_BASE_URL),
+        ),
+        None => (bot_token, None),
+    };
+
+    let bot = create_bot(&bot_token, api_url.as_deref()).map_err(|e| e.to_string())?;
 
     // 发送事件通知前端开始监听
     if let Err(e) = app_handle.emit("chat-id-detection-started", ()) {
@@ -110,12 +125,20 @@ pub async fn auto_get_chat_id(
                                 .and_then(|u| u.username.as_ref())
                                 .map(|s| s.as_str())
                                 .unwrap_or("未知用户");
+                            let user_id = message.from.as_ref().map(|u| u.id.0);
+                            // 论坛话题中的消息会携带话题ID
+                            let message_thread_id = message
+                                .thread_id
+                                .filter(|_| message.is_topic_message)
+                                .map(|thread_id| thread_id.0 .0);
 
                             // 发送检测到的Chat ID到前端
                             let chat_info = serde_json::json!({
                                 "chat_id": chat_id,
                                 "chat_title": chat_title,
                                 "username": username,
+                                "user_id": user_id,
+                                "message_thread_id": message_thread_id,
                                 "message_text": message.text().unwrap_or(""),
                             });
 
@@ -182,49 +205,36 @@ pub async fn start_telegram_sync(
     app_handle: AppHandle,
 ) -> Result<(), String> {
     // 获取Telegram配置
-    let (enabled, bot_token, chat_id, continue_reply_enabled) = {
+    let (telegram_config, continue_reply_enabled, custom_prompt_config) = {
         let config = state
             .config
             .lock()
             .map_err(|e| format!("获取配置失败: {}", e))?;
         (
-            config.telegram_config.enabled,
-            config.telegram_config.bot_token.clone(),
-            config.telegram_config.chat_id.clone(),
+            config.telegram_config.clone(),
             config.reply_config.enable_continue_reply,
+            config.custom_prompt_config.clone(),
         )
     };
 
-    if !enabled {
+    if !telegram_config.enabled {
         return Ok(());
     }
 
-    if bot_token.trim().is_empty() || chat_id.trim().is_empty() {
+    if telegram_config.bot_token.trim().is_empty() || telegram_config.chat_id.trim().is_empty() {
         return Err("Telegram配置不完整".to_string());
     }
 
-    // 获取API URL配置
-    let api_url = {
-        let config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
-        config.telegram_config.api_base_url.clone()
-    };
-
-    // 使用默认API URL时传递None，否则传递自定义URL
-    let api_url_option = if api_url == telegram_constants::API_BASE_URL {
-        None
-    } else {
-        Some(api_url)
-    };
-
-    // 创建Telegram核心实例
-    let core = TelegramCore::new_with_api_url(bot_token.clone(), chat_id.clone(), api_url_option)
-        .map_err(|e| format!("创建Telegram核心失败: {}", e))?;
+    // 创建Telegram核心实例（解析群组、话题和允许回复的用户）
+    let core = TelegramCore::from_config(&telegram_config)
+        .await
+        .map_err(|e| format!("创建Telegram核心失败: {}", e))?
+        .with_custom_prompts(&custom_prompt_config);
 
-    // 发送选项消息
-    core.send_options_message(&message, &predefined_options, is_markdown)
+    // 发送选项消息（每次同步生成新的按钮标识，避免旧消息的按钮被误处理）
+    let request_token = callback_token(&uuid::Uuid::new_v4().to_string());
+    let options_message_id = core
+        .send_options_message(&message, &predefined_options, is_markdown, &request_token)
         .await
         .map_err(|e| format!("发送选项消息失败: {}", e))?;
 
@@ -232,22 +242,24 @@ pub async fn start_telegram_sync(
     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
 
     // 发送操作消息
-    core.send_operation_message(continue_reply_enabled)
+    let operation_message_id = core
+        .send_operation_message(continue_reply_enabled)
         .await
         .map_err(|e| format!("发送操作消息失败: {}", e))?;
 
     // 启动消息监听（根据是否有预定义选项选择监听模式）
-    let bot_token_clone = bot_token.clone();
-    let chat_id_clone = chat_id.clone();
     let app_handle_clone = app_handle.clone();
 
     tokio::spawn(async move {
         // 使用统一的监听器，传递选项参数
         match start_telegram_listener(
-            bot_token_clone,
-            chat_id_clone,
+            core,
             app_handle_clone,
+            request_token,
             predefined_options,
+            // 返回0表示未能获取消息ID
+            Some(options_message_id).filter(|id| *id > 0),
+            Some(operation_message_id).filter(|id| *id > 0),
         )
         .await
         {
@@ -261,45 +273,30 @@ pub async fn start_telegram_sync(
 
 /// 启动Telegram消息监听（统一版本，支持有选项和无选项模式）
 async fn start_telegram_listener(
-    bot_token: String,
-    chat_id: String,
+    mut core: TelegramCore,
     app_handle: AppHandle,
+    request_token: String,
     predefined_options_list: Vec<String>,
+    options_message_id: Option<i32>,
+    operation_message_id: Option<i32>,
 ) -> Result<(), String> {
-    // 从AppHandle获取应用状态来读取API URL配置
-    let api_url = match app_handle.try_state::<AppState>() {
-        Some(state) => {
-            let config = state
-                .config
-                .lock()
-                .map_err(|e| format!("获取配置失败: {}", e))?;
-            let api_url = config.telegram_config.api_base_url.clone();
-                         if api_url == telegram_constants::API_BASE_URL {
-                None
-            } else {
-                Some(api_url)
-            }
-        }
-        None => None, // 如果无法获取状态，使用默认API
-    };
-
-    let core = TelegramCore::new_with_api_url(bot_token, chat_id, api_url)
-        .map_err(|e| format!("创建Telegram核心失败: {}", e))?;
-
-    let mut offset = 0i32;
+    // 从上次确认的位置继续拉取，避免消费之前请求遗留的更新
+    let offset_store = UpdateOffsetStore::for_bot(core.bot.token())
+        .map_err(|e| format!("初始化Telegram偏移量存储失败: {}", e))?;
+    let mut offset = offset_store.next_offset().unwrap_or(0);
 
     // 用于跟踪选项状态和消息ID
     let mut selected_options: std::collections::HashSet<String> = std::collections::HashSet::new();
-    let mut options_message_id: Option<i32> = None;
+    let mut options_message_id = options_message_id;
+    let mut current_page: usize = 0;
     let mut user_input: String = String::new(); // 存储用户输入的文本
+    let mut image_count: usize = 0; // 已收到的图片数量
     let predefined_options = predefined_options_list;
-    let has_options = !predefined_options.is_empty(); // 是否有预定义选项
+    let has_inline_keyboard = core.has_inline_keyboard(&predefined_options); // 是否有选项或条件性prompt开关
 
-    // 获取当前最新的消息ID作为基准
-    if let Ok(updates) = core.bot.get_updates().limit(10).await {
-        if let Some(update) = updates.last() {
-            offset = update.id.0 as i32 + 1;
-        }
+    // 注册命令菜单，等待回复期间也可以使用 /pending 等命令
+    if let Err(e) = register_bot_commands(&core.bot).await {
+        log_important!(warn, "{}", e);
     }
 
     // 监听循环
@@ -308,11 +305,25 @@ async fn start_telegram_listener(
             Ok(updates) => {
                 for update in updates {
                     offset = update.id.0 as i32 + 1;
+                    if let Err(e) = offset_store.acknowledge(update.id.0 as i32) {
+                        log_important!(warn, "保存Telegram更新偏移量失败: {}", e);
+                    }
 
                     match update.kind {
                         teloxide::types::UpdateKind::CallbackQuery(callback_query) => {
-                            // 只有当有预定义选项时才处理 callback queries
-                            if has_options {
+                            // 只有当有inline keyboard时才处理 callback queries
+                            if has_inline_keyboard {
+                                let action = match handle_callback_query(
+                                    &core,
+                                    &callback_query,
+                                    &request_token,
+                                )
+                                .await
+                                {
+                                    Ok(Some(action)) => action,
+                                    _ => continue,
+                                };
+
                                 // 从callback_query中提取消息ID
                                 if let Some(message) = &callback_query.message {
                                     if options_message_id.is_none() {
@@ -320,99 +331,130 @@ async fn start_telegram_listener(
                                     }
                                 }
 
-                                if let Ok(Some(option)) =
-                                    handle_callback_query(&core.bot, &callback_query, core.chat_id)
-                                        .await
-                                {
-                                    // 切换选项状态
-                                    let selected = if selected_options.contains(&option) {
-                                        selected_options.remove(&option);
-                                        false
-                                    } else {
-                                        selected_options.insert(option.clone());
-                                        true
-                                    };
-
-                                    // 发送事件到前端
-                                    use crate::telegram::TelegramEvent;
-                                    let event = TelegramEvent::OptionToggled {
-                                        option: option.clone(),
-                                        selected,
-                                    };
-
-                                    let _ = app_handle.emit("telegram-event", &event);
-
-                                    // 更新按钮状态
-                                    if let Some(msg_id) = options_message_id {
-                                        let selected_vec: Vec<String> =
-                                            selected_options.iter().cloned().collect();
-                                        if let Ok(_) = core
-                                            .update_inline_keyboard(
-                                                msg_id,
-                                                &predefined_options,
-                                                &selected_vec,
-                                            )
-                                            .await {}
+                                match action {
+                                    CallbackAction::Toggle(index) => {
+                                        let Some(option) = predefined_options.get(index).cloned()
+                                        else {
+                                            continue;
+                                        };
+
+                                        // 切换选项状态
+                                        let selected = if selected_options.contains(&option) {
+                                            selected_options.remove(&option);
+                                            false
+                                        } else {
+                                            selected_options.insert(option.clone());
+                                            true
+                                        };
+
+                                        // 发送事件到前端
+                                        let event = TelegramEvent::OptionToggled { option, selected };
+
+                                        let _ = app_handle.emit("telegram-event", &event);
+                                    }
+                                    CallbackAction::Page(page) => current_page = page,
+                                    CallbackAction::Condition(index) => {
+                                        let Some(prompt) = core.toggle_conditional_prompt(index)
+                                        else {
+                                            continue;
+                                        };
+
+                                        // 由前端同步开关并保存配置
+                                        let event = TelegramEvent::ConditionToggled {
+                                            prompt_id: prompt.id.clone(),
+                                            current_state: prompt.current_state,
+                                        };
+                                        let _ = app_handle.emit("telegram-event", &event);
                                     }
+                                    CallbackAction::Noop => continue,
+                                }
+
+                                // 更新按钮状态
+                                if let Some(msg_id) = options_message_id {
+                                    let selected_vec: Vec<String> =
+                                        selected_options.iter().cloned().collect();
+                                    let _ = core
+                                        .update_inline_keyboard(
+                                            msg_id,
+                                            &request_token,
+                                            &predefined_options,
+                                            &selected_vec,
+                                            current_page,
+                                        )
+                                        .await;
                                 }
                             }
                         }
                         teloxide::types::UpdateKind::Message(message) => {
-                            // 只有当有预定义选项时才检查 inline keyboard
-                            if has_options {
-                                // 检查是否是包含 inline keyboard 的选项消息
-                                if let Some(inline_keyboard) = message.reply_markup() {
-                                    // 检查是否包含我们的选项按钮
-                                    let mut contains_our_options = false;
-                                    for row in &inline_keyboard.inline_keyboard {
-                                        for button in row {
-                                            if let teloxide::types::InlineKeyboardButtonKind::CallbackData(callback_data) = &button.kind {
-                                                if callback_data.starts_with("toggle:") {
-                                                    contains_our_options = true;
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                        if contains_our_options {
-                                            break;
-                                        }
-                                    }
+                            match handle_bot_command(&core, &message).await {
+                                Ok(true) => continue,
+                                Ok(false) => {}
+                                Err(e) => log_important!(warn, "处理Bot命令失败: {}", e),
+                            }
 
-                                    if contains_our_options {
-                                        options_message_id = Some(message.id.0);
-                                    }
-                                }
+                            // 收到的照片和图片文件转发到前端，由前端随响应一并提交
+                            let received_images =
+                                download_message_images(&core, &message, operation_message_id)
+                                    .await;
+                            image_count += received_images.len();
+                            for image in received_images {
+                                let event = TelegramEvent::ImageReceived { image };
+                                let _ = app_handle.emit("telegram-event", &event);
                             }
 
-                            if let Ok(Some(event)) = handle_text_message(
+                            if let Ok(Some(mut event)) = handle_text_message(
+                                &core,
                                 &message,
-                                core.chat_id,
-                                None, // 简化版本不过滤消息ID
+                                operation_message_id, // 早于操作消息的消息属于之前的请求
                             )
                             .await
                             {
+                                // 提示词按钮转换为文本更新，由前端统一处理
+                                if let TelegramEvent::PromptSelected { name, content } = &event {
+                                    user_input = insert_prompt_content(&user_input, content);
+                                    let _ = core
+                                        .send_message(&format!("📝 已插入提示词「{}」", name))
+                                        .await;
+                                    event = TelegramEvent::TextUpdated {
+                                        text: user_input.clone(),
+                                    };
+                                }
+
+                                // 图片说明追加到已有输入，转换为文本更新
+                                if let TelegramEvent::CaptionReceived { text } = &event {
+                                    event = TelegramEvent::TextUpdated {
+                                        text: append_caption(&user_input, text),
+                                    };
+                                }
+
                                 // 处理发送和继续按钮，发送反馈消息
                                 match &event {
-                                    crate::telegram::TelegramEvent::SendPressed => {
+                                    crate::telegram::TelegramEvent::SendPressed { .. } => {
                                         let selected_list: Vec<String> =
                                             selected_options.iter().cloned().collect();
 
-                                        // 使用统一的反馈消息生成函数
+                                        // 使用统一的反馈消息生成函数（包含条件性prompt内容）
+                                        let final_input = compose_user_input(
+                                            &user_input,
+                                            core.conditional_prompts(),
+                                        );
                                         let feedback_message =
                                             crate::telegram::core::build_feedback_message(
                                                 &selected_list,
-                                                &user_input,
+                                                &final_input,
+                                                image_count,
                                                 false, // 不是继续操作
                                             );
 
                                         let _ = core.send_message(&feedback_message).await;
                                     }
-                                    crate::telegram::TelegramEvent::ContinuePressed => {
+                                    crate::telegram::TelegramEvent::ContinuePressed { .. } => {
                                         // 使用统一的反馈消息生成函数
                                         let feedback_message =
                                             crate::telegram::core::build_feedback_message(
                                                 &[],  // 继续操作没有选项
                                                 "",   // 继续操作没有用户输入
+                                                0,    // 继续操作没有图片
                                                 true, // 是继续操作
                                             );
 
@@ -1,16 +1,24 @@
 use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
 use serde::Serialize;
 // use tauri::{AppHandle, Emitter}; // 暂时不需要，由调用方处理事件
 use teloxide::{
+    net::Download,
     prelude::*,
     types::{
-        ChatId, InlineKeyboardButton, InlineKeyboardMarkup, KeyboardButton, KeyboardMarkup,
-        MessageId, ParseMode,
+        ChatId, FileId, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, KeyboardButton,
+        KeyboardMarkup, MessageId, ParseMode, Recipient, ThreadId, User, UserId,
     },
-    Bot,
+    ApiError, Bot, RequestError,
 };
 
-use super::markdown::process_telegram_markdown;
+use super::markdown::{process_telegram_html, process_telegram_markdown};
+use super::message_split::{split_message, MessageChunk};
+use crate::config::{CustomPrompt, CustomPromptConfig, TelegramConfig};
+use crate::constants::telegram as telegram_constants;
+use crate::log_important;
+use crate::mcp::types::ImageAttachment;
+use crate::utils::create_http_client_builder;
 
 /// Telegram事件类型
 #[derive(Debug, Clone, Serialize)]
@@ -20,16 +28,44 @@ pub enum TelegramEvent {
     OptionToggled { option: String, selected: bool },
     /// 文本输入更新
     TextUpdated { text: String },
-    /// 继续按钮点击
-    ContinuePressed,
-    /// 发送按钮点击
-    SendPressed,
+    /// 收到图片（照片或图片文件）
+    ImageReceived { image: ImageAttachment },
+    /// 继续按钮点击（记录操作者）
+    ContinuePressed { responder: Option<String> },
+    /// 发送按钮点击（记录操作者）
+    SendPressed { responder: Option<String> },
+    /// 点击了自定义提示词快捷回复按钮（由监听器合成到用户输入）
+    PromptSelected { name: String, content: String },
+    /// 图片或文件消息的说明文字（由监听器追加到用户输入，不覆盖已输入的内容）
+    CaptionReceived { text: String },
+    /// 条件性提示词开关变化
+    ConditionToggled { prompt_id: String, current_state: bool },
+}
+
+/// inline keyboard 回调动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallbackAction {
+    /// 切换指定索引的选项
+    Toggle(usize),
+    /// 翻到指定页
+    Page(usize),
+    /// 切换指定索引的条件性提示词
+    Condition(usize),
+    /// 无操作（页码指示按钮）
+    Noop,
 }
 
 /// Telegram Bot 核心功能
+#[derive(Clone)]
 pub struct TelegramCore {
     pub bot: Bot,
     pub chat_id: ChatId,
+    /// 论坛话题ID，设置后消息发送到该话题，并只接收该话题内的回复
+    pub thread_id: Option<ThreadId>,
+    /// 允许回复的用户，为空时不限制
+    pub allowed_user_ids: Vec<UserId>,
+    /// 启用的自定义提示词：普通提示词作为快捷回复按钮，条件性提示词作为开关
+    pub custom_prompts: Vec<CustomPrompt>,
 }
 
 impl TelegramCore {
@@ -39,27 +75,100 @@ impl TelegramCore {
     }
 
     /// 创建新的Telegram核心实例，支持自定义API URL
+    ///
+    /// 只接受数字Chat ID，`@username` 需要联网解析，请使用 [`TelegramCore::from_config`]
     pub fn new_with_api_url(bot_token: String, chat_id: String, api_url: Option<String>) -> Result<Self> {
-        let mut bot = Bot::new(bot_token);
+        let bot = create_bot(&bot_token, api_url.as_deref())?;
+        let chat_id = parse_numeric_chat_id(&chat_id)?;
 
-        // 如果提供了自定义API URL，则设置它
-        if let Some(url_str) = api_url {
-            let url = reqwest::Url::parse(&url_str)
-                .map_err(|e| anyhow::anyhow!("无效的API URL格式: {}", e))?;
-            bot = bot.set_api_url(url);
-        }
+        Ok(Self {
+            bot,
+            chat_id,
+            thread_id: None,
+            allowed_user_ids: Vec::new(),
+            custom_prompts: Vec::new(),
+        })
+    }
+
+    /// 根据Telegram配置创建实例（解析@username、话题和允许回复的用户）
+    pub async fn from_config(telegram_config: &TelegramConfig) -> Result<Self> {
+        // 使用默认API URL时传递None，否则传递自定义URL
+        let api_url = if telegram_config.api_base_url == telegram_constants::API_BASE_URL {
+            None
+        } else {
+            Some(telegram_config.api_base_url.as_str())
+        };
 
-        // 解析chat_id
-        let chat_id = if chat_id.starts_with('@') {
-            return Err(anyhow::anyhow!("暂不支持@username格式，请使用数字Chat ID"));
+        let bot = create_bot(&telegram_config.bot_token, api_url)?;
+        let chat_id = resolve_chat_id(&bot, &telegram_config.chat_id).await?;
+
+        Ok(Self {
+            bot,
+            chat_id,
+            thread_id: telegram_config
+                .message_thread_id
+                .map(|id| ThreadId(MessageId(id))),
+            allowed_user_ids: telegram_config
+                .allowed_user_ids
+                .iter()
+                .map(|id| UserId(*id as u64))
+                .collect(),
+            custom_prompts: Vec::new(),
+        })
+    }
+
+    /// 使用自定义提示词配置（未启用时不显示提示词按钮）
+    pub fn with_custom_prompts(mut self, prompt_config: &CustomPromptConfig) -> Self {
+        self.custom_prompts = if prompt_config.enabled {
+            let mut prompts = prompt_config.prompts.clone();
+            prompts.sort_by_key(|prompt| prompt.sort_order);
+            prompts
         } else {
-            let id = chat_id
-                .parse::<i64>()
-                .map_err(|_| anyhow::anyhow!("无效的Chat ID格式，请使用数字ID"))?;
-            ChatId(id)
+            Vec::new()
         };
+        self
+    }
+
+    /// 普通提示词（快捷回复按钮）
+    pub fn normal_prompts(&self) -> impl Iterator<Item = &CustomPrompt> {
+        self.custom_prompts.iter().filter(|prompt| !is_conditional_prompt(prompt))
+    }
+
+    /// 条件性提示词（inline 开关）
+    pub fn conditional_prompts(&self) -> impl Iterator<Item = &CustomPrompt> {
+        self.custom_prompts.iter().filter(|prompt| is_conditional_prompt(prompt))
+    }
+
+    /// 切换指定索引的条件性提示词，返回切换后的提示词
+    pub fn toggle_conditional_prompt(&mut self, index: usize) -> Option<&CustomPrompt> {
+        let prompt = self
+            .custom_prompts
+            .iter_mut()
+            .filter(|prompt| is_conditional_prompt(prompt))
+            .nth(index)?;
+        prompt.current_state = !prompt.current_state;
+        Some(prompt)
+    }
+
+    /// 检查消息是否来自目标聊天（及目标话题）
+    pub fn is_target_message(&self, message: &Message) -> bool {
+        if message.chat.id != self.chat_id {
+            return false;
+        }
+
+        match self.thread_id {
+            Some(thread_id) => message.thread_id == Some(thread_id),
+            None => true,
+        }
+    }
+
+    /// 检查用户是否允许回复
+    pub fn is_allowed_user(&self, user: Option<&User>) -> bool {
+        if self.allowed_user_ids.is_empty() {
+            return true;
+        }
 
-        Ok(Self { bot, chat_id })
+        user.is_some_and(|u| self.allowed_user_ids.contains(&u.id))
     }
 
     /// 发送普通消息
@@ -73,112 +182,213 @@ impl TelegramCore {
         message: &str,
         use_markdown: bool,
     ) -> Result<()> {
-        let mut send_request = self.bot.send_message(self.chat_id, message);
-
-        // 如果启用Markdown，设置解析模式
-        if use_markdown {
-            send_request = send_request.parse_mode(ParseMode::MarkdownV2);
-        }
-
-        send_request
+        self.send_long_message(message, use_markdown, None)
             .await
             .map_err(|e| anyhow::anyhow!("发送消息失败: {}", e))?;
 
         Ok(())
     }
 
-    /// 发送选项消息（消息一）
+    /// 发送选项消息（消息一），返回消息ID
+    ///
+    /// `request_token` 用于区分不同请求的按钮回调，见 [`callback_token`]
     pub async fn send_options_message(
         &self,
         message: &str,
         predefined_options: &[String],
         is_markdown: bool,
-    ) -> Result<()> {
-        // 处理消息内容
-        let processed_message = if is_markdown {
-            process_telegram_markdown(message)
+        request_token: &str,
+    ) -> Result<i32> {
+        // 按钮放不下完整选项时，在消息正文中列出全部选项
+        let message = if needs_option_listing(predefined_options) {
+            format!("{}{}", message, build_option_listing(predefined_options))
         } else {
             message.to_string()
         };
 
-        // 创建消息发送请求
-        let mut send_request = self.bot.send_message(self.chat_id, processed_message);
+        // 只有当有预定义选项或条件性提示词时才添加inline keyboard
+        let inline_keyboard = if self.has_inline_keyboard(predefined_options) {
+            Some(self.create_inline_keyboard(request_token, predefined_options, &[], 0)?)
+        } else {
+            None
+        };
 
-        // 只有当有预定义选项时才添加inline keyboard
-        if !predefined_options.is_empty() {
-            let inline_keyboard = Self::create_inline_keyboard(predefined_options, &[])?;
-            send_request = send_request.reply_markup(inline_keyboard);
-        }
+        self.send_long_message(&message, is_markdown, inline_keyboard)
+            .await
+            .map_err(|e| anyhow::anyhow!("发送选项消息失败: {}", e))
+    }
 
-        // 如果是Markdown，设置解析模式
-        if is_markdown {
-            send_request = send_request.parse_mode(ParseMode::MarkdownV2);
+    /// 发送可能超长的消息，返回最后一条消息的ID
+    ///
+    /// 按段落和代码块边界拆分为多条消息，过大的代码块以 .md 文件发送，
+    /// `reply_markup` 附加在最后一条消息上
+    async fn send_long_message(
+        &self,
+        message: &str,
+        is_markdown: bool,
+        reply_markup: Option<InlineKeyboardMarkup>,
+    ) -> Result<i32> {
+        let measure = |text: &str| {
+            if is_markdown {
+                process_telegram_markdown(text).chars().count()
+            } else {
+                text.chars().count()
+            }
+        };
+        let chunks = split_message(message, telegram_constants::MAX_MESSAGE_LENGTH, measure);
+
+        let last_index = chunks.len() - 1;
+        let mut last_message_id = 0;
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let markup = if index == last_index {
+                reply_markup.clone()
+            } else {
+                None
+            };
+
+            last_message_id = match chunk {
+                MessageChunk::Text(text) => self.send_text_chunk(&text, is_markdown, markup).await?,
+                MessageChunk::Document { file_name, content } => {
+                    self.send_document_chunk(file_name, content, markup).await?
+                }
+            };
         }
 
-        match send_request.await {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                let error_str = e.to_string();
+        Ok(last_message_id)
+    }
 
-                // 检查是否是JSON解析错误但消息实际发送成功
-                let has_parsing_json = error_str.contains("parsing JSON");
-                let has_ok_true = error_str.contains("\\\"ok\\\":true");
+    /// 发送单条文本消息，MarkdownV2 被拒绝时依次降级为 HTML 和纯文本
+    async fn send_text_chunk(
+        &self,
+        text: &str,
+        is_markdown: bool,
+        reply_markup: Option<InlineKeyboardMarkup>,
+    ) -> Result<i32> {
+        if is_markdown {
+            let attempts = [
+                (process_telegram_markdown(text), ParseMode::MarkdownV2),
+                (process_telegram_html(text), ParseMode::Html),
+            ];
 
-                if has_parsing_json && has_ok_true {
-                    // 消息实际发送成功
-                    Ok(())
-                } else {
-                    Err(anyhow::anyhow!("发送选项消息失败: {}", e))
+            for (rendered, parse_mode) in attempts {
+                match self
+                    .send_rendered_text(rendered, Some(parse_mode), reply_markup.clone())
+                    .await
+                {
+                    Err(e) if is_formatting_error(&e) => {
+                        log_important!(warn, "Telegram拒绝{:?}格式消息，降级重试: {}", parse_mode, e);
+                    }
+                    result => return sent_message_id(result),
                 }
             }
         }
+
+        sent_message_id(self.send_rendered_text(text.to_string(), None, reply_markup).await)
+    }
+
+    /// 按指定解析模式发送文本
+    async fn send_rendered_text(
+        &self,
+        text: String,
+        parse_mode: Option<ParseMode>,
+        reply_markup: Option<InlineKeyboardMarkup>,
+    ) -> Result<Message, RequestError> {
+        let mut send_request = self.bot.send_message(self.chat_id, text);
+        if let Some(thread_id) = self.thread_id {
+            send_request = send_request.message_thread_id(thread_id);
+        }
+        if let Some(parse_mode) = parse_mode {
+            send_request = send_request.parse_mode(parse_mode);
+        }
+        if let Some(reply_markup) = reply_markup {
+            send_request = send_request.reply_markup(reply_markup);
+        }
+
+        send_request.await
+    }
+
+    /// 以 .md 文件发送过大的代码块
+    async fn send_document_chunk(
+        &self,
+        file_name: String,
+        content: String,
+        reply_markup: Option<InlineKeyboardMarkup>,
+    ) -> Result<i32> {
+        let caption = format!("📄 代码块较长，已作为文件 {} 发送", file_name);
+        let document = InputFile::memory(content.into_bytes()).file_name(file_name);
+
+        let mut send_request = self
+            .bot
+            .send_document(self.chat_id, document)
+            .caption(caption);
+        if let Some(thread_id) = self.thread_id {
+            send_request = send_request.message_thread_id(thread_id);
+        }
+        if let Some(reply_markup) = reply_markup {
+            send_request = send_request.reply_markup(reply_markup);
+        }
+
+        sent_message_id(send_request.await)
     }
 
     /// 发送操作消息（消息二）
     pub async fn send_operation_message(&self, continue_reply_enabled: bool) -> Result<i32> {
         // 创建reply keyboard
-        let reply_keyboard = Self::create_reply_keyboard(continue_reply_enabled);
+        let reply_keyboard = self.create_reply_keyboard(continue_reply_enabled);
 
         // 发送操作消息
         let operation_message = "键盘上选择操作完成对话";
 
-        match self
+        let mut send_request = self
             .bot
             .send_message(self.chat_id, operation_message)
-            .reply_markup(reply_keyboard)
-            .await
-        {
-            Ok(msg) => Ok(msg.id.0),
-            Err(e) => {
-                let error_str = e.to_string();
-                // 检查是否是JSON解析错误但消息实际发送成功
-                if error_str.contains("parsing JSON") && error_str.contains("\\\"ok\\\":true") {
-                    // 消息实际发送成功，返回默认ID
-                    Ok(0)
-                } else {
-                    Err(anyhow::anyhow!("发送操作消息失败: {}", e))
-                }
-            }
+            .reply_markup(reply_keyboard);
+        if let Some(thread_id) = self.thread_id {
+            send_request = send_request.message_thread_id(thread_id);
         }
+
+        sent_message_id(send_request.await)
+            .map_err(|e| anyhow::anyhow!("发送操作消息失败: {}", e))
+    }
+
+    /// 是否需要inline keyboard（有预定义选项或条件性提示词）
+    pub fn has_inline_keyboard(&self, predefined_options: &[String]) -> bool {
+        !predefined_options.is_empty() || self.conditional_prompts().next().is_some()
     }
 
-    /// 创建inline keyboard
+    /// 创建inline keyboard（显示指定页的选项和条件性提示词开关）
     pub fn create_inline_keyboard(
+        &self,
+        request_token: &str,
         predefined_options: &[String],
         selected_options: &[String],
+        page: usize,
     ) -> Result<InlineKeyboardMarkup> {
+        let per_page = telegram_constants::OPTIONS_PER_PAGE;
+        let page_count = option_page_count(predefined_options.len());
+        let page = page.min(page_count - 1);
+        let page_start = page * per_page;
+        let page_end = (page_start + per_page).min(predefined_options.len());
+
         let mut keyboard_rows = Vec::new();
 
-        // 添加选项按钮（每行最多2个）
-        for chunk in predefined_options.chunks(2) {
+        // 添加选项按钮（每行最多2个），回调数据只携带选项索引
+        let page_options: Vec<(usize, &String)> = predefined_options
+            .iter()
+            .enumerate()
+            .skip(page_start)
+            .take(page_end - page_start)
+            .collect();
+        for chunk in page_options.chunks(2) {
             let mut row = Vec::new();
-            for option in chunk {
-                let callback_data = format!("toggle:{}", option);
+            for (index, option) in chunk {
+                let callback_data = build_callback_data(request_token, CallbackAction::Toggle(*index));
+                let label = format!("{}. {}", index + 1, truncate_option_label(option));
                 // 根据选中状态显示按钮
                 let button_text = if selected_options.contains(option) {
-                    format!("✅ {}", option)
+                    format!("✅ {}", label)
                 } else {
-                    option.to_string()
+                    label
                 };
 
                 row.push(InlineKeyboardButton::callback(button_text, callback_data));
@@ -186,31 +396,82 @@ impl TelegramCore {
             keyboard_rows.push(row);
         }
 
+        // 多页时添加翻页按钮
+        if page_count > 1 {
+            let mut nav_row = Vec::new();
+            if page > 0 {
+                nav_row.push(InlineKeyboardButton::callback(
+                    "◀️ 上一页",
+                    build_callback_data(request_token, CallbackAction::Page(page - 1)),
+                ));
+            }
+            nav_row.push(InlineKeyboardButton::callback(
+                format!("{}/{}", page + 1, page_count),
+                build_callback_data(request_token, CallbackAction::Noop),
+            ));
+            if page + 1 < page_count {
+                nav_row.push(InlineKeyboardButton::callback(
+                    "下一页 ▶️",
+                    build_callback_data(request_token, CallbackAction::Page(page + 1)),
+                ));
+            }
+            keyboard_rows.push(nav_row);
+        }
+
+        // 条件性提示词开关（每行一个），与界面中的开关共享状态
+        for (index, prompt) in self.conditional_prompts().enumerate() {
+            let state_icon = if prompt.current_state { "✅" } else { "⬜" };
+            let label = prompt
+                .condition_text
+                .as_deref()
+                .filter(|text| !text.trim().is_empty())
+                .unwrap_or(&prompt.name);
+            keyboard_rows.push(vec![InlineKeyboardButton::callback(
+                format!("{} {}", state_icon, truncate_option_label(label)),
+                build_callback_data(request_token, CallbackAction::Condition(index)),
+            )]);
+        }
+
         let keyboard = InlineKeyboardMarkup::new(keyboard_rows);
         Ok(keyboard)
     }
 
-    /// 创建reply keyboard
-    pub fn create_reply_keyboard(continue_reply_enabled: bool) -> KeyboardMarkup {
+    /// 创建reply keyboard（普通提示词在上，继续/发送在最后一行）
+    pub fn create_reply_keyboard(&self, continue_reply_enabled: bool) -> KeyboardMarkup {
+        let prompt_buttons: Vec<KeyboardButton> = self
+            .normal_prompts()
+            .map(|prompt| KeyboardButton::new(prompt_button_text(prompt)))
+            .collect();
+        let mut keyboard_rows: Vec<Vec<KeyboardButton>> =
+            prompt_buttons.chunks(2).map(|row| row.to_vec()).collect();
+
         let mut keyboard_buttons = vec![KeyboardButton::new("↗️发送")];
 
         if continue_reply_enabled {
             keyboard_buttons.insert(0, KeyboardButton::new("⏩继续"));
         }
+        keyboard_rows.push(keyboard_buttons);
 
-        KeyboardMarkup::new(vec![keyboard_buttons])
+        KeyboardMarkup::new(keyboard_rows)
             .resize_keyboard()
             .one_time_keyboard()
     }
 
-    /// 更新inline keyboard中的选项状态
+    /// 更新inline keyboard中的选项状态和当前页
     pub async fn update_inline_keyboard(
         &self,
         message_id: i32,
+        request_token: &str,
         predefined_options: &[String],
         selected_options: &[String],
+        page: usize,
     ) -> Result<()> {
-        let new_keyboard = Self::create_inline_keyboard(predefined_options, selected_options)?;
+        let new_keyboard = self.create_inline_keyboard(
+            request_token,
+            predefined_options,
+            selected_options,
+            page,
+        )?;
 
         match self
             .bot
@@ -228,41 +489,173 @@ impl TelegramCore {
 }
 
 /// 处理callback query的通用函数（不发送事件，由调用方处理）
+///
+/// 只接受属于当前请求（`request_token`）的回调，旧请求残留的按钮会被忽略
 pub async fn handle_callback_query(
-    bot: &Bot,
+    core: &TelegramCore,
     callback_query: &CallbackQuery,
-    target_chat_id: ChatId,
-) -> ResponseResult<Option<String>> {
+    request_token: &str,
+) -> ResponseResult<Option<CallbackAction>> {
     // 检查是否是目标聊天
     if let Some(message) = &callback_query.message {
-        if message.chat().id != target_chat_id {
+        if message.chat().id != core.chat_id {
             return Ok(None);
         }
     }
 
-    let mut toggled_option = None;
+    let action = callback_query
+        .data
+        .as_deref()
+        .and_then(|data| parse_callback_data(data, request_token));
 
-    if let Some(data) = &callback_query.data {
-        if data.starts_with("toggle:") {
-            let option = data.strip_prefix("toggle:").unwrap().to_string();
-            toggled_option = Some(option);
-        }
+    // 检查用户是否允许操作（只对当前请求的按钮提示）
+    if action.is_some() && !core.is_allowed_user(Some(&callback_query.from)) {
+        core.bot
+            .answer_callback_query(&callback_query.id)
+            .text("⛔ 你没有权限回复此请求")
+            .show_alert(true)
+            .await?;
+        return Ok(None);
     }
 
     // 回答callback query
-    bot.answer_callback_query(&callback_query.id).await?;
+    core.bot.answer_callback_query(&callback_query.id).await?;
+
+    Ok(action)
+}
+
+/// 根据请求ID生成按钮回调使用的短标识
+///
+/// Telegram 限制 callback_data 最长 64 字节，这里只取请求ID中的前若干个字母数字字符
+pub fn callback_token(request_id: &str) -> String {
+    request_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(telegram_constants::CALLBACK_TOKEN_LENGTH)
+        .collect()
+}
+
+/// 构建按钮回调数据：`t:<token>:<index>`、`p:<token>:<page>`、`c:<token>:<index>` 或 `n:<token>`
+pub fn build_callback_data(request_token: &str, action: CallbackAction) -> String {
+    match action {
+        CallbackAction::Toggle(index) => format!("t:{}:{}", request_token, index),
+        CallbackAction::Page(page) => format!("p:{}:{}", request_token, page),
+        CallbackAction::Condition(index) => format!("c:{}:{}", request_token, index),
+        CallbackAction::Noop => format!("n:{}", request_token),
+    }
+}
+
+/// 解析按钮回调数据，不属于当前请求的回调返回 None
+pub fn parse_callback_data(data: &str, request_token: &str) -> Option<CallbackAction> {
+    let mut parts = data.splitn(3, ':');
+    let kind = parts.next()?;
+    if parts.next()? != request_token {
+        return None;
+    }
+
+    match (kind, parts.next()) {
+        ("t", Some(value)) => value.parse().ok().map(CallbackAction::Toggle),
+        ("p", Some(value)) => value.parse().ok().map(CallbackAction::Page),
+        ("c", Some(value)) => value.parse().ok().map(CallbackAction::Condition),
+        ("n", None) => Some(CallbackAction::Noop),
+        _ => None,
+    }
+}
+
+/// 是否为条件性提示词
+fn is_conditional_prompt(prompt: &CustomPrompt) -> bool {
+    prompt.r#type == "conditional"
+}
+
+/// 普通提示词按钮的文字，收到相同文字的消息即视为点击了该按钮
+fn prompt_button_text(prompt: &CustomPrompt) -> String {
+    format!("📝 {}", prompt.name)
+}
+
+/// 将提示词内容插入用户输入（与界面一致：内容为空时清空，已有输入时追加）
+pub fn insert_prompt_content(user_input: &str, content: &str) -> String {
+    if content.trim().is_empty() {
+        String::new()
+    } else if user_input.trim().is_empty() {
+        content.to_string()
+    } else {
+        format!("{}\n\n{}", user_input.trim(), content)
+    }
+}
+
+/// 将图片说明追加到用户输入，已有输入时另起一行
+pub fn append_caption(user_input: &str, caption: &str) -> String {
+    match (user_input.trim().is_empty(), caption.trim().is_empty()) {
+        (_, true) => user_input.to_string(),
+        (true, false) => caption.trim().to_string(),
+        (false, false) => format!("{}\n{}", user_input.trim_end(), caption.trim()),
+    }
+}
+
+/// 在用户输入后追加条件性提示词的模板内容（与界面的 generateConditionalContent 一致）
+pub fn compose_user_input<'a>(
+    user_input: &str,
+    conditional_prompts: impl Iterator<Item = &'a CustomPrompt>,
+) -> String {
+    let conditional_texts: Vec<&str> = conditional_prompts
+        .filter_map(|prompt| {
+            let template = if prompt.current_state {
+                prompt.template_true.as_deref()
+            } else {
+                prompt.template_false.as_deref()
+            };
+            template.map(str::trim).filter(|text| !text.is_empty())
+        })
+        .collect();
+
+    if conditional_texts.is_empty() {
+        user_input.to_string()
+    } else {
+        format!("{}\n\n{}", user_input, conditional_texts.join("\n"))
+    }
+}
+
+/// 计算选项分页数（至少一页）
+pub fn option_page_count(option_count: usize) -> usize {
+    option_count.div_ceil(telegram_constants::OPTIONS_PER_PAGE).max(1)
+}
+
+/// 截断过长的按钮文字
+fn truncate_option_label(option: &str) -> String {
+    let max_chars = telegram_constants::MAX_BUTTON_LABEL_CHARS;
+    if option.chars().count() <= max_chars {
+        option.to_string()
+    } else {
+        let truncated: String = option.chars().take(max_chars - 1).collect();
+        format!("{}…", truncated)
+    }
+}
 
-    Ok(toggled_option)
+/// 按钮无法完整展示选项（被截断或需要翻页）时，需要在正文列出选项
+fn needs_option_listing(predefined_options: &[String]) -> bool {
+    predefined_options.len() > telegram_constants::OPTIONS_PER_PAGE
+        || predefined_options
+            .iter()
+            .any(|option| option.chars().count() > telegram_constants::MAX_BUTTON_LABEL_CHARS)
+}
+
+/// 生成正文中的完整选项列表，序号与按钮一致
+fn build_option_listing(predefined_options: &[String]) -> String {
+    let mut listing = "\n\n📋 选项：".to_string();
+    for (index, option) in predefined_options.iter().enumerate() {
+        listing.push_str(&format!("\n{}. {}", index + 1, option));
+    }
+    listing
 }
 
 /// 处理文本消息的通用函数（不发送事件，由调用方处理）
 pub async fn handle_text_message(
+    core: &TelegramCore,
     message: &Message,
-    target_chat_id: ChatId,
     operation_message_id: Option<i32>,
 ) -> ResponseResult<Option<TelegramEvent>> {
-    // 检查是否是目标聊天
-    if message.chat.id != target_chat_id {
+    // 检查是否是目标聊天和允许的用户
+    if !core.is_target_message(message) || !core.is_allowed_user(message.from.as_ref()) {
         return Ok(None);
     }
 
@@ -274,29 +667,256 @@ pub async fn handle_text_message(
     }
 
     if let Some(text) = message.text() {
+        let responder = message.from.as_ref().map(format_responder);
         let event = match text {
-            "⏩继续" => TelegramEvent::ContinuePressed,
-            "↗️发送" => TelegramEvent::SendPressed,
-            _ => TelegramEvent::TextUpdated {
-                text: text.to_string(),
+            "⏩继续" => TelegramEvent::ContinuePressed { responder },
+            "↗️发送" => TelegramEvent::SendPressed { responder },
+            _ => match core.normal_prompts().find(|prompt| prompt_button_text(prompt) == text) {
+                Some(prompt) => TelegramEvent::PromptSelected {
+                    name: prompt.name.clone(),
+                    content: prompt.content.clone(),
+                },
+                None => TelegramEvent::TextUpdated {
+                    text: text.to_string(),
+                },
             },
         };
 
         return Ok(Some(event));
     }
 
+    // 图片/文件消息的说明文字追加到用户输入
+    if let Some(caption) = message.caption() {
+        return Ok(Some(TelegramEvent::CaptionReceived {
+            text: caption.to_string(),
+        }));
+    }
+
     Ok(None)
 }
 
+/// 下载消息中的图片（照片或图片类型的文件），转换为图片附件
+///
+/// 非图片文件和下载失败的图片会被跳过并记录日志，不影响后续消息处理
+pub async fn download_message_images(
+    core: &TelegramCore,
+    message: &Message,
+    operation_message_id: Option<i32>,
+) -> Vec<ImageAttachment> {
+    // 检查是否是目标聊天和允许的用户
+    if !core.is_target_message(message) || !core.is_allowed_user(message.from.as_ref()) {
+        return Vec::new();
+    }
+
+    let bot = &core.bot;
+
+    // 检查消息ID过滤
+    if let Some(op_id) = operation_message_id {
+        if message.id.0 <= op_id {
+            return Vec::new();
+        }
+    }
+
+    let mut images = Vec::new();
+
+    // 照片：Telegram会提供多个尺寸，取最大的一张
+    if let Some(photo) = message.photo().and_then(|sizes| sizes.last()) {
+        match download_telegram_file(bot, &photo.file.id, photo.file.size).await {
+            Ok(bytes) => {
+                // Telegram会将照片统一压缩为JPEG
+                let media_type = detect_image_media_type(&bytes).unwrap_or("image/jpeg");
+                images.push(ImageAttachment {
+                    data: general_purpose::STANDARD.encode(&bytes),
+                    media_type: media_type.to_string(),
+                    filename: None,
+                });
+            }
+            Err(e) => log_important!(warn, "下载Telegram照片失败: {}", e),
+        }
+    }
+
+    // 文件：只接受图片类型（以原图发送的截图会走这里）
+    if let Some(document) = message.document() {
+        let declared_type = document
+            .mime_type
+            .as_ref()
+            .map(|m| m.essence_str().to_string())
+            .filter(|m| m.starts_with("image/"));
+
+        if declared_type.is_some() || is_image_file_name(document.file_name.as_deref()) {
+            match download_telegram_file(bot, &document.file.id, document.file.size).await {
+                Ok(bytes) => {
+                    let media_type = detect_image_media_type(&bytes)
+                        .map(|t| t.to_string())
+                        .or(declared_type);
+
+                    match media_type {
+                        Some(media_type) => images.push(ImageAttachment {
+                            data: general_purpose::STANDARD.encode(&bytes),
+                            media_type,
+                            filename: document.file_name.clone(),
+                        }),
+                        None => log_important!(
+                            warn,
+                            "无法识别Telegram文件的图片格式，已忽略: {:?}",
+                            document.file_name
+                        ),
+                    }
+                }
+                Err(e) => log_important!(warn, "下载Telegram文件失败: {}", e),
+            }
+        } else {
+            log_important!(warn, "忽略非图片类型的Telegram文件: {:?}", document.file_name);
+        }
+    }
+
+    images
+}
+
+/// 提取已发送消息的ID
+fn sent_message_id(result: Result<Message, RequestError>) -> Result<i32> {
+    match result {
+        Ok(msg) => Ok(msg.id.0),
+        Err(e) => {
+            let error_str = e.to_string();
+            // 检查是否是JSON解析错误但消息实际发送成功
+            if error_str.contains("parsing JSON") && error_str.contains("\\\"ok\\\":true") {
+                // 消息实际发送成功，返回默认ID
+                Ok(0)
+            } else {
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// 是否为格式相关的错误（实体解析失败或转义后超长），可降级重试
+fn is_formatting_error(error: &RequestError) -> bool {
+    matches!(
+        error,
+        RequestError::Api(ApiError::CantParseEntities(_) | ApiError::MessageIsTooLong)
+    )
+}
+
+/// 生成回复者的显示名称，如 `张三 (@zhangsan)`
+pub fn format_responder(user: &User) -> String {
+    match &user.username {
+        Some(username) => format!("{} (@{})", user.full_name(), username),
+        None => user.full_name(),
+    }
+}
+
+/// 创建Bot实例，支持自定义API URL，使用共享的网络配置（代理、CA证书）
+pub(crate) fn create_bot(bot_token: &str, api_url: Option<&str>) -> Result<Bot> {
+    // Bot API 请求都很短（长轮询不超过10秒），设置整体超时避免连接卡住
+    let client = create_http_client_builder()?
+        .timeout(std::time::Duration::from_millis(telegram_constants::REQUEST_TIMEOUT_MS))
+        .build()
+        .map_err(|e| anyhow::anyhow!("创建HTTP客户端失败: {}", e))?;
+    let mut bot = Bot::with_client(bot_token, client);
+
+    // 如果提供了自定义API URL，则设置它
+    if let Some(url_str) = api_url {
+        let url = reqwest::Url::parse(url_str)
+            .map_err(|e| anyhow::anyhow!("无效的API URL格式: {}", e))?;
+        bot = bot.set_api_url(url);
+    }
+
+    Ok(bot)
+}
+
+/// 解析数字Chat ID（群组为负数）
+fn parse_numeric_chat_id(chat_id: &str) -> Result<ChatId> {
+    let chat_id = chat_id.trim();
+    if chat_id.starts_with('@') {
+        return Err(anyhow::anyhow!("@username格式的Chat ID需要通过getChat解析"));
+    }
+
+    let id = chat_id
+        .parse::<i64>()
+        .map_err(|_| anyhow::anyhow!("无效的Chat ID格式，请使用数字ID或@频道用户名"))?;
+    Ok(ChatId(id))
+}
+
+/// 解析Chat ID，`@username` 形式通过Bot API的getChat获取数字ID
+pub async fn resolve_chat_id(bot: &Bot, chat_id: &str) -> Result<ChatId> {
+    let chat_id = chat_id.trim();
+    if !chat_id.starts_with('@') {
+        return parse_numeric_chat_id(chat_id);
+    }
+
+    let chat = bot
+        .get_chat(Recipient::ChannelUsername(chat_id.to_string()))
+        .await
+        .map_err(|e| anyhow::anyhow!("解析{}失败，请确认Bot已加入该群组或频道: {}", chat_id, e))?;
+
+    Ok(chat.id)
+}
+
+/// 通过Bot API的getFile下载文件内容
+async fn download_telegram_file(bot: &Bot, file_id: &FileId, file_size: u32) -> Result<Vec<u8>> {
+    if file_size > telegram_constants::MAX_DOWNLOAD_FILE_SIZE {
+        return Err(anyhow::anyhow!(
+            "文件过大（{} 字节），超过Bot API下载上限 {} 字节",
+            file_size,
+            telegram_constants::MAX_DOWNLOAD_FILE_SIZE
+        ));
+    }
+
+    let file = bot
+        .get_file(file_id.clone())
+        .await
+        .map_err(|e| anyhow::anyhow!("获取文件信息失败: {}", e))?;
+
+    let mut buffer = Vec::with_capacity(file_size as usize);
+    bot.download_file(&file.path, &mut buffer)
+        .await
+        .map_err(|e| anyhow::anyhow!("下载文件失败: {}", e))?;
+
+    Ok(buffer)
+}
+
+/// 根据文件头识别图片的媒体类型
+fn detect_image_media_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.starts_with(b"BM") {
+        Some("image/bmp")
+    } else {
+        None
+    }
+}
+
+/// 根据文件扩展名判断是否为图片
+fn is_image_file_name(file_name: Option<&str>) -> bool {
+    let Some(name) = file_name else {
+        return false;
+    };
+
+    let lower = name.to_lowercase();
+    [".png", ".jpg", ".jpeg", ".gif", ".webp", ".bmp"]
+        .iter()
+        .any(|ext| lower.ends_with(ext))
+}
+
 /// 生成统一的反馈消息
 pub fn build_feedback_message(
     selected_options: &[String],
     user_input: &str,
+    image_count: usize,
     is_continue: bool,
 ) -> String {
     if is_continue {
         // 继续操作的反馈消息
-        let continue_prompt = if let Ok(config) = crate::config::load_standalone_config() {
+        let continue_prompt = if let Ok(config) =
+            crate::config::load_project_config(crate::config::request_project_path())
+        {
             config.reply_config.continue_prompt
         } else {
             "请按照最佳实践继续".to_string()
@@ -319,20 +939,25 @@ pub fn build_feedback_message(
             feedback_message.push_str(&format!("\n📝 补充说明：\n{}", user_input));
         }
 
+        if image_count > 0 {
+            feedback_message.push_str(&format!("\n\n🖼️ 附带图片：{} 张", image_count));
+        }
+
         feedback_message
     }
 }
 
 /// 测试Telegram连接的通用函数
 pub async fn test_telegram_connection(bot_token: &str, chat_id: &str) -> Result<String> {
-    test_telegram_connection_with_api_url(bot_token, chat_id, None).await
+    test_telegram_connection_with_api_url(bot_token, chat_id, None, None).await
 }
 
 /// 测试Telegram连接的通用函数，支持自定义API URL
 pub async fn test_telegram_connection_with_api_url(
     bot_token: &str,
     chat_id: &str,
-    api_url: Option<&str>
+    api_url: Option<&str>,
+    message_thread_id: Option<i32>,
 ) -> Result<String> {
     if bot_token.trim().is_empty() {
         return Err(anyhow::anyhow!("Bot Token不能为空"));
@@ -343,26 +968,96 @@ pub async fn test_telegram_connection_with_api_url(
     }
 
     // 创建Bot实例
-    let mut bot = Bot::new(bot_token);
-
-    // 如果提供了自定义API URL，则设置它
-    if let Some(url_str) = api_url {
-        let url = reqwest::Url::parse(url_str)
-            .map_err(|e| anyhow::anyhow!("无效的API URL格式: {}", e))?;
-        bot = bot.set_api_url(url);
-    }
+    let bot = create_bot(bot_token, api_url)?;
 
-    // 验证Chat ID格式
-    let chat_id_parsed: i64 = chat_id
-        .parse()
-        .map_err(|_| anyhow::anyhow!("Chat ID格式无效，请输入有效的数字ID"))?;
+    // 解析Chat ID（支持@频道用户名）
+    let chat_id_parsed = resolve_chat_id(&bot, chat_id).await?;
 
     // 发送测试消息
     let test_message =
         "🤖 寸止应用测试消息\n\n这是一条来自寸止应用的测试消息，表示Telegram Bot配置成功！";
 
-    match bot.send_message(ChatId(chat_id_parsed), test_message).await {
+    let mut send_request = bot.send_message(chat_id_parsed, test_message);
+    if let Some(thread_id) = message_thread_id {
+        send_request = send_request.message_thread_id(ThreadId(MessageId(thread_id)));
+    }
+
+    match send_request.await {
         Ok(_) => Ok("测试消息发送成功！Telegram Bot配置正确。".to_string()),
         Err(e) => Err(anyhow::anyhow!("发送测试消息失败: {}", e)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callback_data_round_trip() {
+        let token = callback_token("3f2b8c1d-9e4a-4b7c-8d6e-1a2b3c4d5e6f");
+        assert_eq!(token, "3f2b8c1d");
+
+        for action in [
+            CallbackAction::Toggle(0),
+            CallbackAction::Toggle(127),
+            CallbackAction::Page(3),
+            CallbackAction::Condition(2),
+            CallbackAction::Noop,
+        ] {
+            let data = build_callback_data(&token, action);
+            assert!(data.len() <= 64);
+            assert_eq!(parse_callback_data(&data, &token), Some(action));
+        }
+
+        // 其他请求或旧格式的回调不应被接受
+        assert_eq!(parse_callback_data("t:deadbeef:1", &token), None);
+        assert_eq!(parse_callback_data("toggle:选项一", &token), None);
+    }
+
+    #[test]
+    fn test_long_options_are_listed_and_truncated() {
+        let long_option = "这是一个非常非常长的中文选项，按钮上肯定放不下完整的文字".to_string();
+        assert!(needs_option_listing(&[long_option.clone()]));
+        assert!(!needs_option_listing(&["确认".to_string(), "取消".to_string()]));
+
+        let label = truncate_option_label(&long_option);
+        assert_eq!(label.chars().count(), telegram_constants::MAX_BUTTON_LABEL_CHARS);
+        assert!(label.ends_with('…'));
+
+        assert_eq!(option_page_count(0), 1);
+        assert_eq!(option_page_count(telegram_constants::OPTIONS_PER_PAGE + 1), 2);
+    }
+
+    #[test]
+    fn test_prompt_composition_matches_popup() {
+        assert_eq!(insert_prompt_content("", "请继续"), "请继续");
+        assert_eq!(insert_prompt_content("好的 ", "请继续"), "好的\n\n请继续");
+        assert_eq!(insert_prompt_content("好的", "  "), "");
+
+        // 图片说明不会覆盖已输入的回复
+        assert_eq!(append_caption("", " 截图 "), "截图");
+        assert_eq!(append_caption("按这个改", "截图"), "按这个改\n截图");
+        assert_eq!(append_caption("按这个改", " "), "按这个改");
+
+        let prompt = |current_state: bool| CustomPrompt {
+            id: "test".to_string(),
+            name: "测试".to_string(),
+            content: String::new(),
+            description: None,
+            sort_order: 0,
+            created_at: String::new(),
+            updated_at: String::new(),
+            r#type: "conditional".to_string(),
+            condition_text: Some("是否生成测试".to_string()),
+            template_true: Some("✔️请生成测试 ".to_string()),
+            template_false: Some("❌请不要生成测试".to_string()),
+            current_state,
+        };
+        let prompts = [prompt(true), prompt(false)];
+        assert_eq!(
+            compose_user_input("好的", prompts.iter()),
+            "好的\n\n✔️请生成测试\n❌请不要生成测试"
+        );
+        assert_eq!(compose_user_input("好的", std::iter::empty()), "好的");
+    }
+}
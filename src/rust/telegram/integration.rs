@@ -4,8 +4,14 @@ use tauri::{AppHandle, Emitter};
 use teloxide::prelude::*;
 use tokio::sync::Mutex;
 
-use super::core::{handle_text_message, TelegramCore, TelegramEvent};
+use super::core::{
+    append_caption, callback_token, download_message_images, handle_text_message, insert_prompt_content,
+    parse_callback_data, CallbackAction, TelegramCore, TelegramEvent,
+};
+use super::bot_commands::{handle_bot_command, register_bot_commands};
+use super::offset_store::UpdateOffsetStore;
 use crate::log_important;
+use crate::mcp::types::ImageAttachment;
 
 /// Telegram集成管理器
 pub struct TelegramIntegration {
@@ -15,6 +21,8 @@ pub struct TelegramIntegration {
     selected_options: Arc<Mutex<Vec<String>>>,
     /// 用户输入文本
     user_input: Arc<Mutex<String>>,
+    /// 用户发送的图片
+    images: Arc<Mutex<Vec<ImageAttachment>>>,
     /// 操作消息ID，用于过滤后续消息
     operation_message_id: Arc<Mutex<Option<i32>>>,
     /// 停止信号发送器
@@ -36,6 +44,7 @@ impl TelegramIntegration {
             app_handle,
             selected_options: Arc::new(Mutex::new(Vec::new())),
             user_input: Arc::new(Mutex::new(String::new())),
+            images: Arc::new(Mutex::new(Vec::new())),
             operation_message_id: Arc::new(Mutex::new(None)),
             stop_sender: None,
         })
@@ -54,10 +63,15 @@ impl TelegramIntegration {
             let mut selected = self.selected_options.lock().await;
             selected.clear();
         }
+        {
+            let mut images = self.images.lock().await;
+            images.clear();
+        }
 
         // 发送选项消息
+        let request_token = callback_token(&uuid::Uuid::new_v4().to_string());
         self.core
-            .send_options_message(message, &predefined_options, is_markdown)
+            .send_options_message(message, &predefined_options, is_markdown, &request_token)
             .await?;
 
         // 发送操作消息
@@ -73,7 +87,8 @@ impl TelegramIntegration {
         }
 
         // 启动消息监听
-        self.start_message_listener().await?;
+        self.start_message_listener(request_token, predefined_options)
+            .await?;
 
         Ok(())
     }
@@ -81,20 +96,35 @@ impl TelegramIntegration {
 
 
     /// 启动消息监听
-    async fn start_message_listener(&mut self) -> Result<()> {
-        let bot = self.core.bot.clone();
-        let chat_id = self.core.chat_id;
+    async fn start_message_listener(
+        &mut self,
+        request_token: String,
+        predefined_options: Vec<String>,
+    ) -> Result<()> {
+        let mut core = self.core.clone();
+        let bot = core.bot.clone();
+        let chat_id = core.chat_id;
         let app_handle = self.app_handle.clone();
         let selected_options = self.selected_options.clone();
         let user_input = self.user_input.clone();
+        let images = self.images.clone();
         let operation_message_id = self.operation_message_id.clone();
 
         let (stop_tx, mut stop_rx) = tokio::sync::oneshot::channel();
         self.stop_sender = Some(stop_tx);
 
+        // 从上次确认的位置继续拉取，避免消费之前请求遗留的更新
+        let offset_store = UpdateOffsetStore::for_bot(bot.token())?;
+
         // 启动监听任务
         tokio::spawn(async move {
-            let mut offset = 0i32;
+            let mut offset = offset_store.next_offset().unwrap_or(0);
+            let mut current_page = 0usize;
+
+            // 注册命令菜单，等待回复期间也可以使用 /pending 等命令
+            if let Err(e) = register_bot_commands(&bot).await {
+                log_important!(warn, "{}", e);
+            }
 
             loop {
                 tokio::select! {
@@ -107,6 +137,9 @@ impl TelegramIntegration {
                             Ok(updates) => {
                                 for update in updates {
                                     offset = update.id.0 as i32 + 1;
+                                    if let Err(e) = offset_store.acknowledge(update.id.0 as i32) {
+                                        log_important!(warn, "保存Telegram更新偏移量失败: {}", e);
+                                    }
 
                                     // 处理不同类型的更新
                                     match update.kind {
@@ -118,9 +151,16 @@ impl TelegramIntegration {
                                                 }
                                             }
 
-                                            if let Some(data) = &callback_query.data {
-                                                if data.starts_with("toggle:") {
-                                                    let option = data.strip_prefix("toggle:").unwrap().to_string();
+                                            let action = callback_query
+                                                .data
+                                                .as_deref()
+                                                .and_then(|data| parse_callback_data(data, &request_token))
+                                                // 只有允许的用户可以操作
+                                                .filter(|_| core.is_allowed_user(Some(&callback_query.from)));
+
+                                            let refresh_keyboard = match action {
+                                                Some(CallbackAction::Toggle(index)) if index < predefined_options.len() => {
+                                                    let option = predefined_options[index].clone();
 
                                                     // 切换选项状态
                                                     let selected = {
@@ -143,6 +183,44 @@ impl TelegramIntegration {
                                                     if let Err(e) = app_handle.emit("telegram-event", &event) {
                                                         log_important!(warn, "Telegram事件发送失败: {}", e);
                                                     }
+                                                    true
+                                                }
+                                                Some(CallbackAction::Page(page)) => {
+                                                    current_page = page;
+                                                    true
+                                                }
+                                                Some(CallbackAction::Condition(index)) => {
+                                                    match core.toggle_conditional_prompt(index) {
+                                                        Some(prompt) => {
+                                                            // 由前端同步开关并保存配置
+                                                            let event = TelegramEvent::ConditionToggled {
+                                                                prompt_id: prompt.id.clone(),
+                                                                current_state: prompt.current_state,
+                                                            };
+                                                            if let Err(e) = app_handle.emit("telegram-event", &event) {
+                                                                log_important!(warn, "Telegram事件发送失败: {}", e);
+                                                            }
+                                                            true
+                                                        }
+                                                        None => false,
+                                                    }
+                                                }
+                                                _ => false,
+                                            };
+
+                                            // 更新按钮状态和当前页
+                                            if let (true, Some(message)) = (refresh_keyboard, &callback_query.message) {
+                                                let selected_opts = selected_options.lock().await.clone();
+                                                if let Ok(keyboard) = core.create_inline_keyboard(
+                                                    &request_token,
+                                                    &predefined_options,
+                                                    &selected_opts,
+                                                    current_page,
+                                                ) {
+                                                    let _ = bot
+                                                        .edit_message_reply_markup(chat_id, message.id())
+                                                        .reply_markup(keyboard)
+                                                        .await;
                                                 }
                                             }
 
@@ -150,19 +228,56 @@ impl TelegramIntegration {
                                             let _ = bot.answer_callback_query(callback_query.id).await;
                                         }
                                         teloxide::types::UpdateKind::Message(message) => {
+                                            match handle_bot_command(&core, &message).await {
+                                                Ok(true) => continue,
+                                                Ok(false) => {}
+                                                Err(e) => log_important!(warn, "处理Bot命令失败: {}", e),
+                                            }
+
                                             // 获取操作消息ID
                                             let op_msg_id = {
                                                 let op_id = operation_message_id.lock().await;
                                                 *op_id
                                             };
 
+                                            // 下载照片和图片文件
+                                            let received_images = download_message_images(
+                                                &core,
+                                                &message,
+                                                op_msg_id,
+                                            ).await;
+                                            for image in received_images {
+                                                images.lock().await.push(image.clone());
+
+                                                let event = TelegramEvent::ImageReceived { image };
+                                                if let Err(e) = app_handle.emit("telegram-event", &event) {
+                                                    log_important!(warn, "Telegram图片事件发送失败: {}", e);
+                                                }
+                                            }
+
                                             // 使用核心模块的处理函数
                                             match handle_text_message(
+                                                &core,
                                                 &message,
-                                                chat_id,
                                                 op_msg_id,
                                             ).await {
-                                                Ok(Some(event)) => {
+                                                Ok(Some(mut event)) => {
+                                                    // 提示词按钮插入到当前输入，转换为文本更新
+                                                    if let TelegramEvent::PromptSelected { content, .. } = &event {
+                                                        let input = user_input.lock().await.clone();
+                                                        event = TelegramEvent::TextUpdated {
+                                                            text: insert_prompt_content(&input, content),
+                                                        };
+                                                    }
+
+                                                    // 图片说明追加到当前输入，转换为文本更新
+                                                    if let TelegramEvent::CaptionReceived { text } = &event {
+                                                        let input = user_input.lock().await.clone();
+                                                        event = TelegramEvent::TextUpdated {
+                                                            text: append_caption(&input, text),
+                                                        };
+                                                    }
+
                                                     // 如果是文本更新，保存到用户输入
                                                     if let TelegramEvent::TextUpdated { text } = &event {
                                                         let mut input = user_input.lock().await;
@@ -212,6 +327,12 @@ impl TelegramIntegration {
         input.clone()
     }
 
+    /// 获取用户发送的图片
+    pub async fn get_images(&self) -> Vec<ImageAttachment> {
+        let images = self.images.lock().await;
+        images.clone()
+    }
+
     /// 停止Telegram集成
     pub async fn stop(&mut self) {
         if let Some(sender) = self.stop_sender.take() {
@@ -1,152 +1,488 @@
-use regex;
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
 
-/// 智能处理 Telegram MarkdownV2 格式
+/// 将 Markdown 转换为 Telegram MarkdownV2 格式
 ///
-/// 支持尽可能多的 Markdown 格式，同时确保消息能正确发送
+/// 基于 CommonMark 解析：标题降级为粗体，表格转为等宽代码块，其余文本按 MarkdownV2 规则转义
 pub fn process_telegram_markdown(text: &str) -> String {
-    let mut result = text.to_string();
+    TelegramRenderer::new(Dialect::MarkdownV2).render(text)
+}
 
-    // 第一步：保护代码块和行内代码
-    let mut protected_segments = Vec::new();
-    let mut segment_index = 0;
+/// 将 Markdown 转换为 Telegram HTML 格式
+///
+/// MarkdownV2 被拒绝时的降级方案：HTML 只需转义 `&`、`<`、`>`，对任意文本都更稳妥
+pub fn process_telegram_html(text: &str) -> String {
+    TelegramRenderer::new(Dialect::Html).render(text)
+}
 
-    // 保护代码块 ```language\ncode\n```
-    result = protect_code_blocks(result, &mut protected_segments, &mut segment_index);
+/// Telegram 支持的两种富文本格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    MarkdownV2,
+    Html,
+}
 
-    // 保护行内代码 `code`
-    result = protect_inline_code(result, &mut protected_segments, &mut segment_index);
+/// 行内格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Bold,
+    Italic,
+    Strikethrough,
+}
 
-    // 第二步：处理 Markdown 格式
-    // 转换标准 Markdown 到 Telegram MarkdownV2
-    result = convert_markdown_to_telegram(result);
+/// 正在收集的表格
+#[derive(Default)]
+struct TableState {
+    rows: Vec<Vec<String>>,
+    header_rows: usize,
+}
 
-    // 第三步：转义剩余的特殊字符
-    result = escape_remaining_special_chars(result);
+/// 事件驱动的 Telegram 渲染器
+struct TelegramRenderer {
+    dialect: Dialect,
+    /// 输出缓冲区栈，引用块内容先写入独立缓冲区，结束时再整体加上引用格式
+    buffers: Vec<String>,
+    /// 已打开的行内格式，同类格式嵌套时不重复输出标记（Telegram 不允许同类实体嵌套）
+    styles: Vec<Style>,
+    /// 列表栈，`Some(n)` 为有序列表的下一个序号
+    lists: Vec<Option<u64>>,
+    /// 刚输出列表项标记，尚未输出内容
+    at_item_start: bool,
+    /// 链接栈，`None` 表示该链接无法作为实体输出，结束时以纯文本附加地址
+    links: Vec<Option<String>>,
+    /// 正在收集的代码块（语言, 内容）
+    code_block: Option<(String, String)>,
+    table: Option<TableState>,
+}
 
-    // 第四步：恢复保护的代码
-    for (i, segment) in protected_segments.into_iter().enumerate() {
-        let placeholder = format!("CODEBLOCK{}", i);
-        result = result.replace(&placeholder, &segment);
+impl TelegramRenderer {
+    fn new(dialect: Dialect) -> Self {
+        Self {
+            dialect,
+            buffers: vec![String::new()],
+            styles: Vec::new(),
+            lists: Vec::new(),
+            at_item_start: false,
+            links: Vec::new(),
+            code_block: None,
+            table: None,
+        }
     }
 
-    result
-}
+    fn render(mut self, text: &str) -> String {
+        let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
 
-/// 保护代码块
-fn protect_code_blocks(mut text: String, protected: &mut Vec<String>, index: &mut usize) -> String {
-    while let Some(start) = text.find("```") {
-        if let Some(end_start) = text[start + 3..].find("```") {
-            let end_pos = start + 3 + end_start + 3;
-            let code_block = text[start..end_pos].to_string();
-            let placeholder = format!("CODEBLOCK{}", *index);
-            protected.push(code_block);
-            text.replace_range(start..end_pos, &placeholder);
-            *index += 1;
-        } else {
-            break;
+        for event in Parser::new_ext(text, options) {
+            self.handle_event(event);
         }
+
+        let mut result = self.buffers.concat();
+        result.truncate(result.trim_end().len());
+        result
     }
-    text
-}
 
-/// 保护行内代码
-fn protect_inline_code(mut text: String, protected: &mut Vec<String>, index: &mut usize) -> String {
-    let mut start = 0;
-    while let Some(pos) = text[start..].find('`') {
-        let abs_pos = start + pos;
-        if let Some(end_pos) = text[abs_pos + 1..].find('`') {
-            let abs_end = abs_pos + 1 + end_pos;
-            let inline_code = text[abs_pos..=abs_end].to_string();
-            let placeholder = format!("CODEBLOCK{}", *index);
-            protected.push(inline_code);
-            text.replace_range(abs_pos..=abs_end, &placeholder);
-            *index += 1;
-            start = abs_pos + placeholder.len();
-        } else {
-            break;
+    fn handle_event(&mut self, event: Event) {
+        // 表格内只收集纯文本
+        if self.table.is_some() && self.handle_table_event(&event) {
+            return;
+        }
+
+        match event {
+            Event::Start(tag) => self.start_tag(tag),
+            Event::End(tag) => self.end_tag(tag),
+            Event::Text(text) => {
+                if let Some((_, code)) = &mut self.code_block {
+                    code.push_str(&text);
+                } else {
+                    let escaped = self.escape_text(&text);
+                    self.push(&escaped);
+                }
+            }
+            Event::Code(code) => {
+                let rendered = match self.dialect {
+                    Dialect::MarkdownV2 => format!("`{}`", escape_code(&code)),
+                    Dialect::Html => format!("<code>{}</code>", escape_html(&code)),
+                };
+                self.push(&rendered);
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                // 原始 HTML 按普通文本显示
+                let escaped = self.escape_text(&html);
+                self.push(&escaped);
+            }
+            Event::InlineMath(math) | Event::DisplayMath(math) => {
+                let escaped = self.escape_text(&math);
+                self.push(&escaped);
+            }
+            Event::FootnoteReference(name) => {
+                let escaped = self.escape_text(&format!("[{}]", name));
+                self.push(&escaped);
+            }
+            Event::SoftBreak | Event::HardBreak => self.push_newline(),
+            Event::Rule => {
+                self.ensure_blank_line();
+                self.push("──────────");
+            }
+            Event::TaskListMarker(checked) => self.push(if checked { "✅ " } else { "⬜ " }),
+        }
+    }
+
+    fn start_tag(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => self.ensure_blank_line(),
+            Tag::Heading { .. } => {
+                self.ensure_blank_line();
+                self.open_style(Style::Bold);
+            }
+            Tag::BlockQuote(_) => {
+                self.ensure_blank_line();
+                self.buffers.push(String::new());
+            }
+            Tag::CodeBlock(kind) => {
+                self.ensure_blank_line();
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or_default().to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code_block = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                if self.lists.is_empty() {
+                    self.ensure_blank_line();
+                } else {
+                    self.ensure_newline();
+                }
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.ensure_newline();
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        let marker = format!("{}. ", number);
+                        *number += 1;
+                        marker
+                    }
+                    _ => "• ".to_string(),
+                };
+                let marker = format!("{}{}", "  ".repeat(depth), self.escape_text(&marker));
+                self.push(&marker);
+                self.at_item_start = true;
+            }
+            Tag::Table(_) => {
+                self.ensure_blank_line();
+                self.table = Some(TableState::default());
+            }
+            Tag::Emphasis => self.open_style(Style::Italic),
+            Tag::Strong => self.open_style(Style::Bold),
+            Tag::Strikethrough => self.open_style(Style::Strikethrough),
+            Tag::Link { dest_url, .. } => self.open_link(&dest_url, ""),
+            Tag::Image { dest_url, .. } => self.open_link(&dest_url, "🖼 "),
+            _ => {}
         }
     }
-    text
-}
 
-/// 转换标准 Markdown 到 Telegram MarkdownV2
-fn convert_markdown_to_telegram(text: String) -> String {
-    let mut result = text;
+    fn end_tag(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(level) => {
+                self.close_style(Style::Bold);
+                // 一、二级标题后额外空行，突出层级
+                if matches!(level, HeadingLevel::H1 | HeadingLevel::H2) {
+                    self.ensure_blank_line();
+                }
+            }
+            TagEnd::BlockQuote(_) => {
+                let content = self.buffers.pop().unwrap_or_default();
+                let content = content.trim_end();
+                let quoted = match self.dialect {
+                    Dialect::MarkdownV2 => content
+                        .lines()
+                        .map(|line| format!(">{}", line))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    Dialect::Html => format!("<blockquote>{}</blockquote>", content),
+                };
+                self.push(&quoted);
+            }
+            TagEnd::CodeBlock => {
+                if let Some((language, code)) = self.code_block.take() {
+                    let code = code.trim_end_matches('\n');
+                    let rendered = match self.dialect {
+                        Dialect::MarkdownV2 => {
+                            format!("```{}\n{}\n```", language, escape_code(code))
+                        }
+                        Dialect::Html if language.is_empty() => {
+                            format!("<pre><code>{}</code></pre>", escape_html(code))
+                        }
+                        Dialect::Html => format!(
+                            "<pre><code class=\"language-{}\">{}</code></pre>",
+                            escape_html(&language),
+                            escape_html(code)
+                        ),
+                    };
+                    self.push(&rendered);
+                }
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+            }
+            TagEnd::Emphasis => self.close_style(Style::Italic),
+            TagEnd::Strong => self.close_style(Style::Bold),
+            TagEnd::Strikethrough => self.close_style(Style::Strikethrough),
+            TagEnd::Link | TagEnd::Image => self.close_link(),
+            _ => {}
+        }
+    }
 
-    // 处理标题 - 转换为引用格式，更明显
-    // # Title -> >Title
-    // ## Title -> >Title
-    if let Ok(header_regex) = regex::Regex::new(r"^(#{1,6})\s+(.+)$") {
-        let lines: Vec<&str> = result.split('\n').collect();
-        let mut new_lines = Vec::new();
+    /// 处理表格内的事件，返回 `true` 表示事件已被消费
+    fn handle_table_event(&mut self, event: &Event) -> bool {
+        let Some(table) = &mut self.table else {
+            return false;
+        };
 
-        for line in lines {
-            if let Some(captures) = header_regex.captures(line) {
-                let title = captures.get(2).unwrap().as_str();
-                new_lines.push(format!(">{}", title));
-            } else {
-                new_lines.push(line.to_string());
+        match event {
+            Event::Start(Tag::TableRow) | Event::Start(Tag::TableHead) => table.rows.push(Vec::new()),
+            Event::End(TagEnd::TableHead) => table.header_rows = table.rows.len(),
+            Event::Start(Tag::TableCell) => {
+                if let Some(row) = table.rows.last_mut() {
+                    row.push(String::new());
+                }
+            }
+            Event::Text(text) | Event::Code(text) | Event::InlineHtml(text) => {
+                if let Some(cell) = table.rows.last_mut().and_then(|row| row.last_mut()) {
+                    cell.push_str(text);
+                }
             }
+            Event::End(TagEnd::Table) => {
+                let table = self.table.take().unwrap_or_default();
+                let rendered = render_table(&table);
+                let rendered = match self.dialect {
+                    Dialect::MarkdownV2 => format!("```\n{}\n```", escape_code(&rendered)),
+                    Dialect::Html => format!("<pre>{}</pre>", escape_html(&rendered)),
+                };
+                self.push(&rendered);
+            }
+            _ => {}
         }
-        result = new_lines.join("\n");
+
+        true
     }
 
-    // 处理粗体 **text** -> *text*
-    if let Ok(bold_regex) = regex::Regex::new(r"\*\*([^*]+)\*\*") {
-        while let Some(captures) = bold_regex.captures(&result) {
-            let full_match = captures.get(0).unwrap().as_str();
-            let content = captures.get(1).unwrap().as_str();
-            result = result.replace(full_match, &format!("*{}*", content));
+    fn open_style(&mut self, style: Style) {
+        if !self.styles.contains(&style) {
+            let marker = self.style_marker(style, true);
+            self.push(marker);
         }
+        self.styles.push(style);
     }
 
-    // 处理引用 > text -> >text (保持不变，但确保格式正确)
-    let lines: Vec<&str> = result.split('\n').collect();
-    let mut new_lines = Vec::new();
+    fn close_style(&mut self, style: Style) {
+        if let Some(pos) = self.styles.iter().rposition(|s| *s == style) {
+            self.styles.remove(pos);
+        }
+        if !self.styles.contains(&style) {
+            let marker = self.style_marker(style, false);
+            self.push(marker);
+        }
+    }
 
-    for line in lines {
-        if line.trim_start().starts_with('>') {
-            // 保持引用格式
-            new_lines.push(line.to_string());
+    fn style_marker(&self, style: Style, open: bool) -> &'static str {
+        match (self.dialect, style, open) {
+            (Dialect::MarkdownV2, Style::Bold, _) => "*",
+            (Dialect::MarkdownV2, Style::Italic, _) => "_",
+            (Dialect::MarkdownV2, Style::Strikethrough, _) => "~",
+            (Dialect::Html, Style::Bold, true) => "<b>",
+            (Dialect::Html, Style::Bold, false) => "</b>",
+            (Dialect::Html, Style::Italic, true) => "<i>",
+            (Dialect::Html, Style::Italic, false) => "</i>",
+            (Dialect::Html, Style::Strikethrough, true) => "<s>",
+            (Dialect::Html, Style::Strikethrough, false) => "</s>",
+        }
+    }
+
+    fn open_link(&mut self, url: &str, prefix: &str) {
+        // Telegram 只接受绝对地址，相对地址和锚点只保留链接文字
+        let is_absolute = url.contains("://") || url.starts_with("mailto:") || url.starts_with("tg:");
+        let is_nested = self.links.iter().any(Option::is_some);
+
+        if is_absolute && !is_nested {
+            let opening = match self.dialect {
+                Dialect::MarkdownV2 => "[".to_string(),
+                Dialect::Html => format!("<a href=\"{}\">", escape_html(url).replace('"', "&quot;")),
+            };
+            self.push(&opening);
+            self.links.push(Some(url.to_string()));
         } else {
-            new_lines.push(line.to_string());
+            self.links.push(None);
+        }
+
+        if !prefix.is_empty() {
+            self.push(prefix);
         }
     }
-    result = new_lines.join("\n");
 
-    result
+    fn close_link(&mut self) {
+        if let Some(Some(url)) = self.links.pop() {
+            let closing = match self.dialect {
+                Dialect::MarkdownV2 => format!("]({})", escape_link_url(&url)),
+                Dialect::Html => "</a>".to_string(),
+            };
+            self.push(&closing);
+        }
+    }
+
+    fn escape_text(&self, text: &str) -> String {
+        match self.dialect {
+            Dialect::MarkdownV2 => escape_markdown_v2(text),
+            Dialect::Html => escape_html(text),
+        }
+    }
+
+    fn current(&mut self) -> &mut String {
+        self.buffers.last_mut().expect("渲染缓冲区不能为空")
+    }
+
+    fn push(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.at_item_start = false;
+        self.current().push_str(text);
+    }
+
+    /// 换行，列表内的续行保持缩进
+    fn push_newline(&mut self) {
+        let indent = "  ".repeat(self.lists.len());
+        let buffer = self.current();
+        buffer.push('\n');
+        buffer.push_str(&indent);
+    }
+
+    /// 确保从新行开始
+    fn ensure_newline(&mut self) {
+        let buffer = self.current();
+        if !buffer.is_empty() && !buffer.ends_with('\n') {
+            buffer.push('\n');
+        }
+    }
+
+    /// 确保与上一个块之间有空行（列表项内只换行）
+    fn ensure_blank_line(&mut self) {
+        if self.at_item_start {
+            return;
+        }
+        if !self.lists.is_empty() {
+            if !self.current().is_empty() && !self.current().ends_with('\n') {
+                self.push_newline();
+            }
+            return;
+        }
+
+        let buffer = self.current();
+        if buffer.is_empty() {
+            return;
+        }
+        while !buffer.ends_with("\n\n") {
+            buffer.push('\n');
+        }
+    }
 }
 
-/// 转义剩余的特殊字符
-fn escape_remaining_special_chars(text: String) -> String {
-    let mut result = text;
+/// 表格渲染为对齐的纯文本
+fn render_table(table: &TableState) -> String {
+    let column_count = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut widths = vec![0; column_count];
+    for row in &table.rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(display_width(cell.trim()));
+        }
+    }
 
-    // 需要转义的字符，但排除我们想保留的格式字符
-    let chars_to_escape = [
-        // 不转义 * 因为我们用它做粗体
-        // 不转义 > 因为我们用它做引用
-        // 不转义 ` 因为已经保护了代码
-        '_', '[', ']', '(', ')', '~', '#', '+', '-', '=', '|', '{', '}', '.', '!',
-    ];
+    let format_row = |row: &Vec<String>| {
+        let cells: Vec<String> = (0..column_count)
+            .map(|i| {
+                let cell = row.get(i).map(|c| c.trim()).unwrap_or_default();
+                format!("{}{}", cell, " ".repeat(widths[i] - display_width(cell)))
+            })
+            .collect();
+        cells.join(" | ").trim_end().to_string()
+    };
 
-    for &ch in &chars_to_escape {
-        result = result.replace(ch, &format!("\\{}", ch));
+    let mut lines = Vec::new();
+    for (i, row) in table.rows.iter().enumerate() {
+        lines.push(format_row(row));
+        if i + 1 == table.header_rows {
+            let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+            lines.push(separator.join("-+-"));
+        }
     }
 
+    lines.join("\n")
+}
+
+/// 等宽字体下的显示宽度（中日韩字符和表情占两列）
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c {
+            '\u{1100}'..='\u{115F}'
+            | '\u{2E80}'..='\u{A4CF}'
+            | '\u{AC00}'..='\u{D7A3}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FE30}'..='\u{FE4F}'
+            | '\u{FF00}'..='\u{FF60}'
+            | '\u{FFE0}'..='\u{FFE6}'
+            | '\u{1F300}'..='\u{1FAFF}' => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// 转义 MarkdownV2 普通文本中的全部特殊字符
+fn escape_markdown_v2(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '_' | '*' | '[' | ']' | '(' | ')' | '~' | '`' | '>' | '#' | '+' | '-' | '=' | '|'
+                | '{' | '}' | '.' | '!' | '\\'
+        ) {
+            result.push('\\');
+        }
+        result.push(c);
+    }
     result
 }
 
+/// 转义 MarkdownV2 代码中的字符（只需转义 ` 和 \）
+fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+/// 转义 MarkdownV2 链接地址中的字符（只需转义 ) 和 \）
+fn escape_link_url(url: &str) -> String {
+    url.replace('\\', "\\\\").replace(')', "\\)")
+}
+
+/// 转义 HTML 特殊字符
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::Path;
 
     #[test]
     fn test_markdown_processing() {
         let input = "# 标题\n\n**粗体文本**\n\n`代码`\n\n```rust\nfn main() {}\n```";
         let result = process_telegram_markdown(input);
-        
-        // 验证标题转换为引用
-        assert!(result.contains(">标题"));
+
+        // 验证标题降级为粗体
+        assert!(result.contains("*标题*"));
         // 验证粗体转换
         assert!(result.contains("*粗体文本*"));
         // 验证代码块保持不变
@@ -157,7 +493,58 @@ mod tests {
     fn test_special_char_escaping() {
         let input = "测试_下划线和[方括号]";
         let result = process_telegram_markdown(input);
-        
+
         assert!(result.contains("测试\\_下划线和\\[方括号\\]"));
     }
+
+    #[test]
+    fn test_html_fallback() {
+        let input = "# 标题\n\n**粗体** <tag> & `a<b`\n\n```rust\nlet x = 1 < 2;\n```";
+        let result = process_telegram_html(input);
+
+        assert!(result.contains("<b>标题</b>"));
+        assert!(result.contains("<b>粗体</b> &lt;tag&gt; &amp; <code>a&lt;b</code>"));
+        assert!(result.contains("<pre><code class=\"language-rust\">let x = 1 &lt; 2;</code></pre>"));
+    }
+
+    /// 黄金测试：`testdata/markdown/*.md` 为真实的 AI 消息，
+    /// 同名 `.mdv2` 和 `.html` 为期望输出；设置 `UPDATE_GOLDEN=1` 重新生成
+    #[test]
+    fn test_golden_messages() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/rust/telegram/testdata/markdown");
+        let update = std::env::var("UPDATE_GOLDEN").is_ok();
+
+        let mut inputs: Vec<_> = std::fs::read_dir(&dir)
+            .expect("读取黄金测试目录失败")
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+            .collect();
+        inputs.sort();
+        assert!(!inputs.is_empty(), "黄金测试目录为空");
+
+        for input_path in inputs {
+            let input = std::fs::read_to_string(&input_path).unwrap();
+            let outputs = [
+                ("mdv2", process_telegram_markdown(&input)),
+                ("html", process_telegram_html(&input)),
+            ];
+
+            for (extension, actual) in outputs {
+                let expected_path = input_path.with_extension(extension);
+                if update {
+                    std::fs::write(&expected_path, format!("{}\n", actual)).unwrap();
+                    continue;
+                }
+
+                let expected = std::fs::read_to_string(&expected_path)
+                    .unwrap_or_else(|_| panic!("缺少期望输出: {:?}", expected_path));
+                assert_eq!(
+                    actual,
+                    expected.trim_end_matches('\n'),
+                    "输出与 {:?} 不一致",
+                    expected_path
+                );
+            }
+        }
+    }
 }
@@ -2,9 +2,19 @@ use anyhow::Result;
 use std::collections::HashSet;
 use teloxide::prelude::*;
 
-use crate::config::load_standalone_config;
-use crate::mcp::types::{build_continue_response, build_send_response, PopupRequest};
-use crate::telegram::{handle_callback_query, handle_text_message, TelegramCore, TelegramEvent};
+use crate::config::{load_project_config, update_standalone_config};
+use crate::mcp::types::{
+    build_continue_response_with_responder, build_mcp_response_with_responder, ImageAttachment,
+    PopupRequest,
+};
+use crate::telegram::core::{
+    append_caption, callback_token, compose_user_input, download_message_images, insert_prompt_content,
+    CallbackAction,
+};
+use crate::telegram::{
+    handle_bot_command, handle_callback_query, handle_text_message, register_bot_commands,
+    TelegramCore, TelegramEvent, UpdateOffsetStore,
+};
 use crate::log_important;
 
 /// 处理纯Telegram模式的MCP请求（不启动GUI）
@@ -13,8 +23,8 @@ pub async fn handle_telegram_only_mcp_request(request_file: &str) -> Result<()>
     let request_json = std::fs::read_to_string(request_file)?;
     let request: PopupRequest = serde_json::from_str(&request_json)?;
 
-    // 加载完整配置
-    let app_config = load_standalone_config()?;
+    // 加载完整配置（合并请求所属项目的配置）
+    let app_config = load_project_config(request.project_path.as_deref())?;
     let telegram_config = &app_config.telegram_config;
 
     if !telegram_config.enabled {
@@ -27,52 +37,72 @@ pub async fn handle_telegram_only_mcp_request(request_file: &str) -> Result<()>
         return Ok(());
     }
 
-    // 创建Telegram核心实例，使用配置中的API URL
-    let api_url = if telegram_config.api_base_url == crate::constants::telegram::API_BASE_URL {
-        None
-    } else {
-        Some(telegram_config.api_base_url.clone())
-    };
-
-    let core = TelegramCore::new_with_api_url(
-        telegram_config.bot_token.clone(),
-        telegram_config.chat_id.clone(),
-        api_url,
-    )?;
+    // 创建Telegram核心实例（解析群组、话题和允许回复的用户）
+    let core = TelegramCore::from_config(telegram_config)
+        .await?
+        .with_custom_prompts(&app_config.custom_prompt_config);
 
     // 发送消息到Telegram
     let predefined_options = request.predefined_options.clone().unwrap_or_default();
 
     // 发送选项消息
-    core.send_options_message(&request.message, &predefined_options, request.is_markdown)
+    let request_token = callback_token(&request.id);
+    let options_message_id = core
+        .send_options_message(
+            &request.message,
+            &predefined_options,
+            request.is_markdown,
+            &request_token,
+        )
         .await?;
 
     // 短暂延迟确保消息顺序
     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
 
     // 发送操作消息（假设启用继续回复）
-    core.send_operation_message(true).await?;
+    let operation_message_id = core.send_operation_message(true).await?;
 
     // 启动消息监听循环
-    start_telegram_mcp_listener(core, request, predefined_options).await
+    let state = ListenerState {
+        request_token,
+        predefined_options,
+        selected_options: HashSet::new(),
+        current_page: 0,
+        user_input: String::new(),
+        images: Vec::new(),
+        // 返回0表示未能获取消息ID，此时从callback中识别
+        options_message_id: Some(options_message_id).filter(|id| *id > 0),
+        operation_message_id: Some(operation_message_id).filter(|id| *id > 0),
+    };
+    start_telegram_mcp_listener(core, request, state).await
+}
+
+/// 单个请求的监听状态
+struct ListenerState {
+    request_token: String,
+    predefined_options: Vec<String>,
+    selected_options: HashSet<String>,
+    current_page: usize,
+    user_input: String,
+    images: Vec<ImageAttachment>,
+    options_message_id: Option<i32>,
+    /// 操作消息ID，早于它的消息属于之前的请求，直接丢弃
+    operation_message_id: Option<i32>,
 }
 
 /// 启动Telegram MCP消息监听循环
 async fn start_telegram_mcp_listener(
-    core: TelegramCore,
+    mut core: TelegramCore,
     request: PopupRequest,
-    predefined_options: Vec<String>,
+    mut state: ListenerState,
 ) -> Result<()> {
-    let mut offset = 0i32;
-    let mut selected_options: HashSet<String> = HashSet::new();
-    let mut user_input = String::new();
-    let mut options_message_id: Option<i32> = None;
-
-    // 获取当前最新的消息ID作为基准
-    if let Ok(updates) = core.bot.get_updates().limit(10).await {
-        if let Some(update) = updates.last() {
-            offset = update.id.0 as i32 + 1;
-        }
+    // 从上次确认的位置继续拉取，避免消费其他进程遗留的更新
+    let offset_store = UpdateOffsetStore::for_bot(core.bot.token())?;
+    let mut offset = offset_store.next_offset().unwrap_or(0);
+
+    // 注册命令菜单，等待回复期间也可以使用 /pending 等命令
+    if let Err(e) = register_bot_commands(&core.bot).await {
+        log_important!(warn, "{}", e);
     }
 
     // 监听循环（简化版本，只等待发送或继续操作）
@@ -81,30 +111,29 @@ async fn start_telegram_mcp_listener(
             Ok(updates) => {
                 for update in updates {
                     offset = update.id.0 as i32 + 1;
+                    if let Err(e) = offset_store.acknowledge(update.id.0 as i32) {
+                        log_important!(warn, "保存Telegram更新偏移量失败: {}", e);
+                    }
 
                     match update.kind {
                         teloxide::types::UpdateKind::CallbackQuery(callback_query) => {
-                            if let Err(e) = handle_callback_query_update(
-                                &core,
-                                &callback_query,
-                                &predefined_options,
-                                &mut selected_options,
-                                &mut options_message_id,
-                            ).await {
+                            if let Err(e) =
+                                handle_callback_query_update(&mut core, &callback_query, &mut state)
+                                    .await
+                            {
                                 log_important!(warn, "处理callback query失败: {}", e);
                             }
                         }
                         teloxide::types::UpdateKind::Message(message) => {
-                            // 处理选项消息ID识别
-                            if let Err(e) = handle_message_update(
-                                &core,
-                                &message,
-                                &predefined_options,
-                                &mut options_message_id,
-                                &mut user_input,
-                                &selected_options,
-                                &request,
-                            ).await {
+                            match handle_bot_command(&core, &message).await {
+                                Ok(true) => continue,
+                                Ok(false) => {}
+                                Err(e) => log_important!(warn, "处理Bot命令失败: {}", e),
+                            }
+
+                            if let Err(e) =
+                                handle_message_update(&core, &message, &mut state, &request).await
+                            {
                                 if let Some(_result) = e.downcast_ref::<ProcessingComplete>() {
                                     return Ok(());
                                 }
@@ -127,39 +156,65 @@ async fn start_telegram_mcp_listener(
 
 /// 处理callback query更新
 async fn handle_callback_query_update(
-    core: &TelegramCore,
+    core: &mut TelegramCore,
     callback_query: &teloxide::types::CallbackQuery,
-    predefined_options: &[String],
-    selected_options: &mut HashSet<String>,
-    options_message_id: &mut Option<i32>,
+    state: &mut ListenerState,
 ) -> Result<()> {
-    // 只有当有预定义选项时才处理 callback queries
-    if predefined_options.is_empty() {
+    // 只有当有inline keyboard时才处理 callback queries
+    if !core.has_inline_keyboard(&state.predefined_options) {
         return Ok(());
     }
 
-    // 从callback_query中提取消息ID
+    let Ok(Some(action)) =
+        handle_callback_query(core, callback_query, &state.request_token).await
+    else {
+        return Ok(());
+    };
+
+    // 从callback_query中提取消息ID（只认当前请求的按钮）
     if let Some(message) = &callback_query.message {
-        if options_message_id.is_none() {
-            *options_message_id = Some(message.id().0);
+        if state.options_message_id.is_none() {
+            state.options_message_id = Some(message.id().0);
         }
     }
 
-    if let Ok(Some(option)) = handle_callback_query(&core.bot, callback_query, core.chat_id).await {
-        // 切换选项状态
-        if selected_options.contains(&option) {
-            selected_options.remove(&option);
-        } else {
-            selected_options.insert(option.clone());
-        }
+    match action {
+        CallbackAction::Toggle(index) => {
+            let Some(option) = state.predefined_options.get(index) else {
+                return Ok(());
+            };
 
-        // 更新按钮状态
-        if let Some(msg_id) = *options_message_id {
-            let selected_vec: Vec<String> = selected_options.iter().cloned().collect();
-            let _ = core
-                .update_inline_keyboard(msg_id, predefined_options, &selected_vec)
-                .await;
+            // 切换选项状态
+            if !state.selected_options.remove(option) {
+                state.selected_options.insert(option.clone());
+            }
+        }
+        CallbackAction::Page(page) => state.current_page = page,
+        CallbackAction::Condition(index) => {
+            let Some(prompt) = core.toggle_conditional_prompt(index) else {
+                return Ok(());
+            };
+
+            // 与界面开关一样保存到配置
+            if let Err(e) = save_conditional_prompt_state(&prompt.id, prompt.current_state) {
+                log_important!(warn, "保存条件性prompt状态失败: {}", e);
+            }
         }
+        CallbackAction::Noop => return Ok(()),
+    }
+
+    // 更新按钮状态
+    if let Some(msg_id) = state.options_message_id {
+        let selected_vec: Vec<String> = state.selected_options.iter().cloned().collect();
+        let _ = core
+            .update_inline_keyboard(
+                msg_id,
+                &state.request_token,
+                &state.predefined_options,
+                &selected_vec,
+                state.current_page,
+            )
+            .await;
     }
 
     Ok(())
@@ -169,28 +224,44 @@ async fn handle_callback_query_update(
 async fn handle_message_update(
     core: &TelegramCore,
     message: &teloxide::types::Message,
-    predefined_options: &[String],
-    options_message_id: &mut Option<i32>,
-    user_input: &mut String,
-    selected_options: &HashSet<String>,
+    state: &mut ListenerState,
     request: &PopupRequest,
 ) -> Result<()> {
-    // 识别选项消息ID
-    identify_options_message_id(message, predefined_options, options_message_id);
+    // 收集照片和图片文件（说明文字由文本事件处理）
+    state
+        .images
+        .extend(download_message_images(core, message, state.operation_message_id).await);
 
     // 处理文本消息事件
-    if let Ok(Some(event)) = handle_text_message(message, core.chat_id, None).await {
+    if let Ok(Some(event)) = handle_text_message(core, message, state.operation_message_id).await {
         match event {
-            TelegramEvent::SendPressed => {
-                handle_send_pressed(core, selected_options, user_input, request).await?;
+            TelegramEvent::SendPressed { responder } => {
+                // 与界面一致：在用户输入后追加条件性prompt内容
+                let user_input = compose_user_input(&state.user_input, core.conditional_prompts());
+                handle_send_pressed(
+                    core,
+                    &state.selected_options,
+                    &user_input,
+                    &state.images,
+                    request,
+                    responder,
+                )
+                .await?;
                 return Err(ProcessingComplete.into());
             }
-            TelegramEvent::ContinuePressed => {
-                handle_continue_pressed(core, request).await?;
+            TelegramEvent::ContinuePressed { responder } => {
+                handle_continue_pressed(core, request, responder).await?;
                 return Err(ProcessingComplete.into());
             }
             TelegramEvent::TextUpdated { text } => {
-                *user_input = text;
+                state.user_input = text;
+            }
+            TelegramEvent::CaptionReceived { text } => {
+                state.user_input = append_caption(&state.user_input, &text);
+            }
+            TelegramEvent::PromptSelected { name, content } => {
+                state.user_input = insert_prompt_content(&state.user_input, &content);
+                let _ = core.send_message(&format!("📝 已插入提示词「{}」", name)).await;
             }
             _ => {}
         }
@@ -199,39 +270,19 @@ async fn handle_message_update(
     Ok(())
 }
 
-/// 识别选项消息ID
-fn identify_options_message_id(
-    message: &teloxide::types::Message,
-    predefined_options: &[String],
-    options_message_id: &mut Option<i32>,
-) {
-    // 只有当有预定义选项时才检查 inline keyboard
-    if predefined_options.is_empty() {
-        return;
-    }
-
-    // 检查是否是包含 inline keyboard 的选项消息
-    if let Some(inline_keyboard) = message.reply_markup() {
-        // 检查是否包含我们的选项按钮
-        let mut contains_our_options = false;
-        for row in &inline_keyboard.inline_keyboard {
-            for button in row {
-                if let teloxide::types::InlineKeyboardButtonKind::CallbackData(callback_data) = &button.kind {
-                    if callback_data.starts_with("toggle:") {
-                        contains_our_options = true;
-                        break;
-                    }
-                }
-            }
-            if contains_our_options {
-                break;
-            }
-        }
-
-        if contains_our_options {
-            *options_message_id = Some(message.id.0);
+/// 保存条件性prompt的开关状态到配置文件
+fn save_conditional_prompt_state(prompt_id: &str, current_state: bool) -> Result<()> {
+    update_standalone_config(|config| {
+        if let Some(prompt) = config
+            .custom_prompt_config
+            .prompts
+            .iter_mut()
+            .find(|p| p.id == prompt_id)
+        {
+            prompt.current_state = current_state;
+            prompt.updated_at = chrono::Utc::now().to_rfc3339();
         }
-    }
+    })
 }
 
 /// 处理发送按钮按下
@@ -239,7 +290,9 @@ async fn handle_send_pressed(
     core: &TelegramCore,
     selected_options: &HashSet<String>,
     user_input: &str,
+    images: &[ImageAttachment],
     request: &PopupRequest,
+    responder: Option<String>,
 ) -> Result<()> {
     // 使用统一的响应构建函数
     let selected_list: Vec<String> = selected_options.iter().cloned().collect();
@@ -250,12 +303,13 @@ async fn handle_send_pressed(
         Some(user_input.to_string())
     };
 
-    let response = build_send_response(
+    let response = build_mcp_response_with_responder(
         user_input_option,
         selected_list.clone(),
-        vec![], // 无GUI模式下没有图片
+        images.to_vec(),
         Some(request.id.clone()),
         "telegram",
+        responder,
     );
 
     // 输出JSON响应到stdout（MCP协议要求）
@@ -265,6 +319,7 @@ async fn handle_send_pressed(
     let feedback_message = crate::telegram::core::build_feedback_message(
         &selected_list,
         user_input,
+        images.len(),
         false, // 不是继续操作
     );
     let _ = core.send_message(&feedback_message).await;
@@ -276,11 +331,13 @@ async fn handle_send_pressed(
 async fn handle_continue_pressed(
     core: &TelegramCore,
     request: &PopupRequest,
+    responder: Option<String>,
 ) -> Result<()> {
     // 使用统一的继续响应构建函数
-    let response = build_continue_response(
+    let response = build_continue_response_with_responder(
         Some(request.id.clone()),
         "telegram_continue",
+        responder,
     );
 
     // 输出JSON响应到stdout（MCP协议要求）
@@ -290,6 +347,7 @@ async fn handle_continue_pressed(
     let feedback_message = crate::telegram::core::build_feedback_message(
         &[],  // 继续操作没有选项
         "",   // 继续操作没有用户输入
+        0,    // 继续操作没有图片
         true, // 是继续操作
     );
     let _ = core.send_message(&feedback_message).await;
@@ -0,0 +1,248 @@
+use crate::constants::telegram as telegram_constants;
+
+/// 拆分后的消息片段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageChunk {
+    /// 文本片段（保留原始Markdown，由发送方决定渲染方式）
+    Text(String),
+    /// 过大的代码块，作为 .md 文件发送
+    Document { file_name: String, content: String },
+}
+
+/// 消息中的基本单元：段落或完整的代码块
+enum Block {
+    Paragraph(String),
+    CodeFence(String),
+}
+
+/// 按段落和代码块边界拆分长消息
+///
+/// `measure` 返回片段渲染后的长度（如 MarkdownV2 转义后），每个文本片段都不超过 `max_len`；
+/// 超过 [`telegram_constants::LARGE_CODE_BLOCK_CHARS`] 或渲染后超过 `max_len` 的代码块单独作为文件发送
+pub fn split_message(text: &str, max_len: usize, measure: impl Fn(&str) -> usize) -> Vec<MessageChunk> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut document_count = 0;
+
+    for block in parse_blocks(text) {
+        let block = match block {
+            // 渲染后超过单条消息长度的代码块也作为文件发送，按行拆分会留下未闭合的围栏
+            Block::CodeFence(code)
+                if code.chars().count() > telegram_constants::LARGE_CODE_BLOCK_CHARS
+                    || measure(&code) > max_len =>
+            {
+                flush_text(&mut chunks, &mut current);
+                document_count += 1;
+                chunks.push(MessageChunk::Document {
+                    file_name: format!("code_{}.md", document_count),
+                    content: code,
+                });
+                continue;
+            }
+            Block::CodeFence(code) => code,
+            Block::Paragraph(paragraph) => paragraph,
+        };
+
+        let pieces = if measure(&block) > max_len {
+            split_oversized(&block, max_len, &measure)
+        } else {
+            vec![block]
+        };
+
+        for piece in pieces {
+            let candidate = if current.is_empty() {
+                piece.clone()
+            } else {
+                format!("{}\n\n{}", current, piece)
+            };
+
+            if measure(&candidate) <= max_len {
+                current = candidate;
+            } else {
+                flush_text(&mut chunks, &mut current);
+                current = piece;
+            }
+        }
+    }
+    flush_text(&mut chunks, &mut current);
+
+    if chunks.is_empty() {
+        chunks.push(MessageChunk::Text(text.to_string()));
+    }
+
+    chunks
+}
+
+/// 将当前累积的文本作为一个片段输出
+fn flush_text(chunks: &mut Vec<MessageChunk>, current: &mut String) {
+    if !current.trim().is_empty() {
+        chunks.push(MessageChunk::Text(std::mem::take(current)));
+    }
+    current.clear();
+}
+
+/// 解析为段落（空行分隔）和代码块（``` 或 ~~~ 围栏，内部空行不拆分）
+fn parse_blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut fence: Option<(&str, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        if let Some((marker, mut code_lines)) = fence.take() {
+            code_lines.push(line);
+            if line.trim_start().starts_with(marker) && line.trim().trim_start_matches(marker).is_empty() {
+                blocks.push(Block::CodeFence(code_lines.join("\n")));
+            } else {
+                fence = Some((marker, code_lines));
+            }
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            if !paragraph.is_empty() {
+                blocks.push(Block::Paragraph(paragraph.join("\n")));
+                paragraph.clear();
+            }
+            fence = Some((marker, vec![line]));
+        } else if line.trim().is_empty() {
+            if !paragraph.is_empty() {
+                blocks.push(Block::Paragraph(paragraph.join("\n")));
+                paragraph.clear();
+            }
+        } else {
+            paragraph.push(line);
+        }
+    }
+
+    // 未闭合的代码块按原样保留
+    if let Some((_, code_lines)) = fence {
+        blocks.push(Block::CodeFence(code_lines.join("\n")));
+    }
+    if !paragraph.is_empty() {
+        blocks.push(Block::Paragraph(paragraph.join("\n")));
+    }
+
+    blocks
+}
+
+/// 拆分单个超长的段落：优先按行，单行仍超长时按字符截断
+fn split_oversized(block: &str, max_len: usize, measure: &impl Fn(&str) -> usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+
+    for line in block.lines() {
+        let candidate = if current.is_empty() {
+            line.to_string()
+        } else {
+            format!("{}\n{}", current, line)
+        };
+
+        if measure(&candidate) <= max_len {
+            current = candidate;
+            continue;
+        }
+
+        if !current.is_empty() {
+            pieces.push(std::mem::take(&mut current));
+        }
+
+        if measure(line) <= max_len {
+            current = line.to_string();
+        } else {
+            pieces.extend(split_by_chars(line, max_len, measure));
+        }
+    }
+
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    pieces
+}
+
+/// 按字符拆分单行，保证每段渲染后不超过 `max_len`
+fn split_by_chars(line: &str, max_len: usize, measure: &impl Fn(&str) -> usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut rest: Vec<char> = line.chars().collect();
+
+    while !rest.is_empty() {
+        let mut take = rest.len().min(max_len).max(1);
+        loop {
+            let piece: String = rest[..take].iter().collect();
+            if take == 1 || measure(&piece) <= max_len {
+                pieces.push(piece);
+                break;
+            }
+            take -= (take / 4).max(1);
+        }
+        rest.drain(..take);
+    }
+
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn char_count(text: &str) -> usize {
+        text.chars().count()
+    }
+
+    #[test]
+    fn test_split_at_paragraph_boundaries() {
+        let text = format!("{}\n\n{}\n\n{}", "a".repeat(30), "b".repeat(30), "c".repeat(30));
+        let chunks = split_message(&text, 70, char_count);
+
+        assert_eq!(
+            chunks,
+            vec![
+                MessageChunk::Text(format!("{}\n\n{}", "a".repeat(30), "b".repeat(30))),
+                MessageChunk::Text("c".repeat(30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_code_fence_is_kept_whole() {
+        let code = "```rust\nfn main() {}\n\nfn other() {}\n```";
+        let text = format!("说明\n\n{}", code);
+        let chunks = split_message(&text, 40, char_count);
+
+        assert_eq!(chunks.last(), Some(&MessageChunk::Text(code.to_string())));
+    }
+
+    #[test]
+    fn test_large_code_block_becomes_document() {
+        let body = "x\n".repeat(telegram_constants::LARGE_CODE_BLOCK_CHARS);
+        let text = format!("前言\n\n```\n{}```\n\n结尾", body);
+        let chunks = split_message(&text, telegram_constants::MAX_MESSAGE_LENGTH, char_count);
+
+        assert_eq!(chunks.len(), 3);
+        assert!(matches!(&chunks[1], MessageChunk::Document { file_name, .. } if file_name == "code_1.md"));
+        assert_eq!(chunks[2], MessageChunk::Text("结尾".to_string()));
+    }
+
+    #[test]
+    fn test_code_block_longer_than_message_after_escaping_becomes_document() {
+        // 转义后长度翻倍的代码块，原始长度不超过阈值但渲染后放不进一条消息
+        let code = format!("```\n{}\n```", "<&>\n".repeat(20));
+        let text = format!("前言\n\n{}", code);
+        let chunks = split_message(&text, 100, |text| char_count(text) * 2);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1], MessageChunk::Document { file_name: "code_1.md".to_string(), content: code });
+    }
+
+    #[test]
+    fn test_oversized_line_is_split_by_chars() {
+        let text = "长".repeat(25);
+        let chunks = split_message(&text, 10, char_count);
+
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks
+            .iter()
+            .all(|chunk| matches!(chunk, MessageChunk::Text(t) if char_count(t) <= 10)));
+    }
+}
@@ -1,14 +1,19 @@
+pub mod bot_commands;
 pub mod commands;
 pub mod core;
 pub mod integration;
 pub mod markdown;
 pub mod mcp_handler;
+pub mod message_split;
+pub mod offset_store;
 
+pub use bot_commands::{handle_bot_command, register_bot_commands};
 pub use commands::*;
 pub use core::{
-    handle_callback_query, handle_text_message, test_telegram_connection, TelegramCore,
-    TelegramEvent,
+    handle_callback_query, handle_text_message, test_telegram_connection, CallbackAction,
+    TelegramCore, TelegramEvent,
 };
 pub use integration::TelegramIntegration;
 pub use markdown::process_telegram_markdown;
 pub use mcp_handler::handle_telegram_only_mcp_request;
+pub use offset_store::UpdateOffsetStore;
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use fs2::FileExt;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::path::PathBuf;
+
+use crate::config::get_config_dir;
+
+/// 偏移量文件名（位于配置目录）
+const OFFSET_FILE_NAME: &str = "telegram_offsets.json";
+
+/// 偏移量锁文件名，多个 `--mcp-request` 进程通过它串行读写
+const LOCK_FILE_NAME: &str = "telegram_offsets.lock";
+
+/// Telegram 更新偏移量存储
+///
+/// 按Bot记录最后一个已确认的 update id，新进程从该位置继续拉取，
+/// 避免重新消费上一个请求遗留的更新（例如旧的“↗️发送”）
+pub struct UpdateOffsetStore {
+    dir: PathBuf,
+    bot_key: String,
+}
+
+impl UpdateOffsetStore {
+    /// 为指定Bot创建偏移量存储
+    pub fn for_bot(bot_token: &str) -> Result<Self> {
+        Ok(Self {
+            dir: get_config_dir()?,
+            bot_key: bot_key(bot_token),
+        })
+    }
+
+    /// 下一次拉取应使用的offset（从未记录时返回0）
+    pub fn next_offset(&self) -> Result<i32> {
+        self.with_lock(|offsets| {
+            Ok(offsets
+                .get(&self.bot_key)
+                .map(|last_id| *last_id as i32 + 1)
+                .unwrap_or(0))
+        })
+    }
+
+    /// 记录已确认的 update id（只会前进，不会回退）
+    pub fn acknowledge(&self, update_id: i32) -> Result<()> {
+        let update_id = update_id as i64;
+        self.with_lock(|offsets| {
+            let last_id = offsets.entry(self.bot_key.clone()).or_insert(update_id);
+            if *last_id < update_id {
+                *last_id = update_id;
+            }
+            self.write_offsets(offsets)
+        })
+    }
+
+    /// 在文件锁保护下读取偏移量并执行操作
+    fn with_lock<T>(&self, f: impl FnOnce(&mut HashMap<String, i64>) -> Result<T>) -> Result<T> {
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join(LOCK_FILE_NAME))?;
+        lock_file.lock_exclusive()?;
+
+        let mut offsets = self.read_offsets();
+        let result = f(&mut offsets);
+
+        lock_file.unlock()?;
+        result
+    }
+
+    /// 读取偏移量文件，文件不存在或损坏时视为空
+    fn read_offsets(&self) -> HashMap<String, i64> {
+        fs::read_to_string(self.dir.join(OFFSET_FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// 写入偏移量文件（先写临时文件再重命名，避免写入中断导致文件损坏）
+    fn write_offsets(&self, offsets: &HashMap<String, i64>) -> Result<()> {
+        let path = self.dir.join(OFFSET_FILE_NAME);
+        let temp_path = self.dir.join(format!("{}.tmp", OFFSET_FILE_NAME));
+        fs::write(&temp_path, serde_json::to_string_pretty(offsets)?)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+}
+
+/// 使用Token中的Bot ID作为键，避免把完整Token写入偏移量文件
+fn bot_key(bot_token: &str) -> String {
+    bot_token
+        .split(':')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    fn store(dir: &std::path::Path, bot_token: &str) -> UpdateOffsetStore {
+        UpdateOffsetStore {
+            dir: dir.to_path_buf(),
+            bot_key: bot_key(bot_token),
+        }
+    }
+
+    #[test]
+    fn test_offsets_only_move_forward_per_bot() {
+        let dir = test_dir("offsets");
+
+        let first = store(&dir, "111:SECRET_A");
+        let second = store(&dir, "222:SECRET_B");
+        assert_eq!(first.next_offset().unwrap(), 0);
+
+        first.acknowledge(10).unwrap();
+        second.acknowledge(5).unwrap();
+        // 较小的 update id 不会覆盖已确认的较大值
+        first.acknowledge(7).unwrap();
+        assert_eq!(first.next_offset().unwrap(), 11);
+        assert_eq!(second.next_offset().unwrap(), 6);
+
+        // 同一Bot的不同Token共享偏移量，文件中不保存Token
+        assert_eq!(store(&dir, "111:ROTATED").next_offset().unwrap(), 11);
+        let content = fs::read_to_string(dir.join(OFFSET_FILE_NAME)).unwrap();
+        assert!(!content.contains("SECRET"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -6,8 +6,9 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use tauri::{AppHandle, State, Manager};
 use rodio::{Decoder, OutputStream, Sink};
 
-use crate::config::{AppState, save_config};
+use crate::config::{AppState, update_config};
 use crate::log_important;
+use crate::utils::create_http_client;
 use super::audio_assets::{get_audio_asset_manager, AudioSource};
 
 // 音频播放控制器 - 只存储控制信号，不存储音频流
@@ -30,13 +31,11 @@ pub async fn set_audio_notification_enabled(enabled: bool, state: State<'_, AppS
         }
     }
 
-    {
-        let mut config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.audio_config.notification_enabled = enabled;
-    }
-
-    // 保存配置到文件
-    save_config(&state, &app).await.map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
     Ok(())
 }
 
@@ -48,13 +47,11 @@ pub async fn get_audio_url(state: State<'_, AppState>) -> Result<String, String>
 
 #[tauri::command]
 pub async fn set_audio_url(url: String, state: State<'_, AppState>, app: tauri::AppHandle) -> Result<(), String> {
-    {
-        let mut config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.audio_config.custom_url = url;
-    }
-
-    // 保存配置到文件
-    save_config(&state, &app).await.map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
     Ok(())
 }
 
@@ -151,7 +148,11 @@ pub async fn play_audio_file(app: &AppHandle, audio_url: &str) -> Result<()> {
 
 async fn play_audio_from_url(app: &AppHandle, url: &str) -> Result<()> {
     // 下载音频文件到临时目录
-    let response = reqwest::get(url).await
+    let response = create_http_client()?
+        .get(url)
+        .timeout(std::time::Duration::from_millis(crate::constants::network::DOWNLOAD_TIMEOUT_MS))
+        .send()
+        .await
         .map_err(|e| anyhow::anyhow!("下载音频文件失败: {}", e))?;
 
     if !response.status().is_success() {
@@ -1,4 +1,4 @@
-use crate::config::{save_config, load_config, AppState, ReplyConfig, WindowConfig, CustomPrompt, CustomPromptConfig, ShortcutConfig, ShortcutBinding};
+use crate::config::{update_config, load_config, check_standalone_config, AppState, ConfigDiagnostic, NetworkConfig, ReplyConfig, WindowConfig, CustomPrompt, CustomPromptConfig, ShortcutConfig, ShortcutBinding};
 use crate::constants::{window, ui, validation};
 use crate::mcp::types::{build_continue_response, build_send_response, ImageAttachment, PopupRequest};
 use crate::mcp::handlers::create_tauri_popup;
@@ -24,18 +24,11 @@ pub async fn set_always_on_top(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.ui_config.always_on_top = enabled;
-    }
-
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     // 应用到当前窗口
     if let Some(window) = app.get_webview_window("main") {
@@ -73,6 +66,12 @@ pub async fn sync_window_state(
     Ok(())
 }
 
+/// 检查配置文件，返回发现的问题
+#[tauri::command]
+pub async fn get_config_diagnostics() -> Result<Vec<ConfigDiagnostic>, String> {
+    check_standalone_config().map_err(|e| format!("检查配置失败: {}", e))
+}
+
 /// 重新加载配置文件到内存
 #[tauri::command]
 pub async fn reload_config(
@@ -107,18 +106,11 @@ pub async fn set_theme(
         return Err("无效的主题值，只支持 light、dark".to_string());
     }
 
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.ui_config.theme = theme;
-    }
-
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -138,18 +130,11 @@ pub async fn set_window_config(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.ui_config.window_config = window_config;
-    }
-
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -169,18 +154,38 @@ pub async fn set_reply_config(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.reply_config = reply_config;
-    }
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_network_config(state: State<'_, AppState>) -> Result<NetworkConfig, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+    Ok(config.network_config.clone())
+}
+
+#[tauri::command]
+pub async fn set_network_config(
+    network_config: NetworkConfig,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    // 先验证代理地址和CA证书可用，避免保存后所有网络请求失败
+    crate::utils::build_http_client(&network_config).map_err(|e| e.to_string())?;
+
+    update_config(&state, &app, |config| {
+        config.network_config = network_config;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -303,12 +308,7 @@ pub async fn set_window_settings(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
-
+    update_config(&state, &app, |config| {
         // 更新窗口配置
         if let Some(fixed) = window_settings.get("fixed").and_then(|v| v.as_bool()) {
             config.ui_config.window_config.fixed = fixed;
@@ -358,12 +358,9 @@ pub async fn set_window_settings(
                     .update_current_size(width, height);
             }
         }
-    }
-
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -382,10 +379,7 @@ pub async fn send_mcp_response(
     }
 
     // 检查是否为MCP模式
-    let args: Vec<String> = std::env::args().collect();
-    let is_mcp_mode = args.len() >= 3 && args[1] == "--mcp-request";
-
-    if is_mcp_mode {
+    if crate::config::request_file().is_some() {
         // MCP模式：直接输出到stdout（MCP协议要求）
         println!("{}", response_str);
         std::io::Write::flush(&mut std::io::stdout())
@@ -410,14 +404,13 @@ pub async fn send_mcp_response(
 
 #[tauri::command]
 pub fn get_cli_args() -> Result<serde_json::Value, String> {
-    let args: Vec<String> = std::env::args().collect();
     let mut result = serde_json::Map::new();
 
     // 检查是否有 --mcp-request 参数
-    if args.len() >= 3 && args[1] == "--mcp-request" {
+    if let Some(request_file) = crate::config::request_file() {
         result.insert(
             "mcp_request".to_string(),
-            serde_json::Value::String(args[2].clone()),
+            serde_json::Value::String(request_file.to_string_lossy().to_string()),
         );
     }
 
@@ -555,12 +548,7 @@ pub async fn add_custom_prompt(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
-
+    update_config(&state, &app, |config| {
         // 检查是否超过最大数量限制
         if config.custom_prompt_config.prompts.len() >= config.custom_prompt_config.max_prompts as usize {
             return Err(format!("自定义prompt数量已达到上限: {}", config.custom_prompt_config.max_prompts));
@@ -572,12 +560,11 @@ pub async fn add_custom_prompt(
         }
 
         config.custom_prompt_config.prompts.push(prompt);
-    }
 
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))??;
 
     Ok(())
 }
@@ -589,24 +576,18 @@ pub async fn update_custom_prompt(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
-
+    update_config(&state, &app, |config| {
         // 查找并更新prompt
         if let Some(existing_prompt) = config.custom_prompt_config.prompts.iter_mut().find(|p| p.id == prompt.id) {
             *existing_prompt = prompt;
         } else {
             return Err("未找到指定的prompt".to_string());
         }
-    }
 
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))??;
 
     Ok(())
 }
@@ -618,12 +599,7 @@ pub async fn delete_custom_prompt(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
-
+    update_config(&state, &app, |config| {
         // 查找并删除prompt
         let initial_len = config.custom_prompt_config.prompts.len();
         config.custom_prompt_config.prompts.retain(|p| p.id != prompt_id);
@@ -631,12 +607,11 @@ pub async fn delete_custom_prompt(
         if config.custom_prompt_config.prompts.len() == initial_len {
             return Err("未找到指定的prompt".to_string());
         }
-    }
 
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))??;
 
     Ok(())
 }
@@ -648,18 +623,11 @@ pub async fn set_custom_prompt_enabled(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.custom_prompt_config.enabled = enabled;
-    }
-
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -673,12 +641,9 @@ pub async fn update_custom_prompt_order(
 ) -> Result<(), String> {
     log::debug!("开始更新prompt排序，接收到的IDs: {:?}", prompt_ids);
 
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    let save_start = std::time::Instant::now();
 
+    update_config(&state, &app, |config| {
         log::debug!("更新前的prompt顺序:");
         for prompt in &config.custom_prompt_config.prompts {
             log::debug!("  {} (sort_order: {})", prompt.name, prompt.sort_order);
@@ -701,15 +666,9 @@ pub async fn update_custom_prompt_order(
         for prompt in &config.custom_prompt_config.prompts {
             log::debug!("  {} (sort_order: {})", prompt.name, prompt.sort_order);
         }
-    }
-
-    log::debug!("开始保存配置文件...");
-    let save_start = std::time::Instant::now();
-
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     let save_duration = save_start.elapsed();
     log::debug!("配置保存完成，耗时: {:?}", save_duration);
@@ -725,12 +684,7 @@ pub async fn update_conditional_prompt_state(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
-
+    update_config(&state, &app, |config| {
         // 查找并更新指定prompt的current_state
         if let Some(prompt) = config.custom_prompt_config.prompts.iter_mut().find(|p| p.id == prompt_id) {
             prompt.current_state = new_state;
@@ -738,12 +692,11 @@ pub async fn update_conditional_prompt_state(
         } else {
             return Err(format!("未找到ID为 {} 的prompt", prompt_id));
         }
-    }
 
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))??;
 
     Ok(())
 }
@@ -851,20 +804,12 @@ pub async fn update_shortcut_binding(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
-
+    update_config(&state, &app, |config| {
         // 更新指定的快捷键绑定
         config.shortcut_config.shortcuts.insert(shortcut_id, binding);
-    }
-
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -877,18 +822,11 @@ pub async fn reset_shortcuts_to_default(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.shortcut_config = crate::config::default_shortcut_config();
-    }
-
-    // 保存配置到文件
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
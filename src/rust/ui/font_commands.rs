@@ -1,4 +1,4 @@
-use crate::config::{save_config, AppState, FontConfig};
+use crate::config::{update_config, AppState, FontConfig};
 use crate::constants::font;
 use tauri::{AppHandle, State};
 
@@ -48,17 +48,11 @@ pub async fn set_font_family(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.ui_config.font_config.font_family = font_family;
-    }
-
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -69,17 +63,11 @@ pub async fn set_font_size(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.ui_config.font_config.font_size = font_size;
-    }
-
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -92,17 +80,11 @@ pub async fn set_custom_font_family(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.ui_config.font_config.custom_font_family = custom_font_family;
-    }
-
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
@@ -140,21 +122,15 @@ pub async fn reset_font_config(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut config = state
-            .config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
+    update_config(&state, &app, |config| {
         config.ui_config.font_config = FontConfig {
             font_family: font::DEFAULT_FONT_FAMILY.to_string(),
             font_size: font::DEFAULT_FONT_SIZE.to_string(),
             custom_font_family: font::DEFAULT_CUSTOM_FONT_FAMILY.to_string(),
         };
-    }
-
-    save_config(&state, &app)
-        .await
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     Ok(())
 }
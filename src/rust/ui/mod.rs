@@ -3,6 +3,8 @@ pub mod window;
 pub mod audio;
 pub mod audio_assets;
 pub mod font_commands;
+pub mod profile_commands;
+pub mod tray;
 pub mod updater;
 pub mod exit;
 pub mod window_events;
@@ -12,6 +14,7 @@ pub use commands::*;
 pub use window::*;
 pub use audio::*;
 pub use audio_assets::*;
+pub use tray::*;
 pub use updater::*;
 pub use exit::*;
 pub use window_events::*;
@@ -0,0 +1,86 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::config::{
+    apply_profile, delete_profile, list_profiles, load_profile, save_current_profile, update_config,
+    update_file_config, AppState,
+};
+use crate::log_important;
+
+/// 配置方案列表及当前方案
+#[derive(Debug, Serialize)]
+pub struct ConfigProfilesInfo {
+    pub profiles: Vec<String>,
+    pub active: Option<String>,
+}
+
+/// 获取配置方案列表
+#[tauri::command]
+pub async fn get_config_profiles(state: State<'_, AppState>) -> Result<ConfigProfilesInfo, String> {
+    let profiles = list_profiles().map_err(|e| format!("读取配置方案失败: {}", e))?;
+    let active = state
+        .config
+        .lock()
+        .map_err(|e| format!("获取配置失败: {}", e))?
+        .active_profile
+        .clone();
+
+    Ok(ConfigProfilesInfo { profiles, active })
+}
+
+/// 把当前的回复、音频、Telegram 和 MCP 工具设置保存为配置方案
+#[tauri::command]
+pub async fn save_config_profile(name: String, app: AppHandle) -> Result<(), String> {
+    save_current_profile(&name).map_err(|e| format!("保存配置方案失败: {}", e))?;
+
+    crate::ui::tray::refresh_tray_menu(&app);
+    Ok(())
+}
+
+/// 删除配置方案
+#[tauri::command]
+pub async fn delete_config_profile(
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    delete_profile(&name).map_err(|e| format!("删除配置方案失败: {}", e))?;
+
+    // 删除的是当前方案时清除记录
+    update_config(&state, &app, |config| {
+        if config.active_profile.as_deref() == Some(name.as_str()) {
+            config.active_profile = None;
+        }
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    crate::ui::tray::refresh_tray_menu(&app);
+    Ok(())
+}
+
+/// 切换到指定的配置方案
+#[tauri::command]
+pub async fn switch_config_profile(name: String, app: AppHandle) -> Result<(), String> {
+    switch_profile(&app, &name).await
+}
+
+/// 切换配置方案并通知界面和托盘菜单刷新
+///
+/// 配置写入文件后，运行中的 MCP 服务器会在下次读取配置时使用新的设置
+pub async fn switch_profile(app: &AppHandle, name: &str) -> Result<(), String> {
+    let profile = load_profile(name).map_err(|e| e.to_string())?;
+
+    let state = app.state::<AppState>();
+    update_file_config(&state, app, |config| apply_profile(config, name, &profile))
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    log_important!(info, "已切换到配置方案: {}", name);
+
+    crate::ui::tray::refresh_tray_menu(app);
+    if let Err(e) = app.emit("config_reloaded", ()) {
+        log_important!(warn, "发送配置重载事件失败: {}", e);
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use tauri::menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::config::{list_profiles, AppState};
+use crate::log_important;
+
+/// 托盘图标ID
+const TRAY_ID: &str = "main";
+
+/// 配置方案菜单项ID前缀，后接方案名称
+const PROFILE_ITEM_PREFIX: &str = "profile:";
+
+const SHOW_ITEM_ID: &str = "show";
+const QUIT_ITEM_ID: &str = "quit";
+
+/// 创建托盘图标，菜单中可以快速切换配置方案
+pub fn setup_tray(app: &AppHandle) -> tauri::Result<()> {
+    let menu = build_tray_menu(app)?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(crate::constants::app::NAME)
+        .menu(&menu)
+        .on_menu_event(|app, event| handle_menu_event(app, event.id().as_ref()));
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    Ok(())
+}
+
+/// 配置方案变化后重建托盘菜单
+pub fn refresh_tray_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    if let Err(e) = build_tray_menu(app).and_then(|menu| tray.set_menu(Some(menu))) {
+        log_important!(warn, "刷新托盘菜单失败: {}", e);
+    }
+}
+
+fn build_tray_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let profiles = list_profiles().unwrap_or_else(|e| {
+        log_important!(warn, "读取配置方案失败: {}", e);
+        Vec::new()
+    });
+    let active_profile = app
+        .state::<AppState>()
+        .config
+        .lock()
+        .ok()
+        .and_then(|config| config.active_profile.clone());
+
+    let profile_items = profiles
+        .iter()
+        .map(|name| {
+            CheckMenuItem::with_id(
+                app,
+                format!("{}{}", PROFILE_ITEM_PREFIX, name),
+                name,
+                true,
+                active_profile.as_deref() == Some(name.as_str()),
+                None::<&str>,
+            )
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let empty_item = MenuItem::with_id(app, "profile-empty", "暂无配置方案", false, None::<&str>)?;
+
+    let mut submenu_items: Vec<&dyn IsMenuItem<Wry>> = profile_items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<Wry>)
+        .collect();
+    if submenu_items.is_empty() {
+        submenu_items.push(&empty_item);
+    }
+    let profiles_menu = Submenu::with_items(app, "配置方案", true, &submenu_items)?;
+
+    let separator = PredefinedMenuItem::separator(app)?;
+    let show_item = MenuItem::with_id(app, SHOW_ITEM_ID, "打开设置", true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, QUIT_ITEM_ID, "退出", true, None::<&str>)?;
+
+    Menu::with_items(app, &[&profiles_menu, &separator, &show_item, &quit_item])
+}
+
+fn handle_menu_event(app: &AppHandle, id: &str) {
+    if let Some(name) = id.strip_prefix(PROFILE_ITEM_PREFIX) {
+        let app = app.clone();
+        let name = name.to_string();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = crate::ui::profile_commands::switch_profile(&app, &name).await {
+                log_important!(warn, "切换配置方案失败: {}", e);
+                // 恢复菜单的勾选状态
+                refresh_tray_menu(&app);
+            }
+        });
+        return;
+    }
+
+    match id {
+        SHOW_ITEM_ID => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+        }
+        QUIT_ITEM_ID => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = crate::ui::exit::force_exit_app(app).await {
+                    log_important!(error, "退出应用失败: {}", e);
+                }
+            });
+        }
+        _ => {}
+    }
+}
@@ -2,6 +2,9 @@ use tauri::{AppHandle, Emitter};
 use serde::{Deserialize, Serialize};
 use std::{fs, io::Write, path::PathBuf, process::Command};
 
+use crate::constants::network;
+use crate::utils::create_http_client;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UpdateInfo {
     pub available: bool,
@@ -25,14 +28,14 @@ pub async fn check_for_updates(app: AppHandle) -> Result<UpdateInfo, String> {
     log::info!("🔍 开始检查更新");
     
     // 由于Tauri更新器无法处理中文tag，这里直接使用GitHub API检查
-    let client = reqwest::Client::new();
+    let client = create_http_client().map_err(|e| e.to_string())?;
     log::info!("📡 发送 GitHub API 请求");
     
     let response = client
         .get("https://api.github.com/repos/imhuso/cunzhi/releases/latest")
         .header("User-Agent", "cunzhi-app/1.0")
         .header("Accept", "application/vnd.github.v3+json")
-        .timeout(std::time::Duration::from_secs(30))
+        .timeout(std::time::Duration::from_millis(network::DEFAULT_TIMEOUT_MS))
         .send()
         .await
         .map_err(|e| {
@@ -278,9 +281,10 @@ async fn download_and_install_update_impl(app: &AppHandle, update_info: &UpdateI
     let file_path = temp_dir.join(&file_name);
 
     // 下载文件
-    let client = reqwest::Client::new();
+    let client = create_http_client().map_err(|e| e.to_string())?;
     let mut response = client
         .get(&update_info.download_url)
+        .timeout(std::time::Duration::from_millis(network::DOWNLOAD_TIMEOUT_MS))
         .send()
         .await
         .map_err(|e| format!("下载请求失败: {}", e))?;
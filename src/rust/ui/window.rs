@@ -1,5 +1,5 @@
 use tauri::{State, Manager};
-use crate::config::{AppState, save_config};
+use crate::config::{AppState, update_config};
 use crate::constants::window;
 use serde::{Deserialize, Serialize};
 
@@ -55,9 +55,7 @@ pub async fn apply_window_constraints(state: State<'_, AppState>, app: tauri::Ap
 #[tauri::command]
 pub async fn update_window_size(size_update: WindowSizeUpdate, state: State<'_, AppState>, app: tauri::AppHandle) -> Result<(), String> {
     // 更新配置
-    {
-        let mut config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
-
+    update_config(&state, &app, |config| {
         // 更新模式设置
         config.ui_config.window_config.fixed = size_update.fixed;
 
@@ -79,10 +77,9 @@ pub async fn update_window_size(size_update: WindowSizeUpdate, state: State<'_,
             config.ui_config.window_config.max_height = window::MAX_HEIGHT;
             config.ui_config.window_config.auto_resize = window::DEFAULT_AUTO_RESIZE;
         }
-    }
-
-    // 保存配置
-    save_config(&state, &app).await.map_err(|e| format!("保存配置失败: {}", e))?;
+    })
+    .await
+    .map_err(|e| format!("保存配置失败: {}", e))?;
 
     // 获取置顶状态
     let always_on_top = {
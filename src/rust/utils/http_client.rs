@@ -0,0 +1,138 @@
+use anyhow::Result;
+use reqwest::{Certificate, Client, ClientBuilder, NoProxy, Proxy};
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::config::{load_standalone_network_config, NetworkConfig};
+use crate::constants::network as network_constants;
+
+/// 按当前进程的网络配置创建HTTP客户端
+///
+/// 所有出站请求（Telegram、acemcp、更新检查、音频下载）都应通过它创建客户端。
+/// 网络配置只在首次使用时读取，读取失败时退回默认配置
+pub fn create_http_client() -> Result<Client> {
+    build_http_client(&current_network_config())
+}
+
+/// 根据网络配置创建HTTP客户端
+pub fn build_http_client(network_config: &NetworkConfig) -> Result<Client> {
+    http_client_builder(network_config)?
+        .build()
+        .map_err(|e| anyhow::anyhow!("创建HTTP客户端失败: {}", e))
+}
+
+/// 按当前进程的网络配置创建客户端构建器，供需要额外设置（如整体超时）的调用方使用
+pub fn create_http_client_builder() -> Result<ClientBuilder> {
+    http_client_builder(&current_network_config())
+}
+
+/// 应用代理、CA证书和连接超时后的客户端构建器
+///
+/// 不设置整体超时：索引上传等请求可能持续较长时间，需要短超时的调用方按请求设置
+pub fn http_client_builder(network_config: &NetworkConfig) -> Result<ClientBuilder> {
+    let defaults = network_constants::get_default_network_config();
+
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_millis(defaults.connection_timeout_ms))
+        .user_agent(defaults.user_agent);
+
+    if let Some(proxy) = build_proxy(network_config)? {
+        builder = builder.proxy(proxy);
+    }
+
+    for certificate in load_ca_bundle(&network_config.ca_bundle_path)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+
+    Ok(builder)
+}
+
+/// 更新当前进程使用的网络配置，之后创建的客户端使用新配置
+pub fn set_current_network_config(network_config: &NetworkConfig) {
+    if let Ok(mut cached) = cached_network_config().lock() {
+        *cached = Some(network_config.clone());
+    }
+}
+
+/// 当前进程使用的网络配置，首次调用时从配置文件读取
+fn current_network_config() -> NetworkConfig {
+    let Ok(mut cached) = cached_network_config().lock() else {
+        return crate::config::default_network_config();
+    };
+
+    cached
+        .get_or_insert_with(|| {
+            load_standalone_network_config().unwrap_or_else(|e| {
+                log::warn!("读取网络配置失败，使用默认配置: {}", e);
+                crate::config::default_network_config()
+            })
+        })
+        .clone()
+}
+
+fn cached_network_config() -> &'static Mutex<Option<NetworkConfig>> {
+    static NETWORK_CONFIG: OnceLock<Mutex<Option<NetworkConfig>>> = OnceLock::new();
+    NETWORK_CONFIG.get_or_init(|| Mutex::new(None))
+}
+
+/// 解析代理配置，未配置代理地址时返回 `None`（reqwest 仍会读取系统代理环境变量）
+fn build_proxy(network_config: &NetworkConfig) -> Result<Option<Proxy>> {
+    let proxy_url = network_config.proxy_url.trim();
+    if proxy_url.is_empty() {
+        return Ok(None);
+    }
+
+    let proxy = Proxy::all(proxy_url)
+        .map_err(|e| anyhow::anyhow!("无效的代理地址 {}: {}", proxy_url, e))?;
+
+    let no_proxy = network_config
+        .no_proxy
+        .iter()
+        .map(|host| host.trim())
+        .filter(|host| !host.is_empty())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    Ok(Some(proxy.no_proxy(NoProxy::from_string(&no_proxy))))
+}
+
+/// 读取PEM格式的CA证书包，路径为空时返回空列表
+fn load_ca_bundle(ca_bundle_path: &str) -> Result<Vec<Certificate>> {
+    let ca_bundle_path = ca_bundle_path.trim();
+    if ca_bundle_path.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let pem = fs::read(ca_bundle_path)
+        .map_err(|e| anyhow::anyhow!("读取CA证书文件失败 {}: {}", ca_bundle_path, e))?;
+    let certificates = Certificate::from_pem_bundle(&pem)
+        .map_err(|e| anyhow::anyhow!("解析CA证书文件失败 {}: {}", ca_bundle_path, e))?;
+
+    if certificates.is_empty() {
+        return Err(anyhow::anyhow!("CA证书文件中没有证书: {}", ca_bundle_path));
+    }
+
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_http_client_with_proxy() {
+        let mut network_config = crate::config::default_network_config();
+        assert!(build_http_client(&network_config).is_ok());
+
+        network_config.proxy_url = "socks5://127.0.0.1:1080".to_string();
+        assert!(build_http_client(&network_config).is_ok());
+
+        network_config.proxy_url = "ftp://127.0.0.1:21".to_string();
+        assert!(build_http_client(&network_config).is_err());
+
+        network_config.proxy_url = String::new();
+        network_config.ca_bundle_path = "/nonexistent/ca.pem".to_string();
+        assert!(build_http_client(&network_config).is_err());
+    }
+}
@@ -112,19 +112,23 @@ pub fn init_logger(config: LogConfig) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
+/// MCP 模式的日志文件路径，可以通过 `MCP_LOG_FILE` 环境变量指定
+pub fn mcp_log_file_path() -> String {
+    env::var("MCP_LOG_FILE").unwrap_or_else(|_| {
+        let temp_dir = env::temp_dir();
+        temp_dir.join("cunzhi-mcp.log").to_string_lossy().to_string()
+    })
+}
+
 /// 自动检测模式并初始化日志系统
 pub fn auto_init_logger() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
-    let is_mcp_mode = args.len() >= 3 && args[1] == "--mcp-request";
+    // MCP 请求弹窗进程（命令行带 `--mcp-request`）
+    let is_mcp_mode = crate::config::request_file().is_some();
     
     let config = if is_mcp_mode {
         // MCP 模式：输出到文件
-        let log_file_path = env::var("MCP_LOG_FILE")
-            .unwrap_or_else(|_| {
-                let temp_dir = env::temp_dir();
-                temp_dir.join("cunzhi-mcp.log").to_string_lossy().to_string()
-            });
-            
+        let log_file_path = mcp_log_file_path();
+
         LogConfig {
             level: env::var("RUST_LOG")
                 .unwrap_or_else(|_| "warn".to_string())
@@ -1,3 +1,14 @@
+pub mod http_client;
 pub mod logger;
 
-pub use logger::{LogConfig, init_logger, auto_init_logger};
+pub use http_client::{build_http_client, create_http_client, create_http_client_builder, set_current_network_config};
+pub use logger::{LogConfig, init_logger, auto_init_logger, mcp_log_file_path};
+
+/// 为测试创建空的临时目录，目录名包含进程ID，避免与同时运行的其他测试进程冲突
+#[cfg(test)]
+pub fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("cunzhi_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}